@@ -15,8 +15,12 @@ async fn main() -> Result<()> {
 
     // TODO: handle errors
     let pbs_config = load_pbs_config().expect("failed to load pbs config");
-    let _guard = initialize_tracing_log(PBS_MODULE_NAME);
+    let guard = initialize_tracing_log(PBS_MODULE_NAME);
     let state = PbsState::<()>::new(pbs_config);
     PbsService::init_metrics()?;
-    PbsService::run::<(), DefaultBuilderApi>(state).await
+    let res = PbsService::run::<(), DefaultBuilderApi>(state).await;
+
+    // flush the buffered file logs before exiting
+    drop(guard);
+    res
 }
@@ -14,6 +14,10 @@ async fn main() -> Result<()> {
     }
 
     let config = StartSignerConfig::load_from_env()?;
-    let _guard = initialize_tracing_log(SIGNER_MODULE_NAME);
-    SigningService::run(config).await
+    let guard = initialize_tracing_log(SIGNER_MODULE_NAME);
+    let res = SigningService::run(config).await;
+
+    // flush the buffered file logs before exiting
+    drop(guard);
+    res
 }
@@ -0,0 +1,21 @@
+use std::path::Path;
+
+use alloy::rpc::types::beacon::BlsPublicKey;
+use cb_common::pbs::{read_bid_archive, BidArchiveQuery};
+use eyre::Result;
+
+/// Prints the archived bids matching the query, one JSON record per line
+pub fn handle_bids_query(
+    dir_path: String,
+    slot: Option<u64>,
+    validator_pubkey: Option<BlsPublicKey>,
+) -> Result<()> {
+    let records = read_bid_archive(Path::new(&dir_path))?;
+    let query = BidArchiveQuery { slot, validator_pubkey };
+
+    for record in query.filter(records) {
+        println!("{}", serde_json::to_string(&record)?);
+    }
+
+    Ok(())
+}
@@ -1,20 +1,21 @@
-use std::{path::Path, vec};
+use std::{collections::BTreeSet, path::Path, vec};
 
 use cb_common::{
     config::{
-        CommitBoostConfig, ModuleKind, BUILDER_SERVER_ENV, CB_BASE_LOG_PATH, CB_CONFIG_ENV,
-        CB_CONFIG_NAME, JWTS_ENV, METRICS_SERVER_ENV, MODULE_ID_ENV, MODULE_JWT_ENV,
+        CommitBoostConfig, ModuleKind, BID_ARCHIVE_DIR_ENV, BUILDER_SERVER_ENV, CB_BASE_LOG_PATH,
+        CB_CONFIG_ENV, CB_CONFIG_NAME, JWTS_ENV, METRICS_SERVER_ENV, MODULE_ID_ENV, MODULE_JWT_ENV,
+        MUX_KEYS_DIR_ENV, PBS_ADMIN_HOST_ENV, PBS_BID_ARCHIVE_DIR, PBS_MUX_KEYS_DIR,
         SIGNER_DIR_KEYS, SIGNER_DIR_KEYS_ENV, SIGNER_DIR_SECRETS, SIGNER_DIR_SECRETS_ENV,
-        SIGNER_KEYS, SIGNER_KEYS_ENV, SIGNER_SERVER_ENV,
+        SIGNER_KEYS, SIGNER_KEYS_ENV, SIGNER_SERVER_ENV, SIGNER_TLS_CERT_ENV,
     },
     loader::SignerLoader,
-    utils::{random_jwt, MAX_LOG_FILES_ENV, ROLLING_DURATION_ENV, RUST_LOG_ENV},
+    utils::{random_jwt, MAX_LOG_FILES_ENV, OTLP_ENDPOINT_ENV, ROLLING_DURATION_ENV, RUST_LOG_ENV},
 };
 use docker_compose_types::{
     Compose, ComposeVolume, DependsOnOptions, Environment, Labels, LoggingParameters, MapOrEmpty,
     NetworkSettings, Networks, Ports, Service, Services, SingleValue, TopLevelVolumes, Volumes,
 };
-use eyre::Result;
+use eyre::{ensure, Result};
 use indexmap::IndexMap;
 use serde::Serialize;
 
@@ -59,6 +60,8 @@ pub fn handle_docker_init(config_path: String, output_dir: String) -> Result<()>
     // address for signer API communication
     let signer_port = 20000;
     let signer_server = format!("cb_signer:{signer_port}");
+    let signer_tls_cert =
+        cb_config.signer.as_ref().and_then(|signer| signer.tls.as_ref()).map(|tls| &tls.cert_path);
 
     let builder_events_port = 30000;
     let mut builder_events_modules = Vec::new();
@@ -79,6 +82,62 @@ pub fn handle_docker_init(config_path: String, output_dir: String) -> Result<()>
         let (key, val) = get_env_uval(MAX_LOG_FILES_ENV, max_files as u64);
         pbs_envs.insert(key, val);
     }
+    if let Some(otlp_endpoint) = &cb_config.logs.otlp_endpoint {
+        let (key, val) = get_env_val(OTLP_ENDPOINT_ENV, otlp_endpoint.as_str());
+        pbs_envs.insert(key, val);
+    }
+
+    // mount the validators files of the muxes
+    let mut pbs_volumes = vec![config_volume.clone(), log_volume.clone()];
+    if let Some(muxes) = &cb_config.muxes {
+        for mux in muxes {
+            if let Some(validators_file) = &mux.validators_file {
+                pbs_volumes.push(Volumes::Simple(format!(
+                    "{}:{}:ro",
+                    host_path(validators_file),
+                    mux.mounted_keys_path()
+                )));
+                let (k, v) = get_env_val(MUX_KEYS_DIR_ENV, PBS_MUX_KEYS_DIR);
+                pbs_envs.insert(k, v);
+            }
+        }
+    }
+
+    if let Some(bid_archive) = &cb_config.pbs.pbs_config.bid_archive {
+        pbs_volumes.push(Volumes::Simple(format!(
+            "{}:{}",
+            host_path(&bid_archive.dir_path),
+            PBS_BID_ARCHIVE_DIR
+        )));
+        let (k, v) = get_env_val(BID_ARCHIVE_DIR_ENV, PBS_BID_ARCHIVE_DIR);
+        pbs_envs.insert(k, v);
+    }
+
+    // certificates and keys are loaded from the paths in the config
+    let mut pbs_tls_files = BTreeSet::new();
+    if let Some(tls) = &cb_config.pbs.pbs_config.tls {
+        pbs_tls_files.extend([&tls.cert_path, &tls.key_path]);
+    }
+    for relay in &cb_config.relays {
+        pbs_tls_files.extend(
+            [&relay.ca_cert_path, &relay.client_cert_path, &relay.client_key_path]
+                .into_iter()
+                .flatten(),
+        );
+    }
+    for path in pbs_tls_files {
+        pbs_volumes.push(same_path_volume(path)?);
+    }
+
+    let mut pbs_ports =
+        vec![format!("{}:{}", cb_config.pbs.pbs_config.port, cb_config.pbs.pbs_config.port)];
+    // the admin api listens on all interfaces in the container, but is only
+    // published on the configured host
+    if let Some(admin_api) = &cb_config.pbs.pbs_config.admin_api {
+        pbs_ports.push(format!("{}:{}:{}", admin_api.host, admin_api.port, admin_api.port));
+        let (k, v) = get_env_val(PBS_ADMIN_HOST_ENV, "0.0.0.0");
+        pbs_envs.insert(k, v);
+    }
 
     let mut needs_signer_module = cb_config.pbs.with_signer;
 
@@ -115,6 +174,18 @@ pub fn handle_docker_init(config_path: String, output_dir: String) -> Result<()>
                         let (key, val) = get_env_uval(MAX_LOG_FILES_ENV, max_files as u64);
                         module_envs.insert(key, val);
                     }
+                    if let Some(otlp_endpoint) = &cb_config.logs.otlp_endpoint {
+                        let (key, val) = get_env_val(OTLP_ENDPOINT_ENV, otlp_endpoint.as_str());
+                        module_envs.insert(key, val);
+                    }
+
+                    let mut module_volumes = vec![config_volume.clone(), log_volume.clone()];
+                    if let Some(cert_path) = signer_tls_cert {
+                        module_volumes.push(same_path_volume(cert_path)?);
+                        let (k, v) =
+                            get_env_val(SIGNER_TLS_CERT_ENV, &cert_path.display().to_string());
+                        module_envs.insert(k, v);
+                    }
 
                     envs.insert(jwt_name.clone(), jwt.clone());
                     jwts.insert(module.id.clone(), jwt);
@@ -127,7 +198,7 @@ pub fn handle_docker_init(config_path: String, output_dir: String) -> Result<()>
                             METRICS_NETWORK.to_owned(),
                             SIGNER_NETWORK.to_owned(),
                         ]),
-                        volumes: vec![config_volume.clone(), log_volume.clone()],
+                        volumes: module_volumes,
                         environment: Environment::KvPair(module_envs),
                         depends_on: DependsOnOptions::Simple(vec!["cb_signer".to_owned()]),
                         ..Service::default()
@@ -148,6 +219,10 @@ pub fn handle_docker_init(config_path: String, output_dir: String) -> Result<()>
                         let (key, val) = get_env_uval(MAX_LOG_FILES_ENV, max_files as u64);
                         module_envs.insert(key, val);
                     }
+                    if let Some(otlp_endpoint) = &cb_config.logs.otlp_endpoint {
+                        let (key, val) = get_env_val(OTLP_ENDPOINT_ENV, otlp_endpoint.as_str());
+                        module_envs.insert(key, val);
+                    }
 
                     builder_events_modules.push(format!("{module_cid}:{builder_events_port}"));
 
@@ -176,12 +251,9 @@ pub fn handle_docker_init(config_path: String, output_dir: String) -> Result<()>
     let pbs_service = Service {
         container_name: Some("cb_pbs".to_owned()),
         image: Some(cb_config.pbs.docker_image),
-        ports: Ports::Short(vec![format!(
-            "{}:{}",
-            cb_config.pbs.pbs_config.port, cb_config.pbs.pbs_config.port
-        )]),
+        ports: Ports::Short(pbs_ports),
         networks: Networks::Simple(vec![METRICS_NETWORK.to_owned()]),
-        volumes: vec![config_volume.clone(), log_volume.clone()],
+        volumes: pbs_volumes,
         environment: Environment::KvPair(pbs_envs),
         ..Service::default()
     };
@@ -213,6 +285,10 @@ pub fn handle_docker_init(config_path: String, output_dir: String) -> Result<()>
                 let (key, val) = get_env_uval(MAX_LOG_FILES_ENV, max_files as u64);
                 signer_envs.insert(key, val);
             }
+            if let Some(otlp_endpoint) = &cb_config.logs.otlp_endpoint {
+                let (key, val) = get_env_val(OTLP_ENDPOINT_ENV, otlp_endpoint.as_str());
+                signer_envs.insert(key, val);
+            }
 
             // TODO: generalize this, different loaders may not need volumes but eg ports
             match signer_config.loader {
@@ -235,6 +311,11 @@ pub fn handle_docker_init(config_path: String, output_dir: String) -> Result<()>
                 }
             };
 
+            if let Some(tls) = &signer_config.tls {
+                volumes.push(same_path_volume(&tls.cert_path)?);
+                volumes.push(same_path_volume(&tls.key_path)?);
+            }
+
             // write jwts to env
             let jwts_json = serde_json::to_string(&jwts).unwrap().clone();
             envs.insert(JWTS_ENV.into(), format!("{jwts_json:?}"));
@@ -404,6 +485,24 @@ pub fn handle_docker_init(config_path: String, output_dir: String) -> Result<()>
     Ok(())
 }
 
+/// Mounts a file at the same path in the container, for files that are loaded
+/// from a path set in the config
+fn same_path_volume(path: &Path) -> Result<Volumes> {
+    ensure!(path.is_absolute(), "{} must be an absolute path to be mounted", path.display());
+    Ok(Volumes::Simple(format!("{0}:{0}:ro", path.display())))
+}
+
+/// Host path of a bind mount. Absolute paths are kept, relative ones are
+/// prefixed with `./` if needed, otherwise docker compose treats them as named
+/// volumes
+fn host_path(path: &Path) -> String {
+    if path.is_absolute() || path.starts_with(".") || path.starts_with("..") {
+        path.display().to_string()
+    } else {
+        format!("./{}", path.display())
+    }
+}
+
 // FOO=${FOO}
 fn get_env_same(k: &str) -> (String, Option<SingleValue>) {
     get_env_interp(k, k)
@@ -1,7 +1,9 @@
+use alloy::rpc::types::beacon::BlsPublicKey;
 use cb_common::utils::print_logo;
 use clap::{Parser, Subcommand};
 use docker_init::{CB_COMPOSE_FILE, CB_CONFIG_FILE, CB_ENV_FILE};
 
+mod bids;
 mod docker_cmd;
 mod docker_init;
 
@@ -61,11 +63,29 @@ pub enum Command {
         )]
         compose_path: String,
     },
+
+    /// Query the bids in the PBS bid archive
+    Bids {
+        /// Path to the bid archive directory
+        #[arg(long("dir"))]
+        dir_path: String,
+
+        /// Only show bids for this slot
+        #[arg(long)]
+        slot: Option<u64>,
+
+        /// Only show the slots this validator requested headers for
+        #[arg(long("validator"))]
+        validator_pubkey: Option<BlsPublicKey>,
+    },
 }
 
 impl Args {
     pub async fn run(self) -> eyre::Result<()> {
-        print_logo();
+        // keep the output of queries parseable
+        if !matches!(self.cmd, Command::Bids { .. }) {
+            print_logo();
+        }
 
         match self.cmd {
             Command::Init { config_path, output_path } => {
@@ -81,6 +101,10 @@ impl Args {
             }
 
             Command::Logs { compose_path } => docker_cmd::handle_docker_logs(compose_path),
+
+            Command::Bids { dir_path, slot, validator_pubkey } => {
+                bids::handle_bids_query(dir_path, slot, validator_pubkey)
+            }
         }
     }
 }
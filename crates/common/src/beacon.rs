@@ -0,0 +1,359 @@
+//! Client for the beacon node API
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use alloy::{
+    primitives::{Address, B256},
+    rpc::types::beacon::BlsPublicKey,
+};
+use eyre::{bail, Result};
+use reqwest::header::ACCEPT;
+use serde::Deserialize;
+use tokio::{
+    sync::mpsc,
+    time::{sleep, timeout},
+};
+use tracing::warn;
+use url::Url;
+
+/// Requests are sent while serving the builder API, so they can't take long
+const BEACON_REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+/// A head event is expected every slot, the event stream is reopened if
+/// nothing is received for a few slots
+const EVENT_STREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+const EVENT_STREAM_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const EVENT_CHANNEL_SIZE: usize = 32;
+
+/// A client to query a beacon node, safe to share across threads
+#[derive(Debug, Clone)]
+pub struct BeaconClient {
+    url: Url,
+    client: reqwest::Client,
+    /// Client without a request timeout, for the event stream
+    events_client: reqwest::Client,
+    /// Validator pubkeys by index, an index is never reassigned
+    pubkeys: Arc<RwLock<HashMap<u64, BlsPublicKey>>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BeaconResponse<T> {
+    data: T,
+}
+
+#[derive(Debug, Deserialize)]
+struct ValidatorData {
+    validator: Validator,
+}
+
+#[derive(Debug, Deserialize)]
+struct Validator {
+    pubkey: BlsPublicKey,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlindedBlockData {
+    message: BlindedBlockMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlindedBlockMessage {
+    body: BlindedBlockBody,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlindedBlockBody {
+    execution_payload_header: ExecutionBlockInfo,
+}
+
+/// Fields of the execution payload of a beacon block
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ExecutionBlockInfo {
+    pub block_hash: B256,
+    #[serde(with = "serde_utils::quoted_u64")]
+    pub gas_limit: u64,
+}
+
+/// Sent when the head of the chain changes
+#[derive(Debug, Clone, Deserialize)]
+pub struct HeadEvent {
+    #[serde(with = "serde_utils::quoted_u64")]
+    pub slot: u64,
+    /// Root of the head block
+    pub block: B256,
+    /// Root of the head state
+    pub state: B256,
+    pub epoch_transition: bool,
+    #[serde(default)]
+    pub execution_optimistic: bool,
+}
+
+/// Sent when the beacon node computes the payload attributes of the block of
+/// the next slot
+#[derive(Debug, Clone, Deserialize)]
+pub struct PayloadAttributesEvent {
+    #[serde(with = "serde_utils::quoted_u64")]
+    pub proposer_index: u64,
+    #[serde(with = "serde_utils::quoted_u64")]
+    pub proposal_slot: u64,
+    #[serde(with = "serde_utils::quoted_u64")]
+    pub parent_block_number: u64,
+    pub parent_block_root: B256,
+    pub parent_block_hash: B256,
+    pub payload_attributes: PayloadAttributes,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PayloadAttributes {
+    #[serde(with = "serde_utils::quoted_u64")]
+    pub timestamp: u64,
+    pub prev_randao: B256,
+    pub suggested_fee_recipient: Address,
+}
+
+/// Events received from the beacon node event stream
+#[derive(Debug, Clone)]
+pub enum BeaconEvent {
+    Head(HeadEvent),
+    PayloadAttributes(PayloadAttributesEvent),
+}
+
+impl BeaconEvent {
+    /// Parses the data of an event, returns None for other topics
+    fn parse(event: &str, data: &str) -> Result<Option<Self>> {
+        match event {
+            "head" => Ok(Some(Self::Head(serde_json::from_str(data)?))),
+            "payload_attributes" => {
+                let res: BeaconResponse<PayloadAttributesEvent> = serde_json::from_str(data)?;
+                Ok(Some(Self::PayloadAttributes(res.data)))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Incremental parser of a server-sent events stream
+#[derive(Debug, Default)]
+struct EventStreamParser {
+    buf: Vec<u8>,
+    event: String,
+    data: String,
+}
+
+impl EventStreamParser {
+    /// Feeds a chunk of the stream, returns the events it completes as (event,
+    /// data) pairs
+    fn feed(&mut self, chunk: &[u8]) -> Vec<(String, String)> {
+        self.buf.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buf.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            // an empty line ends the event
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push((std::mem::take(&mut self.event), std::mem::take(&mut self.data)));
+                }
+                self.event.clear();
+                continue;
+            }
+
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.event = value.to_string(),
+                "data" => {
+                    if !self.data.is_empty() {
+                        self.data.push('\n');
+                    }
+                    self.data.push_str(value);
+                }
+                // comments, ids and retry times are not used
+                _ => {}
+            }
+        }
+
+        events
+    }
+}
+
+impl BeaconClient {
+    pub fn new(url: Url) -> Result<Self> {
+        let client = reqwest::Client::builder().timeout(BEACON_REQUEST_TIMEOUT).build()?;
+        let events_client =
+            reqwest::Client::builder().connect_timeout(BEACON_REQUEST_TIMEOUT).build()?;
+        Ok(Self { url, client, events_client, pubkeys: Default::default() })
+    }
+
+    pub fn get_url(&self, path: &str) -> String {
+        format!("{}{path}", self.url.as_str().trim_end_matches('/'))
+    }
+
+    /// Pubkey of the validator with the given index, cached after the first
+    /// lookup
+    pub async fn get_validator_pubkey(&self, index: u64) -> Result<BlsPublicKey> {
+        if let Some(pubkey) = self.pubkeys.read().expect("poisoned").get(&index) {
+            return Ok(*pubkey);
+        }
+
+        let url = self.get_url(&format!("/eth/v1/beacon/states/head/validators/{index}"));
+        let res = self.client.get(url).send().await?;
+        let code = res.status();
+        let body = res.bytes().await?;
+
+        if !code.is_success() {
+            bail!("beacon node returned {code}: {}", String::from_utf8_lossy(&body));
+        }
+
+        let res: BeaconResponse<ValidatorData> = serde_json::from_slice(&body)?;
+        let pubkey = res.data.validator.pubkey;
+        self.pubkeys.write().expect("poisoned").insert(index, pubkey);
+
+        Ok(pubkey)
+    }
+
+    /// Execution payload of a beacon block, `block_id` can be a slot, a block
+    /// root or one of "head", "genesis" and "finalized"
+    pub async fn get_execution_block(&self, block_id: &str) -> Result<ExecutionBlockInfo> {
+        let url = self.get_url(&format!("/eth/v1/beacon/blinded_blocks/{block_id}"));
+        let res = self.client.get(url).send().await?;
+        let code = res.status();
+        let body = res.bytes().await?;
+
+        if !code.is_success() {
+            bail!("beacon node returned {code}: {}", String::from_utf8_lossy(&body));
+        }
+
+        let res: BeaconResponse<BlindedBlockData> = serde_json::from_slice(&body)?;
+        Ok(res.data.message.body.execution_payload_header)
+    }
+
+    /// Subscribes to the head and payload attributes events. The stream is
+    /// reopened if it fails, until the receiver is dropped
+    pub fn subscribe_events(&self) -> mpsc::Receiver<BeaconEvent> {
+        let (tx, rx) = mpsc::channel(EVENT_CHANNEL_SIZE);
+        let client = self.clone();
+
+        tokio::spawn(async move {
+            while !tx.is_closed() {
+                if let Err(err) = client.stream_events(&tx).await {
+                    warn!(?err, "beacon node event stream interrupted, reconnecting");
+                }
+                sleep(EVENT_STREAM_RECONNECT_DELAY).await;
+            }
+        });
+
+        rx
+    }
+
+    /// Sends events to `tx` until the stream fails or the receiver is dropped
+    async fn stream_events(&self, tx: &mpsc::Sender<BeaconEvent>) -> Result<()> {
+        let url = self.get_url("/eth/v1/events?topics=head,payload_attributes");
+        let mut res =
+            self.events_client.get(url).header(ACCEPT, "text/event-stream").send().await?;
+        let code = res.status();
+
+        if !code.is_success() {
+            let body = res.bytes().await?;
+            bail!("beacon node returned {code}: {}", String::from_utf8_lossy(&body));
+        }
+
+        let mut parser = EventStreamParser::default();
+        loop {
+            let Ok(chunk) = timeout(EVENT_STREAM_IDLE_TIMEOUT, res.chunk()).await else {
+                bail!("no events received for {EVENT_STREAM_IDLE_TIMEOUT:?}");
+            };
+            let Some(chunk) = chunk? else {
+                bail!("event stream closed by the beacon node");
+            };
+
+            for (event, data) in parser.feed(&chunk) {
+                match BeaconEvent::parse(&event, &data) {
+                    Ok(Some(event)) => {
+                        if tx.send(event).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(err) => warn!(?err, event, "invalid beacon node event"),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{http::header::CONTENT_TYPE, routing::get, Router};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    const HEAD_EVENT: &str = r#"{"slot":"10","block":"0x9a2fefd2fdb57f74993c7780ea5b9030d2897b615b89f808011ca5aebed54eaf","state":"0x600e852a08c1200654ddf11025f1ceacb3c2e74bdd5c630cde0838b2591b69f9","epoch_transition":false,"previous_duty_dependent_root":"0x5e0043f107cb57913498fbf2f99ff55e730bf1e151f02f221e977c91a90a0e91","current_duty_dependent_root":"0x5e0043f107cb57913498fbf2f99ff55e730bf1e151f02f221e977c91a90a0e91","execution_optimistic":false}"#;
+    const PAYLOAD_ATTRIBUTES_EVENT: &str = r#"{"version":"capella","data":{"proposer_index":"123","proposal_slot":"11","parent_block_number":"9","parent_block_root":"0x9a2fefd2fdb57f74993c7780ea5b9030d2897b615b89f808011ca5aebed54eaf","parent_block_hash":"0x9a2fefd2fdb57f74993c7780ea5b9030d2897b615b89f808011ca5aebed54eaf","payload_attributes":{"timestamp":"123456","prev_randao":"0x9a2fefd2fdb57f74993c7780ea5b9030d2897b615b89f808011ca5aebed54eaf","suggested_fee_recipient":"0x0000000000000000000000000000000000000000","withdrawals":[]}}}"#;
+
+    #[test]
+    fn test_event_stream_parser() {
+        let stream = format!(
+            ": keepalive\n\nevent: head\ndata: {HEAD_EVENT}\n\nevent: payload_attributes\r\ndata: {PAYLOAD_ATTRIBUTES_EVENT}\r\n\r\n"
+        );
+
+        // events can be split across chunks at any byte
+        let mut parser = EventStreamParser::default();
+        let mut events = Vec::new();
+        for chunk in stream.as_bytes().chunks(7) {
+            events.extend(parser.feed(chunk));
+        }
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0], ("head".to_string(), HEAD_EVENT.to_string()));
+        assert_eq!(events[1].0, "payload_attributes");
+
+        let Some(BeaconEvent::Head(head)) = BeaconEvent::parse(&events[0].0, &events[0].1).unwrap()
+        else {
+            panic!("expected a head event");
+        };
+        assert_eq!(head.slot, 10);
+
+        let Some(BeaconEvent::PayloadAttributes(attributes)) =
+            BeaconEvent::parse(&events[1].0, &events[1].1).unwrap()
+        else {
+            panic!("expected a payload attributes event");
+        };
+        assert_eq!(attributes.proposal_slot, 11);
+        assert_eq!(attributes.payload_attributes.timestamp, 123456);
+
+        assert!(BeaconEvent::parse("block", "{}").unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_events() {
+        let body = format!(
+            "event: head\ndata: {HEAD_EVENT}\n\nevent: payload_attributes\ndata: {PAYLOAD_ATTRIBUTES_EVENT}\n\n"
+        );
+        let app = Router::new().route(
+            "/eth/v1/events",
+            get(move || async move { ([(CONTENT_TYPE, "text/event-stream")], body) }),
+        );
+        let listener = TcpListener::bind(("127.0.0.1", 24005)).await.unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let client = BeaconClient::new("http://127.0.0.1:24005".parse().unwrap()).unwrap();
+        let mut events = client.subscribe_events();
+
+        assert!(matches!(events.recv().await, Some(BeaconEvent::Head(head)) if head.slot == 10));
+        assert!(matches!(
+            events.recv().await,
+            Some(BeaconEvent::PayloadAttributes(attributes)) if attributes.proposal_slot == 11
+        ));
+
+        // the stub closes the stream, which is then reopened
+        assert!(matches!(events.recv().await, Some(BeaconEvent::Head(_))));
+    }
+}
@@ -1,8 +1,11 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use alloy::rpc::types::beacon::{BlsPublicKey, BlsSignature};
 use eyre::WrapErr;
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::{
+    header::{HeaderMap, HeaderValue, AUTHORIZATION},
+    Certificate,
+};
 use serde::{Deserialize, Serialize};
 
 use super::{
@@ -10,7 +13,7 @@ use super::{
     error::SignerClientError,
     request::{GenerateProxyRequest, SignRequest, SignedProxyDelegation},
 };
-use crate::DEFAULT_REQUEST_TIMEOUT;
+use crate::{config::load_certs, utils::inject_trace_context, DEFAULT_REQUEST_TIMEOUT};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct GetPubkeysResponse {
@@ -27,28 +30,41 @@ pub struct SignerClient {
 }
 
 impl SignerClient {
-    /// Create a new SignerClient
-    pub fn new(signer_server_address: String, jwt: &str) -> eyre::Result<Self> {
-        let url = format!("http://{}", signer_server_address);
+    /// Create a new SignerClient. If `tls_cert_path` is set, the Signer API is
+    /// called over https trusting the certificates in that file
+    pub fn new(
+        signer_server_address: String,
+        jwt: &str,
+        tls_cert_path: Option<PathBuf>,
+    ) -> eyre::Result<Self> {
         let mut headers = HeaderMap::new();
 
         let mut auth_value =
             HeaderValue::from_str(&format!("Bearer {}", jwt)).wrap_err("invalid jwt")?;
         auth_value.set_sensitive(true);
         headers.insert(AUTHORIZATION, auth_value);
-        let client = reqwest::Client::builder()
-            .timeout(DEFAULT_REQUEST_TIMEOUT)
-            .default_headers(headers)
-            .build()?;
-
-        Ok(Self { url: url.into(), client })
+        let mut builder =
+            reqwest::Client::builder().timeout(DEFAULT_REQUEST_TIMEOUT).default_headers(headers);
+
+        let url = match tls_cert_path {
+            Some(path) => {
+                builder = builder.use_rustls_tls();
+                for cert in load_certs(&path)? {
+                    builder = builder.add_root_certificate(Certificate::from_der(&cert)?);
+                }
+                format!("https://{}", signer_server_address)
+            }
+            None => format!("http://{}", signer_server_address),
+        };
+
+        Ok(Self { url: url.into(), client: builder.build()? })
     }
 
     /// Request a list of validator pubkeys for which signatures can be
     /// requested. TODO: add more docs on how proxy keys work
     pub async fn get_pubkeys(&self) -> Result<GetPubkeysResponse, SignerClientError> {
         let url = format!("{}{}", self.url, GET_PUBKEYS_PATH);
-        let res = self.client.get(&url).send().await?;
+        let res = self.client.get(&url).headers(trace_headers()).send().await?;
 
         let status = res.status();
         let response_bytes = res.bytes().await?;
@@ -71,7 +87,7 @@ impl SignerClient {
         request: &SignRequest,
     ) -> Result<BlsSignature, SignerClientError> {
         let url = format!("{}{}", self.url, REQUEST_SIGNATURE_PATH);
-        let res = self.client.post(&url).json(&request).send().await?;
+        let res = self.client.post(&url).headers(trace_headers()).json(&request).send().await?;
 
         let status = res.status();
         let response_bytes = res.bytes().await?;
@@ -94,7 +110,7 @@ impl SignerClient {
     ) -> Result<SignedProxyDelegation, SignerClientError> {
         let url = format!("{}{}", self.url, GENERATE_PROXY_KEY_PATH);
         let request = GenerateProxyRequest::new(pubkey);
-        let res = self.client.post(&url).json(&request).send().await?;
+        let res = self.client.post(&url).headers(trace_headers()).json(&request).send().await?;
 
         let status = res.status();
         let response_bytes = res.bytes().await?;
@@ -111,3 +127,10 @@ impl SignerClient {
         Ok(signed_proxy_delegation)
     }
 }
+
+/// Lets the signer continue the trace of the module that sent the request
+fn trace_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    inject_trace_context(&mut headers);
+    headers
+}
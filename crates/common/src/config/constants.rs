@@ -15,9 +15,18 @@ pub const SIGNER_DIR_KEYS_ENV: &str = "SIGNER_LOADER_DIR_KEYS";
 pub const SIGNER_DIR_KEYS: &str = "/keys";
 pub const SIGNER_DIR_SECRETS_ENV: &str = "SIGNER_LOADER_DIR_SECRETS";
 pub const SIGNER_DIR_SECRETS: &str = "/secrets";
+pub const SIGNER_TLS_CERT_ENV: &str = "CB_SIGNER_TLS_CERT";
 
 pub const JWTS_ENV: &str = "CB_JWTS";
 
+pub const MUX_KEYS_DIR_ENV: &str = "CB_MUX_KEYS_DIR";
+pub const PBS_MUX_KEYS_DIR: &str = "/mux_keys";
+
+pub const BID_ARCHIVE_DIR_ENV: &str = "CB_BID_ARCHIVE_DIR";
+pub const PBS_BID_ARCHIVE_DIR: &str = "/bid_archive";
+
+pub const PBS_ADMIN_HOST_ENV: &str = "CB_PBS_ADMIN_HOST";
+
 // TODO: replace these with an actual image in the registry
 pub const PBS_DEFAULT_IMAGE: &str = "commitboost_pbs_default";
 pub const SIGNER_IMAGE: &str = "commitboost_signer";
@@ -4,6 +4,7 @@ use std::{
 };
 
 use serde::{Deserialize, Serialize};
+use url::Url;
 
 use super::CB_BASE_LOG_PATH;
 
@@ -17,6 +18,9 @@ pub struct LogsSettings {
     pub log_level: String,
     #[serde(default)]
     pub max_log_files: Option<usize>,
+    /// OTLP gRPC endpoint of an OpenTelemetry collector to export spans to
+    #[serde(default)]
+    pub otlp_endpoint: Option<Url>,
 }
 
 impl Default for LogsSettings {
@@ -26,6 +30,7 @@ impl Default for LogsSettings {
             log_dir_path: default_log_dir_path(),
             log_level: default_log_level(),
             max_log_files: None,
+            otlp_endpoint: None,
         }
     }
 }
@@ -6,8 +6,10 @@ use crate::types::Chain;
 mod constants;
 mod metrics;
 mod module;
+mod mux;
 mod pbs;
 mod signer;
+mod tls;
 mod utils;
 
 mod log;
@@ -16,8 +18,10 @@ pub use constants::*;
 pub use log::*;
 pub use metrics::*;
 pub use module::*;
+pub use mux::*;
 pub use pbs::*;
 pub use signer::*;
+pub use tls::*;
 pub use utils::*;
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -26,6 +30,7 @@ pub struct CommitBoostConfig {
     pub chain: Chain,
     pub relays: Vec<RelayConfig>,
     pub pbs: StaticPbsConfig,
+    pub muxes: Option<Vec<MuxConfig>>,
     pub modules: Option<Vec<StaticModuleConfig>>,
     pub signer: Option<SignerConfig>,
     pub metrics: MetricsConfig,
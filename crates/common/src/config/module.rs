@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use eyre::{ContextCompat, Result};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use toml::Table;
@@ -5,7 +7,9 @@ use toml::Table;
 use crate::{
     commit::client::SignerClient,
     config::{
-        constants::{CB_CONFIG_ENV, MODULE_ID_ENV, MODULE_JWT_ENV, SIGNER_SERVER_ENV},
+        constants::{
+            CB_CONFIG_ENV, MODULE_ID_ENV, MODULE_JWT_ENV, SIGNER_SERVER_ENV, SIGNER_TLS_CERT_ENV,
+        },
         load_env_var,
         utils::load_file_from_env,
         BUILDER_SERVER_ENV,
@@ -50,11 +54,14 @@ pub struct StartCommitModuleConfig<T = ()> {
 /// - [MODULE_ID_ENV] - the id of the module to load
 /// - [CB_CONFIG_ENV] - the path to the config file
 /// - [MODULE_JWT_ENV] - the jwt token for the module
+/// - [SIGNER_TLS_CERT_ENV] - optional, the certificate to trust if the signer
+///   is served over https
 // TODO: add metrics url here
 pub fn load_commit_module_config<T: DeserializeOwned>() -> Result<StartCommitModuleConfig<T>> {
     let module_id = ModuleId(load_env_var(MODULE_ID_ENV)?);
     let module_jwt = Jwt(load_env_var(MODULE_JWT_ENV)?);
     let signer_server_address = load_env_var(SIGNER_SERVER_ENV)?;
+    let signer_tls_cert = load_env_var(SIGNER_TLS_CERT_ENV).ok().map(PathBuf::from);
 
     #[derive(Debug, Deserialize)]
     struct ThisModuleConfig<U> {
@@ -98,7 +105,7 @@ pub fn load_commit_module_config<T: DeserializeOwned>() -> Result<StartCommitMod
         .find(|m| m.static_config.id == module_id)
         .wrap_err(format!("failed to find module for {module_id}"))?;
 
-    let signer_client = SignerClient::new(signer_server_address, &module_jwt)?;
+    let signer_client = SignerClient::new(signer_server_address, &module_jwt, signer_tls_cert)?;
 
     Ok(StartCommitModuleConfig {
         id: module_config.static_config.id,
@@ -0,0 +1,190 @@
+//! Configuration to route validators to a subset of relays
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use alloy::{primitives::U256, rpc::types::beacon::BlsPublicKey};
+use eyre::{bail, ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{MUX_KEYS_DIR_ENV, PBS_MUX_KEYS_DIR};
+use crate::{pbs::RelayClient, utils::eth_to_wei};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MuxConfig {
+    /// Unique id of the mux, used in logs
+    pub id: String,
+    /// Validator pubkeys that should use this mux
+    #[serde(default)]
+    pub validator_pubkeys: Vec<BlsPublicKey>,
+    /// Path to a JSON file with a list of validator pubkeys that should use
+    /// this mux
+    pub validators_file: Option<PathBuf>,
+    /// Ids of the relays to use for these validators, as set in `[[relays]]`
+    pub relays: Vec<String>,
+    /// Overrides the global timeout for get_header request in milliseconds
+    pub timeout_get_header_ms: Option<u64>,
+    /// Overrides the global timeout for register_validator request in
+    /// milliseconds
+    pub timeout_register_validator_ms: Option<u64>,
+    /// Overrides the global minimum bid that will be accepted from get_header
+    pub min_bid_eth: Option<f64>,
+}
+
+impl MuxConfig {
+    /// All the pubkeys of this mux, including the ones in the validators file.
+    /// If the file was mounted in a container, it's loaded from the mounted
+    /// directory instead
+    pub fn load_pubkeys(&self) -> Result<Vec<BlsPublicKey>> {
+        let mut pubkeys = self.validator_pubkeys.clone();
+
+        if let Some(path) = &self.validators_file {
+            let path = match std::env::var(MUX_KEYS_DIR_ENV) {
+                Ok(dir) => PathBuf::from(dir).join(self.keys_file_name()),
+                Err(_) => path.clone(),
+            };
+
+            let file = std::fs::read_to_string(&path)
+                .wrap_err(format!("Unable to read validators file: {}", path.display()))?;
+            let file_pubkeys: Vec<BlsPublicKey> = serde_json::from_str(&file)
+                .wrap_err(format!("Invalid validators file: {}", path.display()))?;
+            pubkeys.extend(file_pubkeys);
+        }
+
+        Ok(pubkeys)
+    }
+
+    /// Name of the validators file when mounted in the pbs container
+    pub fn keys_file_name(&self) -> String {
+        format!("{}.json", self.id)
+    }
+
+    /// Path of the validators file when mounted in the pbs container
+    pub fn mounted_keys_path(&self) -> String {
+        format!("{PBS_MUX_KEYS_DIR}/{}", self.keys_file_name())
+    }
+}
+
+/// Runtime config of a mux, shared by all its validators
+#[derive(Debug, Clone)]
+pub struct RuntimeMuxConfig {
+    /// Id of the mux
+    pub id: String,
+    /// Relays to use for the validators in this mux
+    pub relays: Vec<RelayClient>,
+    /// Timeout for get_header request in milliseconds, if overridden
+    pub timeout_get_header_ms: Option<u64>,
+    /// Timeout for register_validator request in milliseconds, if overridden
+    pub timeout_register_validator_ms: Option<u64>,
+    /// Minimum bid that will be accepted from get_header, if overridden
+    pub min_bid_wei: Option<U256>,
+}
+
+/// Builds the lookup of validator pubkey -> mux, checking that each mux uses
+/// known relays and that each validator is in at most one mux
+pub fn load_muxes(
+    muxes: Vec<MuxConfig>,
+    relays: &[RelayClient],
+) -> Result<HashMap<BlsPublicKey, Arc<RuntimeMuxConfig>>> {
+    let mut lookup = HashMap::new();
+    let mut mux_ids = HashSet::new();
+
+    for mux in muxes {
+        ensure!(mux_ids.insert(mux.id.clone()), "duplicate mux id: {}", mux.id);
+        ensure!(!mux.relays.is_empty(), "mux {} has no relays", mux.id);
+
+        let mut mux_relays = Vec::with_capacity(mux.relays.len());
+        for relay_id in mux.relays.iter() {
+            let Some(relay) = relays.iter().find(|relay| relay.id.as_str() == relay_id) else {
+                bail!("mux {} uses unknown relay: {relay_id}", mux.id);
+            };
+            mux_relays.push(relay.clone());
+        }
+
+        let pubkeys = mux.load_pubkeys()?;
+        ensure!(!pubkeys.is_empty(), "mux {} has no validators", mux.id);
+
+        let runtime_mux = Arc::new(RuntimeMuxConfig {
+            id: mux.id.clone(),
+            relays: mux_relays,
+            timeout_get_header_ms: mux.timeout_get_header_ms,
+            timeout_register_validator_ms: mux.timeout_register_validator_ms,
+            min_bid_wei: mux.min_bid_eth.map(eth_to_wei),
+        });
+
+        for pubkey in pubkeys {
+            if let Some(other) = lookup.insert(pubkey, runtime_mux.clone()) {
+                bail!("validator {pubkey} is in both mux {} and mux {}", other.id, mux.id);
+            }
+        }
+    }
+
+    Ok(lookup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RelayConfig;
+
+    fn relay(id: &str) -> RelayClient {
+        let config = RelayConfig {
+            id: Some(id.to_string()),
+            entry: serde_json::from_str(&format!(
+                "\"http://0xa1cec75a3f0661e99299274182938151e8433c61a19222347ea1313d839229cb4ce4e3e5aa2bdeb71c8fcf1b084963c2@{id}.xyz\""
+            ))
+            .unwrap(),
+            ..Default::default()
+        };
+        RelayClient::new(config).unwrap()
+    }
+
+    fn mux(id: &str, pubkeys: Vec<BlsPublicKey>, relays: &[&str]) -> MuxConfig {
+        MuxConfig {
+            id: id.to_string(),
+            validator_pubkeys: pubkeys,
+            validators_file: None,
+            relays: relays.iter().map(|r| r.to_string()).collect(),
+            timeout_get_header_ms: None,
+            timeout_register_validator_ms: None,
+            min_bid_eth: Some(0.5),
+        }
+    }
+
+    #[test]
+    fn test_load_muxes() {
+        let relays = vec![relay("a"), relay("b")];
+        let pk1 = BlsPublicKey::repeat_byte(1);
+        let pk2 = BlsPublicKey::repeat_byte(2);
+
+        let muxes = vec![mux("one", vec![pk1], &["a"]), mux("two", vec![pk2], &["a", "b"])];
+        let lookup = load_muxes(muxes, &relays).unwrap();
+
+        assert_eq!(lookup.len(), 2);
+        assert_eq!(lookup[&pk1].id, "one");
+        assert_eq!(lookup[&pk1].relays.len(), 1);
+        assert_eq!(lookup[&pk2].relays.len(), 2);
+        assert_eq!(lookup[&pk2].min_bid_wei, Some(eth_to_wei(0.5)));
+    }
+
+    #[test]
+    fn test_load_muxes_invalid() {
+        let relays = vec![relay("a")];
+        let pk = BlsPublicKey::repeat_byte(1);
+
+        let unknown_relay = vec![mux("one", vec![pk], &["c"])];
+        assert!(load_muxes(unknown_relay, &relays).is_err());
+
+        let no_relays = vec![mux("one", vec![pk], &[])];
+        assert!(load_muxes(no_relays, &relays).is_err());
+
+        let no_validators = vec![mux("one", vec![], &["a"])];
+        assert!(load_muxes(no_validators, &relays).is_err());
+
+        let duplicate_pubkey = vec![mux("one", vec![pk], &["a"]), mux("two", vec![pk], &["a"])];
+        assert!(load_muxes(duplicate_pubkey, &relays).is_err());
+    }
+}
@@ -1,16 +1,32 @@
 //! Configuration for the PBS module
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+};
 
-use alloy::primitives::U256;
-use eyre::Result;
+use alloy::{primitives::U256, rpc::types::beacon::BlsPublicKey};
+use eyre::{ensure, Result};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use url::Url;
 
-use super::{constants::PBS_DEFAULT_IMAGE, CommitBoostConfig};
+use super::{
+    constants::PBS_DEFAULT_IMAGE, load_muxes, CommitBoostConfig, MuxConfig, RollingDuration,
+    RuntimeMuxConfig, TlsConfig,
+};
 use crate::{
+    beacon::BeaconClient,
     commit::client::SignerClient,
-    config::{load_env_var, load_file_from_env, CB_CONFIG_ENV, MODULE_JWT_ENV, SIGNER_SERVER_ENV},
-    pbs::{BuilderEventPublisher, DefaultTimeout, RelayClient, RelayEntry, LATE_IN_SLOT_TIME_MS},
+    config::{
+        load_env_var, load_file_from_env, CB_CONFIG_ENV, MODULE_JWT_ENV, PBS_ADMIN_HOST_ENV,
+        SIGNER_SERVER_ENV, SIGNER_TLS_CERT_ENV,
+    },
+    pbs::{
+        BidArchive, BuilderEventPublisher, DefaultTimeout, RelayClient, RelayEntry,
+        DEFAULT_BUILDER_BOOST_FACTOR, LATE_IN_SLOT_TIME_MS,
+    },
     types::Chain,
     utils::{as_eth_str, default_bool, default_u256, default_u64},
 };
@@ -22,6 +38,14 @@ pub struct RelayConfig {
     /// Relay in the form of scheme://pubkey@host
     #[serde(rename = "url")]
     pub entry: RelayEntry,
+    /// Other urls of the same relay, e.g. regional endpoints. get_header,
+    /// get_status, the circuit breaker probes and the startup check are sent
+    /// to all of them and pass if any does, submit_block is sent to all of
+    /// them. register_validator tries them in order, starting with `url`,
+    /// until one accepts the registrations. The pubkey can be omitted, and
+    /// must match the one of `url` if set
+    #[serde(default)]
+    pub extra_urls: Vec<Url>,
     /// Optional headers to send with each request
     pub headers: Option<HashMap<String, String>>,
     /// Whether to enable timing games
@@ -31,6 +55,28 @@ pub struct RelayConfig {
     pub target_first_request_ms: Option<u64>,
     /// Frequency in ms to send get_header requests
     pub frequency_get_header_ms: Option<u64>,
+    /// Whether to schedule the timing games requests from the observed
+    /// latency of the relay, see `AdaptiveTimingConfig`. Only used if timing
+    /// games are enabled
+    #[serde(default = "default_bool::<false>")]
+    pub adaptive_timing_games: bool,
+    /// Whether to use SSZ for get_header and submit_block, falls back to JSON
+    /// if the relay rejects it
+    #[serde(default = "default_bool::<false>")]
+    pub enable_ssz: bool,
+    /// PEM file with CA certificates to trust for this relay, on top of the
+    /// built-in roots
+    pub ca_cert_path: Option<PathBuf>,
+    /// PEM file with the client certificate, for relays that require mutual
+    /// TLS
+    pub client_cert_path: Option<PathBuf>,
+    /// PEM file with the private key of the client certificate
+    pub client_key_path: Option<PathBuf>,
+    /// Proxy to send all requests to this relay through, with an http, https
+    /// or socks5 scheme
+    pub proxy: Option<Url>,
+    /// Local IP address to send requests to this relay from
+    pub local_address: Option<IpAddr>,
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -46,18 +92,310 @@ pub struct PbsConfig {
     /// Timeout for get_payload request in milliseconds
     #[serde(default = "default_u64::<{ DefaultTimeout::GET_PAYLOAD_MS }>")]
     pub timeout_get_payload_ms: u64,
+    /// Whether to send submit_block only to the relays that bid the block hash,
+    /// instead of all relays
+    #[serde(default = "default_bool::<false>")]
+    pub submit_block_to_bidders_only: bool,
+    /// If sending submit_block only to bidders, whether to send it to all
+    /// relays when no bid for the block hash is cached, e.g. after a restart
+    #[serde(default = "default_bool::<true>")]
+    pub submit_block_fallback_to_all: bool,
     /// Timeout for register_validator request in milliseconds
     #[serde(default = "default_u64::<{ DefaultTimeout::REGISTER_VALIDATOR_MS }>")]
     pub timeout_register_validator_ms: u64,
     /// Whether to skip the relay signature verification
     #[serde(default = "default_bool::<false>")]
     pub skip_sigverify: bool,
+    /// Whether to skip the proposer signature verification of blinded blocks.
+    /// Needs `beacon_node_url` for the proposer pubkeys
+    #[serde(default = "default_bool::<false>")]
+    pub skip_proposer_sigverify: bool,
+    /// Whether to skip checking that blinded blocks are for the current slot
+    #[serde(default = "default_bool::<false>")]
+    pub skip_slot_checks: bool,
+    /// Whether to reject bids that don't pay the fee recipient the validator
+    /// registered
+    #[serde(default = "default_bool::<false>")]
+    pub validate_fee_recipient: bool,
+    /// Whether to reject bids with a gas limit that doesn't move towards the
+    /// one the validator registered. Needs `beacon_node_url` for the gas limit
+    /// of the parent block
+    #[serde(default = "default_bool::<false>")]
+    pub validate_gas_limit: bool,
+    /// Whether to reject bids with a timestamp different from the slot start
+    #[serde(default = "default_bool::<false>")]
+    pub validate_timestamp: bool,
+    /// Beacon node to look up the pubkeys of block proposers, and the gas limit
+    /// of parent blocks
+    pub beacon_node_url: Option<Url>,
+    /// Whether to follow the head and payload attributes events of the beacon
+    /// node, to track slots between proposals
+    #[serde(default = "default_bool::<true>")]
+    pub beacon_events: bool,
     /// Minimum bid that will be accepted from get_header
     #[serde(rename = "min_bid_eth", with = "as_eth_str", default = "default_u256")]
     pub min_bid_wei: U256,
     /// How late in the slot we consider to be "late"
     #[serde(default = "default_u64::<LATE_IN_SLOT_TIME_MS>")]
     pub late_in_slot_time_ms: u64,
+    /// Minimum premium over the local block value a bid needs to be returned,
+    /// only checked if the beacon node sends the local block value
+    #[serde(rename = "min_premium_eth", with = "as_eth_str", default = "default_u256")]
+    pub min_premium_wei: U256,
+    /// Builder boost factor per validator pubkey, used if the beacon node
+    /// doesn't send one
+    #[serde(default)]
+    pub builder_boost_factors: HashMap<BlsPublicKey, u64>,
+    /// Relay health scoring and circuit breaker
+    #[serde(default)]
+    pub relay_health: RelayHealthConfig,
+    /// Relay checks run before the module starts
+    #[serde(default)]
+    pub startup_check: StartupCheckConfig,
+    /// Timing games scheduled from the observed relay latency
+    #[serde(default)]
+    pub adaptive_timing_games: AdaptiveTimingConfig,
+    /// Built-in filters and scorers used to choose the bid returned from
+    /// get_header
+    #[serde(default)]
+    pub bid_selection: BidSelectionConfig,
+    /// Validator registrations forwarding
+    #[serde(default)]
+    pub registrations: RegistrationsConfig,
+    /// On-disk archive of received bids, disabled if missing
+    pub bid_archive: Option<BidArchiveConfig>,
+    /// Admin API to inspect the module at runtime, disabled if missing
+    pub admin_api: Option<AdminApiConfig>,
+    /// Serve the builder API over https, plain http if missing
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AdminApiConfig {
+    /// Host to listen on, this should not be reachable from the public
+    #[serde(default = "default_admin_host")]
+    pub host: Ipv4Addr,
+    /// Port to listen on, must be different from the builder API port
+    pub port: u16,
+    /// Bearer token required in the `Authorization` header, no auth if missing
+    pub auth_token: Option<String>,
+}
+
+impl AdminApiConfig {
+    /// Address to listen on. In a container this is overridden to all
+    /// interfaces, and the port is only published on `host`
+    pub fn listen_address(&self) -> SocketAddr {
+        let host = std::env::var(PBS_ADMIN_HOST_ENV)
+            .ok()
+            .and_then(|host| host.parse().ok())
+            .unwrap_or(self.host);
+        SocketAddr::from((host, self.port))
+    }
+}
+
+fn default_admin_host() -> Ipv4Addr {
+    Ipv4Addr::LOCALHOST
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BidArchiveConfig {
+    /// Directory where the archive files are written
+    pub dir_path: PathBuf,
+    /// How often to start a new archive file
+    #[serde(default)]
+    pub rotation: RollingDuration,
+    /// Maximum number of archive files to keep, all are kept if missing
+    pub max_files: Option<usize>,
+}
+
+/// Thresholds used to score relays and trip them into a cooldown. Rates are
+/// computed over the last `window_size` requests sent to each relay
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RelayHealthConfig {
+    /// Whether to stop sending requests to relays that cross the thresholds
+    pub enable_circuit_breaker: bool,
+    /// Number of most recent requests used to compute the scores
+    pub window_size: usize,
+    /// Minimum number of requests in the window before a relay can be tripped
+    pub min_requests: usize,
+    /// Maximum rate of failed requests (errors and timeouts) before tripping
+    pub max_error_rate: f64,
+    /// Maximum rate of timed out requests before tripping
+    pub max_timeout_rate: f64,
+    /// Maximum average latency in milliseconds before tripping
+    pub max_avg_latency_ms: Option<u64>,
+    /// How long a tripped relay is skipped before being probed with a
+    /// `get_status` call
+    pub cooldown_secs: u64,
+}
+
+impl Default for RelayHealthConfig {
+    fn default() -> Self {
+        Self {
+            enable_circuit_breaker: false,
+            window_size: 50,
+            min_requests: 10,
+            max_error_rate: 0.5,
+            max_timeout_rate: 0.5,
+            max_avg_latency_ms: None,
+            cooldown_secs: 120,
+        }
+    }
+}
+
+/// Checks that relays are reachable and configured correctly, run once when the
+/// module starts
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StartupCheckConfig {
+    /// Whether to check the relays before starting
+    pub enabled: bool,
+    /// Timeout in milliseconds for each request sent to a relay
+    pub timeout_ms: u64,
+    /// Minimum number of relays that must pass all the checks for the module
+    /// to start, 0 to always start
+    pub min_passing_relays: usize,
+}
+
+impl Default for StartupCheckConfig {
+    fn default() -> Self {
+        Self { enabled: true, timeout_ms: 3000, min_passing_relays: 0 }
+    }
+}
+
+/// Timing games for relays with `adaptive_timing_games` set. The last request
+/// is sent so that it's expected to return just before the get_header deadline,
+/// given the latency percentile of the relay's recent get_header requests
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AdaptiveTimingConfig {
+    /// Number of most recent get_header latencies kept for each relay
+    pub window_size: usize,
+    /// Minimum number of latencies to use the adaptive schedule, the static
+    /// timing games settings of the relay are used until then
+    pub min_samples: usize,
+    /// Percentile of the relay latency the last request is scheduled with
+    pub latency_percentile: f64,
+    /// Time in milliseconds left between the expected arrival of the last
+    /// response and the deadline
+    pub safety_margin_ms: u64,
+}
+
+impl Default for AdaptiveTimingConfig {
+    fn default() -> Self {
+        Self { window_size: 200, min_samples: 20, latency_percentile: 95.0, safety_margin_ms: 50 }
+    }
+}
+
+/// Built-in bid filters and scorers. Each bid is scored starting from its
+/// value, and the bid with the highest score among the ones not filtered out
+/// is returned
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BidSelectionConfig {
+    /// Only keep bids from these relay ids, all relays if empty
+    pub relay_allowlist: Vec<String>,
+    /// Haircut in basis points applied to the score of the bids of each relay
+    /// id
+    pub relay_haircuts_bps: HashMap<String, u64>,
+    /// Relay ids whose bids are preferred over higher bids from other relays,
+    /// as long as they are within `preferred_relays_margin_bps`
+    pub preferred_relays: Vec<String>,
+    /// Margin in basis points added to the score of the bids of preferred
+    /// relays
+    pub preferred_relays_margin_bps: u64,
+}
+
+impl Default for BidSelectionConfig {
+    fn default() -> Self {
+        Self {
+            relay_allowlist: Vec::new(),
+            relay_haircuts_bps: HashMap::new(),
+            preferred_relays: Vec::new(),
+            preferred_relays_margin_bps: 100,
+        }
+    }
+}
+
+impl BidSelectionConfig {
+    /// Checks that all the relay ids are in `[[relays]]`
+    pub fn validate(&self, relays: &[RelayClient]) -> Result<()> {
+        let relay_ids = self
+            .relay_allowlist
+            .iter()
+            .chain(self.relay_haircuts_bps.keys())
+            .chain(self.preferred_relays.iter());
+
+        for relay_id in relay_ids {
+            ensure!(
+                relays.iter().any(|relay| relay.id.as_str() == relay_id),
+                "bid selection uses unknown relay: {relay_id}"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// How validator registrations are verified and forwarded to relays. The
+/// latest registration of each validator is stored
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RegistrationsConfig {
+    /// Whether to skip the signature verification of registrations
+    pub skip_sigverify: bool,
+    /// Whether to forward only new registrations, or with a different fee
+    /// recipient or gas limit than the last one accepted by the relays
+    pub only_forward_changes: bool,
+    /// Seconds between sending all the stored registrations to relays,
+    /// disabled if 0
+    pub rebroadcast_interval_secs: u64,
+    /// Maximum number of registrations per request to a relay, 0 to send them
+    /// all in one request
+    pub batch_size: usize,
+    /// Maximum number of batches being sent to each relay at the same time
+    pub max_concurrent_batches: usize,
+    /// How many times a batch is sent again after a timeout, a connection
+    /// error or a 5xx response
+    pub batch_retries: u32,
+}
+
+impl Default for RegistrationsConfig {
+    fn default() -> Self {
+        Self {
+            skip_sigverify: false,
+            only_forward_changes: true,
+            rebroadcast_interval_secs: 3600,
+            batch_size: 1000,
+            max_concurrent_batches: 4,
+            batch_retries: 2,
+        }
+    }
+}
+
+impl PbsConfig {
+    /// Boost factor for a validator, the one sent by the beacon node takes
+    /// precedence over the configured one
+    pub fn builder_boost_factor(&self, pubkey: &BlsPublicKey, requested: Option<u64>) -> u64 {
+        requested
+            .or_else(|| self.builder_boost_factors.get(pubkey).copied())
+            .unwrap_or(DEFAULT_BUILDER_BOOST_FACTOR)
+    }
+
+    /// Checks settings that depend on each other or on the relays, run before
+    /// the module starts any task
+    pub fn validate(&self, relays: &[RelayClient]) -> Result<()> {
+        if let Some(admin_api) = &self.admin_api {
+            ensure!(
+                admin_api.port != self.port,
+                "admin api must use a different port than the builder api"
+            );
+        }
+        self.bid_selection.validate(relays)?;
+
+        Ok(())
+    }
 }
 
 /// Static pbs config from config file
@@ -85,10 +423,16 @@ pub struct PbsModuleConfig<T = ()> {
     pub pbs_config: Arc<PbsConfig>,
     /// List of relays
     pub relays: Vec<RelayClient>,
+    /// Validator pubkey -> mux, for validators using a subset of the relays
+    pub muxes: Option<Arc<HashMap<BlsPublicKey, Arc<RuntimeMuxConfig>>>>,
     /// Signer client to call Signer API
     pub signer_client: Option<SignerClient>,
     /// Event publisher
     pub event_publiher: Option<BuilderEventPublisher>,
+    /// Archive of received bids
+    pub bid_archive: Option<BidArchive>,
+    /// Beacon node client
+    pub beacon_client: Option<BeaconClient>,
     /// Opaque module config
     pub extra: T,
 }
@@ -102,46 +446,129 @@ pub fn load_pbs_config() -> Result<PbsModuleConfig<()>> {
     let config = CommitBoostConfig::from_env_path()?;
     let relay_clients =
         config.relays.into_iter().map(RelayClient::new).collect::<Result<Vec<_>>>()?;
+    config.pbs.pbs_config.validate(&relay_clients)?;
+    let muxes = config.muxes.map(|muxes| load_muxes(muxes, &relay_clients)).transpose()?;
     let maybe_publiher = BuilderEventPublisher::new_from_env();
+    let bid_archive =
+        config.pbs.pbs_config.bid_archive.as_ref().map(BidArchive::new).transpose()?;
+    let beacon_client =
+        config.pbs.pbs_config.beacon_node_url.clone().map(BeaconClient::new).transpose()?;
 
     Ok(PbsModuleConfig {
         chain: config.chain,
         pbs_config: Arc::new(config.pbs.pbs_config),
         relays: relay_clients,
+        muxes: muxes.map(Arc::new),
         signer_client: None,
         event_publiher: maybe_publiher,
+        bid_archive,
+        beacon_client,
         extra: (),
     })
 }
 
-/// Loads a custom pbs config, i.e. with signer client and/or custom data
-pub fn load_pbs_custom_config<T: DeserializeOwned>() -> Result<PbsModuleConfig<T>> {
-    #[derive(Debug, Deserialize)]
-    struct CustomPbsConfig<U> {
-        #[serde(flatten)]
-        static_config: StaticPbsConfig,
-        #[serde(flatten)]
-        extra: U,
-    }
+/// Pbs config with the custom data of the module
+#[derive(Debug, Deserialize)]
+struct CustomPbsConfig<U> {
+    #[serde(flatten)]
+    static_config: StaticPbsConfig,
+    #[serde(flatten)]
+    extra: U,
+}
 
-    #[derive(Deserialize, Debug)]
-    struct StubConfig<U> {
-        chain: Chain,
-        relays: Vec<RelayConfig>,
-        pbs: CustomPbsConfig<U>,
-    }
+/// Parts of the config file used by the pbs module
+#[derive(Deserialize, Debug)]
+struct StubConfig<U> {
+    chain: Chain,
+    relays: Vec<RelayConfig>,
+    pbs: CustomPbsConfig<U>,
+    muxes: Option<Vec<MuxConfig>>,
+}
+
+/// Reloads the relays, muxes, pbs config and custom data from the config file,
+/// keeping the rest of the current config. Fails if the new config is invalid
+/// or changes settings that need a restart
+pub fn reload_pbs_config<T: DeserializeOwned>(
+    current: &PbsModuleConfig<T>,
+) -> Result<PbsModuleConfig<T>> {
+    let StubConfig { chain, relays, pbs: CustomPbsConfig { static_config, extra }, muxes } =
+        load_file_from_env(CB_CONFIG_ENV)?;
+    let pbs_config = static_config.pbs_config;
+
+    ensure!(chain == current.chain, "changing the chain requires a restart");
+    ensure!(pbs_config.port == current.pbs_config.port, "changing the port requires a restart");
+    ensure!(
+        pbs_config.admin_api == current.pbs_config.admin_api,
+        "changing the admin api requires a restart"
+    );
+    ensure!(
+        pbs_config.beacon_events == current.pbs_config.beacon_events &&
+            (!current.pbs_config.beacon_events ||
+                pbs_config.beacon_node_url == current.pbs_config.beacon_node_url),
+        "changing the beacon node followed for events requires a restart"
+    );
+    ensure!(pbs_config.tls == current.pbs_config.tls, "changing the tls config requires a restart");
+    ensure!(
+        pbs_config.registrations.rebroadcast_interval_secs ==
+            current.pbs_config.registrations.rebroadcast_interval_secs,
+        "changing the registrations rebroadcast interval requires a restart"
+    );
+    ensure!(
+        static_config.with_signer == current.signer_client.is_some(),
+        "changing the signer client requires a restart"
+    );
+    ensure!(!relays.is_empty(), "no relays configured");
 
+    let relay_clients = relays.into_iter().map(RelayClient::new).collect::<Result<Vec<_>>>()?;
+    pbs_config.validate(&relay_clients)?;
+    let muxes = muxes.map(|muxes| load_muxes(muxes, &relay_clients)).transpose()?;
+    let beacon_client = pbs_config.beacon_node_url.clone().map(BeaconClient::new).transpose()?;
+
+    Ok(PbsModuleConfig {
+        chain: current.chain,
+        pbs_config: Arc::new(pbs_config),
+        relays: relay_clients,
+        muxes: muxes.map(Arc::new),
+        signer_client: current.signer_client.clone(),
+        event_publiher: current.event_publiher.clone(),
+        bid_archive: current.bid_archive.clone(),
+        beacon_client,
+        extra,
+    })
+}
+
+/// Loads a custom pbs config, i.e. with signer client and/or custom data
+pub fn load_pbs_custom_config<T: DeserializeOwned>() -> Result<PbsModuleConfig<T>> {
     // load module config including the extra data (if any)
     let cb_config: StubConfig<T> = load_file_from_env(CB_CONFIG_ENV)?;
     let relay_clients =
         cb_config.relays.into_iter().map(RelayClient::new).collect::<Result<Vec<_>>>()?;
+    cb_config.pbs.static_config.pbs_config.validate(&relay_clients)?;
+    let muxes = cb_config.muxes.map(|muxes| load_muxes(muxes, &relay_clients)).transpose()?;
     let maybe_publiher = BuilderEventPublisher::new_from_env();
+    let bid_archive = cb_config
+        .pbs
+        .static_config
+        .pbs_config
+        .bid_archive
+        .as_ref()
+        .map(BidArchive::new)
+        .transpose()?;
+    let beacon_client = cb_config
+        .pbs
+        .static_config
+        .pbs_config
+        .beacon_node_url
+        .clone()
+        .map(BeaconClient::new)
+        .transpose()?;
 
     let signer_client = if cb_config.pbs.static_config.with_signer {
         // if custom pbs requires a signer client, load jwt
         let module_jwt = load_env_var(MODULE_JWT_ENV)?;
         let signer_server_address = load_env_var(SIGNER_SERVER_ENV)?;
-        Some(SignerClient::new(signer_server_address, &module_jwt)?)
+        let signer_tls_cert = load_env_var(SIGNER_TLS_CERT_ENV).ok().map(PathBuf::from);
+        Some(SignerClient::new(signer_server_address, &module_jwt, signer_tls_cert)?)
     } else {
         None
     };
@@ -150,8 +577,55 @@ pub fn load_pbs_custom_config<T: DeserializeOwned>() -> Result<PbsModuleConfig<T
         chain: cb_config.chain,
         pbs_config: Arc::new(cb_config.pbs.static_config.pbs_config),
         relays: relay_clients,
+        muxes: muxes.map(Arc::new),
         signer_client,
         event_publiher: maybe_publiher,
+        bid_archive,
+        beacon_client,
         extra: cb_config.pbs.extra,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relay(id: &str) -> RelayClient {
+        let config = RelayConfig {
+            id: Some(id.to_string()),
+            entry: serde_json::from_str(&format!(
+                "\"http://0xa1cec75a3f0661e99299274182938151e8433c61a19222347ea1313d839229cb4ce4e3e5aa2bdeb71c8fcf1b084963c2@{id}.xyz\""
+            ))
+            .unwrap(),
+            ..Default::default()
+        };
+        RelayClient::new(config).unwrap()
+    }
+
+    #[test]
+    fn test_bid_selection_relays() {
+        let relays = vec![relay("a"), relay("b")];
+
+        let known = BidSelectionConfig {
+            relay_allowlist: vec!["a".to_string()],
+            relay_haircuts_bps: HashMap::from([("b".to_string(), 100)]),
+            preferred_relays: vec!["a".to_string(), "b".to_string()],
+            ..Default::default()
+        };
+        assert!(known.validate(&relays).is_ok());
+
+        let unknown_allowlist =
+            BidSelectionConfig { relay_allowlist: vec!["c".to_string()], ..Default::default() };
+        assert!(unknown_allowlist.validate(&relays).is_err());
+
+        let unknown_haircut = BidSelectionConfig {
+            relay_haircuts_bps: HashMap::from([("c".to_string(), 100)]),
+            ..Default::default()
+        };
+        assert!(unknown_haircut.validate(&relays).is_err());
+
+        let unknown_preferred =
+            BidSelectionConfig { preferred_relays: vec!["c".to_string()], ..Default::default() };
+        assert!(unknown_preferred.validate(&relays).is_err());
+    }
+}
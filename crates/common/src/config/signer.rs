@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use super::{
     constants::{SIGNER_IMAGE, SIGNER_SERVER_ENV},
     utils::{load_env_var, load_jwts},
-    CommitBoostConfig,
+    CommitBoostConfig, TlsConfig,
 };
 use crate::{loader::SignerLoader, types::{Chain, Jwt, ModuleId}};
 
@@ -17,6 +17,9 @@ pub struct SignerConfig {
     pub docker_image: String,
     /// Which keys to load
     pub loader: SignerLoader,
+    /// Serve the signer API over https, plain http if missing. Modules are
+    /// given the certificate to trust it
+    pub tls: Option<TlsConfig>,
 }
 
 fn default_signer() -> String {
@@ -29,6 +32,7 @@ pub struct StartSignerConfig {
     pub loader: SignerLoader,
     pub server_port: u16,
     pub jwts: BiHashMap<ModuleId, Jwt>,
+    pub tls: Option<TlsConfig>,
 }
 
 impl StartSignerConfig {
@@ -37,12 +41,14 @@ impl StartSignerConfig {
 
         let jwts = load_jwts()?;
         let server_port = load_env_var(SIGNER_SERVER_ENV)?.parse()?;
+        let signer_config = config.signer.expect("Signer config is missing");
 
         Ok(StartSignerConfig {
             chain: config.chain,
-            loader: config.signer.expect("Signer config is missing").loader,
+            loader: signer_config.loader,
             server_port,
             jwts,
+            tls: signer_config.tls,
         })
     }
 }
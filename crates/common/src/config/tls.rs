@@ -0,0 +1,172 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use eyre::{ensure, eyre, Result, WrapErr};
+use rustls::{
+    crypto::ring,
+    pki_types::{CertificateDer, PrivateKeyDer},
+    ServerConfig,
+};
+use serde::{Deserialize, Serialize};
+
+/// Certificate and key to serve an API over https
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TlsConfig {
+    /// PEM file with the certificate chain, starting with the server
+    /// certificate
+    pub cert_path: PathBuf,
+    /// PEM file with the private key of the server certificate
+    pub key_path: PathBuf,
+}
+
+impl TlsConfig {
+    pub fn server_config(&self) -> Result<Arc<ServerConfig>> {
+        let certs = load_certs(&self.cert_path)?;
+        let key = load_private_key(&self.key_path)?;
+
+        let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .wrap_err("invalid tls certificate or key")?;
+
+        Ok(Arc::new(config))
+    }
+}
+
+/// Loads all the certificates in a PEM file
+pub fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let file = File::open(path).wrap_err_with(|| format!("failed to open {}", path.display()))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .wrap_err_with(|| format!("failed to read certificates from {}", path.display()))?;
+    ensure!(!certs.is_empty(), "no certificates found in {}", path.display());
+
+    Ok(certs)
+}
+
+/// Loads the first private key in a PEM file
+pub fn load_private_key(path: &Path) -> Result<PrivateKeyDer<'static>> {
+    let file = File::open(path).wrap_err_with(|| format!("failed to open {}", path.display()))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .wrap_err_with(|| format!("failed to read private key from {}", path.display()))?
+        .ok_or_else(|| eyre!("no private key found in {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::{routing::get, Router};
+    use reqwest::Certificate;
+    use rustls::{server::WebPkiClientVerifier, RootCertStore};
+    use tokio::{net::TcpListener, sync::oneshot};
+
+    use super::*;
+    use crate::{
+        config::RelayConfig,
+        pbs::{RelayClient, BULDER_API_PATH, GET_STATUS_PATH},
+        utils::serve_with_shutdown,
+    };
+
+    fn tls_file(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/data/tls").join(name)
+    }
+
+    fn server_tls() -> TlsConfig {
+        TlsConfig { cert_path: tls_file("server.pem"), key_path: tls_file("server.key") }
+    }
+
+    async fn start_server(
+        port: u16,
+        path: &str,
+        tls: Arc<ServerConfig>,
+    ) -> (oneshot::Sender<()>, tokio::task::JoinHandle<std::io::Result<()>>) {
+        let app = Router::new().route(path, get(|| async { "ok" }));
+        let listener = TcpListener::bind(("127.0.0.1", port)).await.unwrap();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let server = tokio::spawn(serve_with_shutdown(
+            listener,
+            app,
+            async move {
+                let _ = shutdown_rx.await;
+            },
+            Duration::from_secs(1),
+            Some(tls),
+        ));
+
+        (shutdown_tx, server)
+    }
+
+    #[tokio::test]
+    async fn test_serve_tls() {
+        let (shutdown_tx, server) =
+            start_server(24003, "/", server_tls().server_config().unwrap()).await;
+
+        let ca = Certificate::from_pem(&std::fs::read(tls_file("ca.pem")).unwrap()).unwrap();
+        let client =
+            reqwest::Client::builder().use_rustls_tls().add_root_certificate(ca).build().unwrap();
+        let res = client.get("https://127.0.0.1:24003/").send().await.unwrap();
+        assert_eq!(res.text().await.unwrap(), "ok");
+
+        // the certificate is not trusted by default, and plain http is not served
+        assert!(reqwest::get("https://127.0.0.1:24003/").await.is_err());
+        assert!(reqwest::get("http://127.0.0.1:24003/").await.is_err());
+
+        drop(client);
+        shutdown_tx.send(()).unwrap();
+        assert!(server.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_relay_client_certificate() {
+        let provider = Arc::new(ring::default_provider());
+        let mut roots = RootCertStore::empty();
+        for cert in load_certs(&tls_file("ca.pem")).unwrap() {
+            roots.add(cert).unwrap();
+        }
+        let verifier = WebPkiClientVerifier::builder_with_provider(roots.into(), provider.clone())
+            .build()
+            .unwrap();
+        let tls = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(
+                load_certs(&server_tls().cert_path).unwrap(),
+                load_private_key(&server_tls().key_path).unwrap(),
+            )
+            .unwrap();
+
+        let status_path = format!("{BULDER_API_PATH}{GET_STATUS_PATH}");
+        let (shutdown_tx, server) = start_server(24004, &status_path, Arc::new(tls)).await;
+
+        let url = "https://0xac6e77dfe25ecd6110b8e780608cce0dab71fdd5ebea22a16c0205200f2f8e2e3ad3b71d3499c54ad14d6c21b41a37ae@127.0.0.1:24004";
+        let relay_config = |client_cert: bool| RelayConfig {
+            entry: serde_json::from_str(&format!("\"{url}\"")).unwrap(),
+            ca_cert_path: Some(tls_file("ca.pem")),
+            client_cert_path: client_cert.then(|| tls_file("client.pem")),
+            client_key_path: client_cert.then(|| tls_file("client.key")),
+            ..Default::default()
+        };
+
+        let relay = RelayClient::new(relay_config(true)).unwrap();
+        let res = relay.client.get(relay.get_status_url()).send().await.unwrap();
+        assert!(res.status().is_success());
+
+        let relay = RelayClient::new(relay_config(false)).unwrap();
+        assert!(relay.client.get(relay.get_status_url()).send().await.is_err());
+
+        // a certificate without a key is a config error
+        let config =
+            RelayConfig { client_cert_path: Some(tls_file("client.pem")), ..relay_config(false) };
+        assert!(RelayClient::new(config).is_err());
+
+        shutdown_tx.send(()).unwrap();
+        assert!(server.await.unwrap().is_ok());
+    }
+}
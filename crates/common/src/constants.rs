@@ -1,7 +1,9 @@
 // TODO: replace with full chain spec, allow loading from file
 
 pub const APPLICATION_BUILDER_DOMAIN: [u8; 4] = [0, 0, 0, 1];
+pub const DOMAIN_BEACON_PROPOSER: [u8; 4] = [0, 0, 0, 0];
 pub const GENESIS_VALIDATORS_ROOT: [u8; 32] = [0; 32];
+pub const SLOTS_PER_EPOCH: u64 = 32;
 
 // MAINNET
 pub const MAINNET_FORK_VERSION: [u8; 4] = [0u8; 4];
@@ -10,6 +12,13 @@ pub const MAINNET_BUILDER_DOMAIN: [u8; 32] = [
     61, 35, 32, 217, 240, 232, 234, 152, 49, 169,
 ];
 pub const MAINNET_GENESIS_TIME_SECONDS: u64 = 1606824023;
+pub const MAINNET_ELECTRA_FORK_EPOCH: u64 = 364032;
+pub const MAINNET_GENESIS_VALIDATORS_ROOT: [u8; 32] = [
+    75, 54, 61, 185, 78, 40, 97, 32, 215, 110, 185, 5, 52, 15, 221, 78, 84, 191, 233, 240, 107,
+    243, 63, 246, 207, 90, 210, 127, 81, 27, 254, 149,
+];
+pub const MAINNET_DENEB_FORK_VERSION: [u8; 4] = [4, 0, 0, 0];
+pub const MAINNET_ELECTRA_FORK_VERSION: [u8; 4] = [5, 0, 0, 0];
 
 // HOLESKY
 pub const HOLESKY_FORK_VERSION: [u8; 4] = [1, 1, 112, 0];
@@ -18,6 +27,13 @@ pub const HOLESKY_BUILDER_DOMAIN: [u8; 32] = [
     196, 152, 143, 62, 13, 159, 119, 240, 83, 135,
 ];
 pub const HOLESKY_GENESIS_TIME_SECONDS: u64 = 1695902400;
+pub const HOLESKY_ELECTRA_FORK_EPOCH: u64 = 115968;
+pub const HOLESKY_GENESIS_VALIDATORS_ROOT: [u8; 32] = [
+    145, 67, 170, 124, 97, 90, 127, 113, 21, 226, 182, 170, 195, 25, 192, 53, 41, 223, 130, 66,
+    174, 112, 95, 186, 157, 243, 155, 121, 197, 159, 168, 177,
+];
+pub const HOLESKY_DENEB_FORK_VERSION: [u8; 4] = [5, 1, 112, 0];
+pub const HOLESKY_ELECTRA_FORK_VERSION: [u8; 4] = [6, 1, 112, 0];
 
 // RHEA DEVNET
 pub const RHEA_FORK_VERSION: [u8; 4] = [16, 0, 0, 56];
@@ -1,5 +1,6 @@
 use std::time::Duration;
 
+pub mod beacon;
 pub mod commit;
 pub mod config;
 pub mod constants;
@@ -13,3 +14,7 @@ pub mod types;
 pub mod utils;
 
 pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(12);
+
+/// How long servers wait for in-flight requests after a shutdown signal. Docker
+/// kills the container 10 seconds after sending SIGTERM
+pub const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(8);
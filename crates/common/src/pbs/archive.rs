@@ -0,0 +1,260 @@
+//! Append-only archive of the bids received from relays, written as JSON lines
+//! to rotating files
+
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::mpsc,
+};
+
+use alloy::{
+    primitives::{B256, U256},
+    rpc::types::beacon::BlsPublicKey,
+};
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+use tracing_appender::rolling::{Builder, Rotation};
+
+use crate::{
+    config::{BidArchiveConfig, RollingDuration, BID_ARCHIVE_DIR_ENV},
+    utils::as_str,
+};
+
+const BID_ARCHIVE_FILE_PREFIX: &str = "bids";
+const BID_ARCHIVE_FILE_SUFFIX: &str = "jsonl";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum BidArchiveRecord {
+    /// A header received from a relay
+    Header {
+        slot: u64,
+        parent_hash: B256,
+        validator_pubkey: BlsPublicKey,
+        relay_id: String,
+        block_hash: B256,
+        #[serde(with = "as_str")]
+        value: U256,
+        latency_ms: u64,
+        /// Unix timestamp in milliseconds when the request was sent
+        request_time_ms: u64,
+        /// Set if the header failed validation, in which case it was discarded
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        validation_error: Option<String>,
+    },
+    /// The bid returned to the beacon node
+    BidReturned {
+        slot: u64,
+        validator_pubkey: BlsPublicKey,
+        block_hash: B256,
+        #[serde(with = "as_str")]
+        value: U256,
+    },
+    /// The payload returned to the beacon node after submitting the block
+    PayloadDelivered { slot: u64, block_hash: B256, relay_id: String },
+}
+
+impl BidArchiveRecord {
+    pub fn slot(&self) -> u64 {
+        match self {
+            Self::Header { slot, .. } |
+            Self::BidReturned { slot, .. } |
+            Self::PayloadDelivered { slot, .. } => *slot,
+        }
+    }
+
+    pub fn validator_pubkey(&self) -> Option<BlsPublicKey> {
+        match self {
+            Self::Header { validator_pubkey, .. } | Self::BidReturned { validator_pubkey, .. } => {
+                Some(*validator_pubkey)
+            }
+            Self::PayloadDelivered { .. } => None,
+        }
+    }
+}
+
+/// Writes bids to the archive from a background thread, so requests are never
+/// blocked on disk. Cheap to clone
+#[derive(Debug, Clone)]
+pub struct BidArchive {
+    sender: mpsc::Sender<BidArchiveRecord>,
+}
+
+impl BidArchive {
+    pub fn new(config: &BidArchiveConfig) -> Result<Self> {
+        let dir_path = std::env::var(BID_ARCHIVE_DIR_ENV)
+            .map(Into::into)
+            .unwrap_or_else(|_| config.dir_path.clone());
+
+        let rotation = match config.rotation {
+            RollingDuration::Hourly => Rotation::HOURLY,
+            RollingDuration::Daily => Rotation::DAILY,
+            RollingDuration::Never => Rotation::NEVER,
+        };
+
+        let mut builder = Builder::new()
+            .rotation(rotation)
+            .filename_prefix(BID_ARCHIVE_FILE_PREFIX)
+            .filename_suffix(BID_ARCHIVE_FILE_SUFFIX);
+        if let Some(max_files) = config.max_files {
+            builder = builder.max_log_files(max_files);
+        }
+        let mut writer = builder
+            .build(&dir_path)
+            .wrap_err(format!("failed to create bid archive in {}", dir_path.display()))?;
+
+        let (sender, receiver) = mpsc::channel::<BidArchiveRecord>();
+        std::thread::spawn(move || {
+            for record in receiver {
+                let mut line = match serde_json::to_vec(&record) {
+                    Ok(line) => line,
+                    Err(err) => {
+                        error!(?err, "failed to serialize bid archive record");
+                        continue;
+                    }
+                };
+                line.push(b'\n');
+
+                if let Err(err) = writer.write_all(&line) {
+                    error!(?err, "failed to write to bid archive");
+                }
+            }
+        });
+
+        Ok(Self { sender })
+    }
+
+    pub fn record(&self, record: BidArchiveRecord) {
+        if self.sender.send(record).is_err() {
+            error!("bid archive writer stopped, record dropped");
+        }
+    }
+}
+
+/// Filters for reading the archive, records have to match all the set ones
+#[derive(Debug, Clone, Default)]
+pub struct BidArchiveQuery {
+    pub slot: Option<u64>,
+    /// Returns all records in the slots this validator requested headers for
+    pub validator_pubkey: Option<BlsPublicKey>,
+}
+
+impl BidArchiveQuery {
+    pub fn filter(&self, records: Vec<BidArchiveRecord>) -> Vec<BidArchiveRecord> {
+        let records = records
+            .into_iter()
+            .filter(|record| self.slot.map_or(true, |slot| record.slot() == slot));
+
+        match self.validator_pubkey {
+            Some(pubkey) => {
+                let records: Vec<_> = records.collect();
+                let slots: HashSet<_> = records
+                    .iter()
+                    .filter(|record| record.validator_pubkey() == Some(pubkey))
+                    .map(|record| record.slot())
+                    .collect();
+
+                records.into_iter().filter(|record| slots.contains(&record.slot())).collect()
+            }
+            None => records.collect(),
+        }
+    }
+}
+
+/// Reads all the records in an archive directory, oldest first
+pub fn read_bid_archive(dir_path: &Path) -> Result<Vec<BidArchiveRecord>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir_path)
+        .wrap_err(format!("failed to read bid archive in {}", dir_path.display()))?
+    {
+        let path = entry?.path();
+        let is_archive = path.file_name().and_then(|name| name.to_str()).is_some_and(|name| {
+            name.starts_with(BID_ARCHIVE_FILE_PREFIX) && name.ends_with(BID_ARCHIVE_FILE_SUFFIX)
+        });
+
+        if is_archive {
+            files.push(path);
+        }
+    }
+    // file names end with the date, so this also sorts them by time
+    files.sort();
+
+    let mut records = Vec::new();
+    for path in files {
+        let reader = BufReader::new(File::open(&path)?);
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str(&line) {
+                Ok(record) => records.push(record),
+                Err(err) => warn!(?err, file = %path.display(), line = i + 1, "invalid record"),
+            }
+        }
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(slot: u64, validator_pubkey: BlsPublicKey) -> BidArchiveRecord {
+        BidArchiveRecord::Header {
+            slot,
+            parent_hash: B256::ZERO,
+            validator_pubkey,
+            relay_id: "relay".to_string(),
+            block_hash: B256::repeat_byte(1),
+            value: U256::from(10),
+            latency_ms: 100,
+            request_time_ms: 1,
+            validation_error: None,
+        }
+    }
+
+    #[test]
+    fn test_record_serde() {
+        let record = header(1, BlsPublicKey::ZERO);
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(json.contains(r#""event":"header""#));
+        assert!(json.contains(r#""value":"10""#));
+        assert!(!json.contains("validation_error"));
+        assert_eq!(serde_json::from_str::<BidArchiveRecord>(&json).unwrap(), record);
+    }
+
+    #[test]
+    fn test_query() {
+        let validator = BlsPublicKey::repeat_byte(1);
+        let records = vec![
+            header(1, validator),
+            BidArchiveRecord::PayloadDelivered {
+                slot: 1,
+                block_hash: B256::repeat_byte(1),
+                relay_id: "relay".to_string(),
+            },
+            header(2, BlsPublicKey::ZERO),
+            header(3, validator),
+        ];
+
+        let by_slot = BidArchiveQuery { slot: Some(2), ..Default::default() };
+        assert_eq!(by_slot.filter(records.clone()), vec![records[2].clone()]);
+
+        let by_validator =
+            BidArchiveQuery { validator_pubkey: Some(validator), ..Default::default() };
+        assert_eq!(by_validator.filter(records.clone()), vec![
+            records[0].clone(),
+            records[1].clone(),
+            records[3].clone()
+        ]);
+
+        let both = BidArchiveQuery { slot: Some(2), validator_pubkey: Some(validator) };
+        assert!(both.filter(records).is_empty());
+    }
+}
@@ -5,12 +5,25 @@ pub const GET_STATUS_PATH: &str = "/status";
 pub const REGISTER_VALIDATOR_PATH: &str = "/validators";
 pub const SUBMIT_BLOCK_PATH: &str = "/blinded_blocks";
 
+pub const ADMIN_API_PATH: &str = "/admin/v1";
+
+pub const ADMIN_RELAYS_PATH: &str = "/relays";
+pub const ADMIN_SLOT_PATH: &str = "/slot";
+pub const ADMIN_BIDS_PATH: &str = "/bids/:slot";
+pub const ADMIN_COUNTERS_PATH: &str = "/counters";
+
 // https://ethereum.github.io/builder-specs/#/Builder
 
 pub const HEADER_SLOT_UUID_KEY: &str = "X-MEVBoost-SlotID";
 pub const HEADER_VERSION_KEY: &str = "X-CommitBoost-Version";
 pub const HEAVER_VERSION_VALUE: &str = env!("CARGO_PKG_VERSION");
 pub const HEADER_START_TIME_UNIX_MS: &str = "X-MEVBoost-StartTimeUnixMS";
+pub const HEADER_CONSENSUS_VERSION: &str = "Eth-Consensus-Version";
+
+pub const CONTENT_TYPE_JSON: &str = "application/json";
+pub const CONTENT_TYPE_SSZ: &str = "application/octet-stream";
+/// Accept header sent to relays with SSZ enabled, JSON is still accepted
+pub const ACCEPT_SSZ_OR_JSON: &str = "application/octet-stream;q=1.0,application/json;q=0.9";
 
 pub const BUILDER_EVENTS_PATH: &str = "/builder_events";
 pub const DEFAULT_PBS_JWT_KEY: &str = "DEFAULT_PBS";
@@ -24,3 +37,6 @@ impl DefaultTimeout {
 }
 
 pub const LATE_IN_SLOT_TIME_MS: u64 = 2000;
+
+/// Bid value is compared as is with the local block value
+pub const DEFAULT_BUILDER_BOOST_FACTOR: u64 = 100;
@@ -0,0 +1,126 @@
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE};
+
+use super::constants::{CONTENT_TYPE_JSON, CONTENT_TYPE_SSZ};
+
+/// Wire encoding of Builder API requests and responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodingType {
+    Json,
+    Ssz,
+}
+
+impl EncodingType {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            EncodingType::Json => CONTENT_TYPE_JSON,
+            EncodingType::Ssz => CONTENT_TYPE_SSZ,
+        }
+    }
+
+    pub fn header_value(&self) -> HeaderValue {
+        HeaderValue::from_static(self.content_type())
+    }
+
+    /// Preferred encoding from the `Accept` header, picking the supported
+    /// media type with the highest quality. Defaults to JSON
+    pub fn from_accept(headers: &HeaderMap) -> Self {
+        let Some(accept) = headers.get(ACCEPT).and_then(|value| value.to_str().ok()) else {
+            return EncodingType::Json;
+        };
+
+        let mut preferred: Option<(EncodingType, f32)> = None;
+        for media_range in accept.split(',') {
+            let mut params = media_range.split(';').map(str::trim);
+            let media_type = params.next().unwrap_or_default().to_ascii_lowercase();
+
+            let encoding = match media_type.as_str() {
+                CONTENT_TYPE_SSZ => EncodingType::Ssz,
+                CONTENT_TYPE_JSON | "application/*" | "*/*" => EncodingType::Json,
+                _ => continue,
+            };
+
+            let quality = params
+                .find_map(|param| param.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            if quality > 0.0 && preferred.map_or(true, |(_, best)| quality > best) {
+                preferred = Some((encoding, quality));
+            }
+        }
+
+        preferred.map(|(encoding, _)| encoding).unwrap_or(EncodingType::Json)
+    }
+
+    /// Encoding of a body from the `Content-Type` header. Defaults to JSON
+    pub fn from_content_type(headers: &HeaderMap) -> Self {
+        let is_ssz = headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|media_type| media_type.trim().eq_ignore_ascii_case(CONTENT_TYPE_SSZ));
+
+        if is_ssz {
+            EncodingType::Ssz
+        } else {
+            EncodingType::Json
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE};
+
+    use super::EncodingType;
+
+    fn with_header(name: reqwest::header::HeaderName, value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn test_from_accept() {
+        assert_eq!(EncodingType::from_accept(&HeaderMap::new()), EncodingType::Json);
+        assert_eq!(
+            EncodingType::from_accept(&with_header(ACCEPT, "application/octet-stream")),
+            EncodingType::Ssz
+        );
+        assert_eq!(
+            EncodingType::from_accept(&with_header(
+                ACCEPT,
+                "application/octet-stream;q=1.0,application/json;q=0.9"
+            )),
+            EncodingType::Ssz
+        );
+        assert_eq!(
+            EncodingType::from_accept(&with_header(
+                ACCEPT,
+                "application/octet-stream;q=0.5, application/json"
+            )),
+            EncodingType::Json
+        );
+        assert_eq!(
+            EncodingType::from_accept(&with_header(ACCEPT, "application/octet-stream;q=0")),
+            EncodingType::Json
+        );
+        assert_eq!(EncodingType::from_accept(&with_header(ACCEPT, "*/*")), EncodingType::Json);
+    }
+
+    #[test]
+    fn test_from_content_type() {
+        assert_eq!(EncodingType::from_content_type(&HeaderMap::new()), EncodingType::Json);
+        assert_eq!(
+            EncodingType::from_content_type(&with_header(CONTENT_TYPE, "application/json")),
+            EncodingType::Json
+        );
+        assert_eq!(
+            EncodingType::from_content_type(&with_header(
+                CONTENT_TYPE,
+                "Application/Octet-Stream; charset=binary"
+            )),
+            EncodingType::Ssz
+        );
+    }
+}
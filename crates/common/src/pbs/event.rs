@@ -1,18 +1,21 @@
 use std::net::SocketAddr;
 
-use alloy::{primitives::B256, rpc::types::beacon::relay::ValidatorRegistration};
+use alloy::{
+    primitives::{B256, U256},
+    rpc::types::beacon::relay::ValidatorRegistration,
+};
 use axum::{
     async_trait,
     extract::State,
+    http::HeaderMap,
     response::{IntoResponse, Response},
     routing::post,
     Json,
 };
-use eyre::bail;
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use tokio::net::TcpListener;
-use tracing::{error, info, trace};
+use tracing::{error, info, info_span, trace, Instrument};
 
 use super::{
     GetHeaderParams, GetHeaderReponse, SignedBlindedBeaconBlock, SubmitBlindedBlockResponse,
@@ -20,17 +23,33 @@ use super::{
 use crate::{
     config::{load_env_var, BUILDER_SERVER_ENV},
     pbs::BUILDER_EVENTS_PATH,
+    utils::{
+        inject_trace_context, serve_with_shutdown, set_trace_parent, wait_for_shutdown_signal,
+    },
+    SHUTDOWN_DRAIN_TIMEOUT,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum BuilderEvent {
     GetHeaderRequest(GetHeaderParams),
     GetHeaderResponse(Box<Option<GetHeaderReponse>>),
+    /// Outcome of comparing the best bid with the local block value
+    BidDecision {
+        slot: u64,
+        block_hash: B256,
+        bid_value: U256,
+        builder_boost_factor: u64,
+        local_block_value: Option<U256>,
+        use_builder: bool,
+    },
     GetStatusEvent,
     GetStatusResponse,
     SubmitBlockRequest(Box<SignedBlindedBeaconBlock>),
     SubmitBlockResponse(Box<SubmitBlindedBlockResponse>),
-    MissedPayload { block_hash: B256, relays: String },
+    MissedPayload {
+        block_hash: B256,
+        relays: String,
+    },
     RegisterValidatorRequest(Vec<ValidatorRegistration>),
     RegisterValidatorResponse,
 }
@@ -60,14 +79,20 @@ impl BuilderEventPublisher {
     }
 
     pub fn publish(&self, event: BuilderEvent) {
+        // spawned tasks don't inherit the current span, so capture it here
+        let mut headers = HeaderMap::new();
+        inject_trace_context(&mut headers);
+
         for endpoint in self.endpoints.clone() {
             let client = self.client.clone();
             let event = event.clone();
+            let headers = headers.clone();
 
             tokio::spawn(async move {
                 trace!("Sending events to {}", endpoint);
                 if let Err(err) = client
                     .post(endpoint)
+                    .headers(headers)
                     .json(&event)
                     .send()
                     .await
@@ -103,18 +128,30 @@ impl<T: OnBuilderApiEvent + Clone + Send + Sync + 'static> BuilderEventClient<T>
         let address = SocketAddr::from(([0, 0, 0, 0], self.port));
         let listener = TcpListener::bind(&address).await?;
 
-        axum::serve(listener, router).await?;
-
-        bail!("Builder events stopped")
+        serve_with_shutdown(
+            listener,
+            router,
+            wait_for_shutdown_signal(),
+            SHUTDOWN_DRAIN_TIMEOUT,
+            None,
+        )
+        .await?;
+
+        info!("Builder events server stopped");
+        Ok(())
     }
 }
 
 async fn handle_builder_event<T: OnBuilderApiEvent>(
     State(processor): State<T>,
+    headers: HeaderMap,
     Json(event): Json<BuilderEvent>,
 ) -> Response {
     trace!("Handling builder event");
-    processor.on_builder_api_event(event).await;
+    let span = info_span!("builder_event");
+    set_trace_parent(&span, &headers);
+
+    processor.on_builder_api_event(event).instrument(span).await;
     StatusCode::OK.into_response()
 }
 
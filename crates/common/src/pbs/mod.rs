@@ -1,9 +1,13 @@
+mod archive;
 mod constants;
+mod encoding;
 mod event;
 mod relay;
 mod types;
 
+pub use archive::*;
 pub use constants::*;
+pub use encoding::*;
 pub use event::*;
 pub use relay::*;
 pub use types::*;
@@ -1,19 +1,33 @@
-use std::{str::FromStr, sync::Arc};
+use std::{
+    fs,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
 use alloy::{
     primitives::{hex::FromHex, B256},
     rpc::types::beacon::BlsPublicKey,
 };
-use eyre::{Result, WrapErr};
-use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use eyre::{bail, ensure, eyre, Result, WrapErr};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Certificate, Identity, Proxy, StatusCode,
+};
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 use url::Url;
 
 use super::{
     constants::{BULDER_API_PATH, GET_STATUS_PATH, REGISTER_VALIDATOR_PATH, SUBMIT_BLOCK_PATH},
-    HEADER_VERSION_KEY, HEAVER_VERSION_VALUE,
+    EncodingType, HEADER_VERSION_KEY, HEAVER_VERSION_VALUE,
+};
+use crate::{
+    config::{load_certs, RelayConfig},
+    DEFAULT_REQUEST_TIMEOUT,
 };
-use crate::{config::RelayConfig, DEFAULT_REQUEST_TIMEOUT};
 /// A parsed entry of the relay url in the format: scheme://pubkey@host
 #[derive(Debug, Default, Clone)]
 pub struct RelayEntry {
@@ -48,6 +62,54 @@ impl<'de> Deserialize<'de> for RelayEntry {
     }
 }
 
+/// One of the urls a relay is reachable at
+#[derive(Debug, Clone)]
+pub struct RelayEndpoint {
+    /// Host and port of the url, used to label metrics
+    pub id: String,
+    /// Full url of the endpoint
+    pub url: String,
+}
+
+impl RelayEndpoint {
+    fn new(url: &str) -> Result<Self> {
+        let parsed = Url::parse(url).wrap_err_with(|| format!("invalid relay url {url}"))?;
+        let host = parsed.host_str().ok_or_else(|| eyre!("relay url {url} has no host"))?;
+        let id = match parsed.port() {
+            Some(port) => format!("{host}:{port}"),
+            None => host.to_string(),
+        };
+
+        Ok(Self { id, url: url.trim_end_matches('/').to_string() })
+    }
+
+    // URL builders
+    pub fn get_url(&self, path: &str) -> String {
+        format!("{}{path}", &self.url)
+    }
+
+    pub fn get_header_url(
+        &self,
+        slot: u64,
+        parent_hash: B256,
+        validator_pubkey: BlsPublicKey,
+    ) -> String {
+        self.get_url(&format!("{BULDER_API_PATH}/header/{slot}/{parent_hash}/{validator_pubkey}"))
+    }
+
+    pub fn get_status_url(&self) -> String {
+        self.get_url(&format!("{BULDER_API_PATH}{GET_STATUS_PATH}"))
+    }
+
+    pub fn register_validator_url(&self) -> String {
+        self.get_url(&format!("{BULDER_API_PATH}{REGISTER_VALIDATOR_PATH}"))
+    }
+
+    pub fn submit_block_url(&self) -> String {
+        self.get_url(&format!("{BULDER_API_PATH}{SUBMIT_BLOCK_PATH}"))
+    }
+}
+
 /// A client to interact with a relay, safe to share across threads
 #[derive(Debug, Clone)]
 pub struct RelayClient {
@@ -57,6 +119,10 @@ pub struct RelayClient {
     pub client: reqwest::Client,
     /// Configuration of the relay
     pub config: Arc<RelayConfig>,
+    /// Urls of the relay, starting with the one of the entry
+    endpoints: Arc<Vec<RelayEndpoint>>,
+    /// Set when the relay rejected an SSZ request, JSON is used from then on
+    ssz_rejected: Arc<AtomicBool>,
 }
 
 impl RelayClient {
@@ -73,15 +139,63 @@ impl RelayClient {
             }
         }
 
-        let client = reqwest::Client::builder()
-            .default_headers(headers)
-            .timeout(DEFAULT_REQUEST_TIMEOUT)
-            .build()?;
+        let id = config.id.clone().unwrap_or(config.entry.id.clone());
+
+        let mut endpoints = vec![RelayEndpoint::new(&config.entry.url)?];
+        for url in &config.extra_urls {
+            if !url.username().is_empty() {
+                let pubkey = BlsPublicKey::from_hex(url.username())
+                    .wrap_err_with(|| format!("invalid pubkey in url {url} of relay {id}"))?;
+                ensure!(
+                    pubkey == config.entry.pubkey,
+                    "url {url} of relay {id} has another pubkey"
+                );
+            }
+            endpoints.push(RelayEndpoint::new(url.as_str())?);
+        }
+        let mut builder =
+            reqwest::Client::builder().default_headers(headers).timeout(DEFAULT_REQUEST_TIMEOUT);
+
+        if let Some(ca_cert_path) = &config.ca_cert_path {
+            builder = builder.use_rustls_tls();
+            for cert in load_certs(ca_cert_path)? {
+                builder = builder.add_root_certificate(Certificate::from_der(&cert)?);
+            }
+        }
+
+        match (&config.client_cert_path, &config.client_key_path) {
+            (Some(cert_path), Some(key_path)) => {
+                let mut pem = fs::read(cert_path)
+                    .wrap_err_with(|| format!("failed to read {}", cert_path.display()))?;
+                pem.push(b'\n');
+                pem.extend(
+                    fs::read(key_path)
+                        .wrap_err_with(|| format!("failed to read {}", key_path.display()))?,
+                );
+                let identity = Identity::from_pem(&pem)
+                    .wrap_err_with(|| format!("invalid client certificate for relay {id}"))?;
+                builder = builder.use_rustls_tls().identity(identity);
+            }
+            (None, None) => {}
+            _ => bail!("relay {id} needs both client_cert_path and client_key_path"),
+        }
+
+        if let Some(proxy) = &config.proxy {
+            let proxy = Proxy::all(proxy.clone())
+                .wrap_err_with(|| format!("invalid proxy for relay {id}"))?;
+            builder = builder.proxy(proxy);
+        }
+
+        if let Some(local_address) = config.local_address {
+            builder = builder.local_address(local_address);
+        }
 
         Ok(Self {
-            id: Arc::new(config.id.clone().unwrap_or(config.entry.id.clone())),
-            client,
+            id: Arc::new(id),
+            client: builder.build()?,
             config: Arc::new(config),
+            endpoints: Arc::new(endpoints),
+            ssz_rejected: Arc::new(AtomicBool::new(false)),
         })
     }
 
@@ -89,9 +203,43 @@ impl RelayClient {
         self.config.entry.pubkey
     }
 
-    // URL builders
+    /// All the urls of the relay, the first one is the url of the entry
+    pub fn endpoints(&self) -> &[RelayEndpoint] {
+        &self.endpoints
+    }
+
+    /// Encoding to use for get_header and submit_block requests
+    pub fn encoding(&self) -> EncodingType {
+        if self.config.enable_ssz && !self.ssz_rejected.load(Ordering::Relaxed) {
+            EncodingType::Ssz
+        } else {
+            EncodingType::Json
+        }
+    }
+
+    /// Checks whether an SSZ request was rejected by the relay, in which case
+    /// all following requests fall back to JSON. Returns true if the request
+    /// should be retried with JSON
+    pub fn check_ssz_rejected(&self, encoding: EncodingType, code: StatusCode) -> bool {
+        let rejected = encoding == EncodingType::Ssz &&
+            matches!(code, StatusCode::NOT_ACCEPTABLE | StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        if rejected && !self.ssz_rejected.swap(true, Ordering::Relaxed) {
+            warn!(relay_id = self.id.as_ref(), %code, "relay rejected SSZ, falling back to JSON");
+        }
+
+        rejected
+    }
+
+    /// Endpoint of the entry url, used for the requests that are not sent to
+    /// all the endpoints
+    pub fn primary_endpoint(&self) -> &RelayEndpoint {
+        &self.endpoints[0]
+    }
+
+    // URL builders, for the primary endpoint
     pub fn get_url(&self, path: &str) -> String {
-        format!("{}{path}", &self.config.entry.url)
+        self.primary_endpoint().get_url(path)
     }
 
     pub fn get_header_url(
@@ -100,19 +248,19 @@ impl RelayClient {
         parent_hash: B256,
         validator_pubkey: BlsPublicKey,
     ) -> String {
-        self.get_url(&format!("{BULDER_API_PATH}/header/{slot}/{parent_hash}/{validator_pubkey}"))
+        self.primary_endpoint().get_header_url(slot, parent_hash, validator_pubkey)
     }
 
     pub fn get_status_url(&self) -> String {
-        self.get_url(&format!("{BULDER_API_PATH}{GET_STATUS_PATH}"))
+        self.primary_endpoint().get_status_url()
     }
 
     pub fn register_validator_url(&self) -> String {
-        self.get_url(&format!("{BULDER_API_PATH}{REGISTER_VALIDATOR_PATH}"))
+        self.primary_endpoint().register_validator_url()
     }
 
     pub fn submit_block_url(&self) -> String {
-        self.get_url(&format!("{BULDER_API_PATH}{SUBMIT_BLOCK_PATH}"))
+        self.primary_endpoint().submit_block_url()
     }
 }
 
@@ -120,7 +268,8 @@ impl RelayClient {
 mod tests {
     use alloy::{primitives::hex::FromHex, rpc::types::beacon::BlsPublicKey};
 
-    use super::RelayEntry;
+    use super::{RelayClient, RelayEntry};
+    use crate::config::RelayConfig;
 
     #[test]
     fn test_relay_entry() {
@@ -132,4 +281,34 @@ mod tests {
         assert_eq!(parsed.url, s);
         assert_eq!(parsed.id, "abc.xyz");
     }
+
+    #[test]
+    fn test_relay_endpoints() {
+        let pubkey = "0xac6e77dfe25ecd6110b8e780608cce0dab71fdd5ebea22a16c0205200f2f8e2e3ad3b71d3499c54ad14d6c21b41a37ae";
+        let config = RelayConfig {
+            entry: serde_json::from_str(&format!("\"https://{pubkey}@abc.xyz\"")).unwrap(),
+            extra_urls: vec![
+                "https://eu.abc.xyz:8443/".parse().unwrap(),
+                format!("https://{pubkey}@us.abc.xyz").parse().unwrap(),
+            ],
+            ..Default::default()
+        };
+
+        let relay = RelayClient::new(config.clone()).unwrap();
+        let ids: Vec<_> = relay.endpoints().iter().map(|endpoint| endpoint.id.as_str()).collect();
+        assert_eq!(ids, ["abc.xyz", "eu.abc.xyz:8443", "us.abc.xyz"]);
+        assert_eq!(
+            relay.get_status_url(),
+            format!("https://{pubkey}@abc.xyz/eth/v1/builder/status")
+        );
+        assert_eq!(
+            relay.endpoints()[1].get_status_url(),
+            "https://eu.abc.xyz:8443/eth/v1/builder/status"
+        );
+
+        // urls of the same relay must have the same pubkey
+        let other = "https://0xa1cec75a3f0661e99299274182938151e8433c61a19222347ea1313d839229cb4ce4e3e5aa2bdeb71c8fcf1b084963c2@eu.abc.xyz";
+        let config = RelayConfig { extra_urls: vec![other.parse().unwrap()], ..config };
+        assert!(RelayClient::new(config).is_err());
+    }
 }
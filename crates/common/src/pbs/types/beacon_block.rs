@@ -1,54 +1,259 @@
 use alloy::{primitives::B256, rpc::types::beacon::BlsSignature};
 use serde::{Deserialize, Serialize};
+use ssz::{Decode, DecodeError, Encode, SszDecoderBuilder, SszEncoder, BYTES_PER_LENGTH_OFFSET};
 use ssz_derive::{Decode, Encode};
+use tree_hash::TreeHash;
+use tree_hash_derive::TreeHash;
 
 use super::{
-    blinded_block_body::BlindedBeaconBlockBody, blobs_bundle::BlobsBundle,
-    execution_payload::ExecutionPayload, spec::DenebSpec, utils::VersionedResponse,
+    blinded_block_body::{BlindedBeaconBlockBodyDeneb, BlindedBeaconBlockBodyElectra},
+    blobs_bundle::BlobsBundle,
+    execution_payload::ExecutionPayload,
+    kzg::KzgCommitment,
+    spec::{DenebSpec, ElectraSpec, EthSpec},
+    utils::Version,
 };
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
-/// Sent to relays in submit_block
-pub struct SignedBlindedBeaconBlock {
-    pub message: BlindedBeaconBlock,
-    pub signature: BlsSignature,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+/// Sent to relays in submit_block. Electra is tried first as it's a superset
+/// of the Deneb block
+pub enum SignedBlindedBeaconBlock {
+    Electra(SignedBlindedBeaconBlockElectra),
+    Deneb(SignedBlindedBeaconBlockDeneb),
+}
+
+impl Default for SignedBlindedBeaconBlock {
+    fn default() -> Self {
+        Self::Deneb(Default::default())
+    }
 }
 
 impl SignedBlindedBeaconBlock {
+    /// Decodes a JSON encoded block. If the fork is known (eg. from the
+    /// `Eth-Consensus-Version` header) only that fork is tried
+    pub fn from_json(data: &[u8], version: Option<Version>) -> serde_json::Result<Self> {
+        match version {
+            Some(Version::Deneb) => serde_json::from_slice(data).map(Self::Deneb),
+            Some(Version::Electra) => serde_json::from_slice(data).map(Self::Electra),
+            None => serde_json::from_slice(data),
+        }
+    }
+
+    /// Decodes an SSZ encoded block. If the fork is not known, Electra is tried
+    /// first
+    pub fn from_ssz_bytes(data: &[u8], version: Option<Version>) -> Result<Self, DecodeError> {
+        match version {
+            Some(Version::Deneb) => Decode::from_ssz_bytes(data).map(Self::Deneb),
+            Some(Version::Electra) => Decode::from_ssz_bytes(data).map(Self::Electra),
+            None => Decode::from_ssz_bytes(data)
+                .map(Self::Electra)
+                .or_else(|_| Decode::from_ssz_bytes(data).map(Self::Deneb)),
+        }
+    }
+
+    pub fn as_ssz_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Deneb(block) => block.as_ssz_bytes(),
+            Self::Electra(block) => block.as_ssz_bytes(),
+        }
+    }
+
+    pub fn version(&self) -> Version {
+        match self {
+            Self::Deneb(_) => Version::Deneb,
+            Self::Electra(_) => Version::Electra,
+        }
+    }
+
+    pub fn slot(&self) -> u64 {
+        match self {
+            Self::Deneb(block) => block.message.slot,
+            Self::Electra(block) => block.message.slot,
+        }
+    }
+
+    pub fn proposer_index(&self) -> u64 {
+        match self {
+            Self::Deneb(block) => block.message.proposer_index,
+            Self::Electra(block) => block.message.proposer_index,
+        }
+    }
+
     pub fn block_hash(&self) -> B256 {
-        self.message.body.execution_payload_header.block_hash
+        match self {
+            Self::Deneb(block) => block.message.body.execution_payload_header.block_hash,
+            Self::Electra(block) => block.message.body.execution_payload_header.block_hash,
+        }
+    }
+
+    pub fn parent_hash(&self) -> B256 {
+        match self {
+            Self::Deneb(block) => block.message.body.execution_payload_header.parent_hash,
+            Self::Electra(block) => block.message.body.execution_payload_header.parent_hash,
+        }
+    }
+
+    pub fn blob_kzg_commitments(&self) -> &[KzgCommitment] {
+        match self {
+            Self::Deneb(block) => &block.message.body.blob_kzg_commitments,
+            Self::Electra(block) => &block.message.body.blob_kzg_commitments,
+        }
+    }
+
+    pub fn signature(&self) -> &BlsSignature {
+        match self {
+            Self::Deneb(block) => &block.signature,
+            Self::Electra(block) => &block.signature,
+        }
+    }
+
+    /// Hash tree root of the block, signed by the proposer
+    pub fn message_root(&self) -> B256 {
+        let root = match self {
+            Self::Deneb(block) => block.message.tree_hash_root(),
+            Self::Electra(block) => block.message.tree_hash_root(),
+        };
+        B256::from(root.0)
     }
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
-pub struct BlindedBeaconBlock {
+pub struct SignedBlindedBeaconBlockDeneb {
+    pub message: BlindedBeaconBlockDeneb,
+    pub signature: BlsSignature,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+pub struct BlindedBeaconBlockDeneb {
     #[serde(with = "serde_utils::quoted_u64")]
     pub slot: u64,
     #[serde(with = "serde_utils::quoted_u64")]
     pub proposer_index: u64,
     pub parent_root: B256,
     pub state_root: B256,
-    pub body: BlindedBeaconBlockBody<DenebSpec>,
+    pub body: BlindedBeaconBlockBodyDeneb<DenebSpec>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct SignedBlindedBeaconBlockElectra {
+    pub message: BlindedBeaconBlockElectra,
+    pub signature: BlsSignature,
 }
 
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+pub struct BlindedBeaconBlockElectra {
+    #[serde(with = "serde_utils::quoted_u64")]
+    pub slot: u64,
+    #[serde(with = "serde_utils::quoted_u64")]
+    pub proposer_index: u64,
+    pub parent_root: B256,
+    pub state_root: B256,
+    pub body: BlindedBeaconBlockBodyElectra<ElectraSpec>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "version", content = "data")]
 /// Returned by relay in submit_block
-pub type SubmitBlindedBlockResponse = VersionedResponse<PayloadAndBlobs>;
+pub enum SubmitBlindedBlockResponse {
+    #[serde(rename = "deneb")]
+    Deneb(PayloadAndBlobs<DenebSpec>),
+    #[serde(rename = "electra")]
+    Electra(PayloadAndBlobs<ElectraSpec>),
+}
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
-pub struct PayloadAndBlobs {
-    pub execution_payload: ExecutionPayload<DenebSpec>,
-    pub blobs_bundle: Option<BlobsBundle<DenebSpec>>,
+impl Default for SubmitBlindedBlockResponse {
+    fn default() -> Self {
+        Self::Deneb(Default::default())
+    }
 }
 
 impl SubmitBlindedBlockResponse {
+    pub fn from_ssz_bytes(data: &[u8], version: Version) -> Result<Self, DecodeError> {
+        match version {
+            Version::Deneb => Decode::from_ssz_bytes(data).map(Self::Deneb),
+            Version::Electra => Decode::from_ssz_bytes(data).map(Self::Electra),
+        }
+    }
+
+    pub fn as_ssz_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Deneb(payload) => payload.as_ssz_bytes(),
+            Self::Electra(payload) => payload.as_ssz_bytes(),
+        }
+    }
+
+    pub fn version(&self) -> Version {
+        match self {
+            Self::Deneb(_) => Version::Deneb,
+            Self::Electra(_) => Version::Electra,
+        }
+    }
+
     pub fn block_hash(&self) -> B256 {
-        self.data.execution_payload.block_hash
+        match self {
+            Self::Deneb(payload) => payload.execution_payload.block_hash,
+            Self::Electra(payload) => payload.execution_payload.block_hash,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(bound = "T: EthSpec")]
+pub struct PayloadAndBlobs<T: EthSpec> {
+    pub execution_payload: ExecutionPayload<T>,
+    pub blobs_bundle: Option<BlobsBundle<T>>,
+}
+
+// The SSZ container in the builder specs has no optional fields, so a missing
+// bundle is encoded as an empty one
+impl<T: EthSpec> Encode for PayloadAndBlobs<T> {
+    fn is_ssz_fixed_len() -> bool {
+        false
+    }
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        let empty_bundle = BlobsBundle::default();
+        let blobs_bundle = self.blobs_bundle.as_ref().unwrap_or(&empty_bundle);
+
+        let mut encoder = SszEncoder::container(buf, 2 * BYTES_PER_LENGTH_OFFSET);
+        encoder.append(&self.execution_payload);
+        encoder.append(blobs_bundle);
+        encoder.finalize();
+    }
+
+    fn ssz_bytes_len(&self) -> usize {
+        let blobs_bundle_len = match &self.blobs_bundle {
+            Some(blobs_bundle) => blobs_bundle.ssz_bytes_len(),
+            None => BlobsBundle::<T>::default().ssz_bytes_len(),
+        };
+
+        2 * BYTES_PER_LENGTH_OFFSET + self.execution_payload.ssz_bytes_len() + blobs_bundle_len
+    }
+}
+
+impl<T: EthSpec> Decode for PayloadAndBlobs<T> {
+    fn is_ssz_fixed_len() -> bool {
+        false
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut builder = SszDecoderBuilder::new(bytes);
+        builder.register_type::<ExecutionPayload<T>>()?;
+        builder.register_type::<BlobsBundle<T>>()?;
+
+        let mut decoder = builder.build()?;
+        Ok(Self {
+            execution_payload: decoder.decode_next()?,
+            blobs_bundle: Some(decoder.decode_next()?),
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::{SignedBlindedBeaconBlock, SubmitBlindedBlockResponse};
+    use crate::pbs::types::Version;
 
     #[test]
     // this is from the builder api spec, but with sync_committee_bits fixed to
@@ -248,7 +453,8 @@ mod tests {
         "signature": "0x1b66ac1fb663c9bc59509846d6ec05345bd908eda73e670af888da41af171505cc411d61252fb6cb3fa0017b679f8bb2305b26a285fa2737f175668d0dff91cc1b66ac1fb663c9bc59509846d6ec05345bd908eda73e670af888da41af171505"
       }"#;
 
-        assert!(serde_json::from_str::<SignedBlindedBeaconBlock>(&data).is_ok());
+        let block = serde_json::from_str::<SignedBlindedBeaconBlock>(&data).unwrap();
+        assert_eq!(block.version(), Version::Deneb);
     }
 
     #[test]
@@ -622,6 +828,58 @@ mod tests {
             blob
         );
 
-        assert!(serde_json::from_str::<SubmitBlindedBlockResponse>(&data).is_ok());
+        let response = serde_json::from_str::<SubmitBlindedBlockResponse>(&data).unwrap();
+
+        let bytes = response.as_ssz_bytes();
+        let decoded = SubmitBlindedBlockResponse::from_ssz_bytes(&bytes, Version::Deneb).unwrap();
+        assert_eq!(decoded.block_hash(), response.block_hash());
+        assert_eq!(decoded.as_ssz_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_submit_blinded_block_response_ssz_no_blobs() {
+        let response = SubmitBlindedBlockResponse::default();
+
+        let bytes = response.as_ssz_bytes();
+        let SubmitBlindedBlockResponse::Deneb(decoded) =
+            SubmitBlindedBlockResponse::from_ssz_bytes(&bytes, Version::Deneb).unwrap()
+        else {
+            panic!("expected a deneb payload")
+        };
+        assert!(decoded.blobs_bundle.is_some_and(|bundle| bundle.blobs.is_empty()));
+    }
+
+    #[test]
+    fn test_signed_blinded_block_electra() {
+        let block = SignedBlindedBeaconBlock::Electra(Default::default());
+        let data = serde_json::to_vec(&block).unwrap();
+
+        let parsed = SignedBlindedBeaconBlock::from_json(&data, None).unwrap();
+        assert_eq!(parsed.version(), Version::Electra);
+
+        let parsed = SignedBlindedBeaconBlock::from_json(&data, Some(Version::Electra)).unwrap();
+        assert_eq!(parsed.version(), Version::Electra);
+
+        let block = SignedBlindedBeaconBlock::Deneb(Default::default());
+        let data = serde_json::to_vec(&block).unwrap();
+        let parsed = SignedBlindedBeaconBlock::from_json(&data, None).unwrap();
+        assert_eq!(parsed.version(), Version::Deneb);
+    }
+
+    #[test]
+    fn test_signed_blinded_block_ssz() {
+        let block = SignedBlindedBeaconBlock::Electra(Default::default());
+        let bytes = block.as_ssz_bytes();
+
+        let parsed = SignedBlindedBeaconBlock::from_ssz_bytes(&bytes, None).unwrap();
+        assert_eq!(parsed.version(), Version::Electra);
+        assert!(SignedBlindedBeaconBlock::from_ssz_bytes(&bytes, Some(Version::Deneb)).is_err());
+
+        let block = SignedBlindedBeaconBlock::Deneb(Default::default());
+        let bytes = block.as_ssz_bytes();
+
+        let parsed = SignedBlindedBeaconBlock::from_ssz_bytes(&bytes, None).unwrap();
+        assert_eq!(parsed.version(), Version::Deneb);
+        assert_eq!(parsed.as_ssz_bytes(), bytes);
     }
 }
@@ -1,18 +1,21 @@
 use alloy::{
-    primitives::{Address, B256},
+    primitives::B256,
     rpc::types::beacon::{BlsPublicKey, BlsSignature},
 };
+use ethereum_types::Address as EAddress;
 use serde::{Deserialize, Serialize};
 use ssz_derive::{Decode, Encode};
 use ssz_types::{typenum, BitList, BitVector, FixedVector, VariableList};
+use tree_hash_derive::TreeHash;
 
 use super::{
-    execution_payload::ExecutionPayloadHeader, kzg::KzgCommitments, spec::EthSpec, utils::*,
+    execution_payload::ExecutionPayloadHeader, execution_requests::ExecutionRequests,
+    kzg::KzgCommitments, spec::EthSpec, utils::*,
 };
 use crate::utils::as_str;
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
-pub struct BlindedBeaconBlockBody<T: EthSpec> {
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+pub struct BlindedBeaconBlockBodyDeneb<T: EthSpec> {
     pub randao_reveal: BlsSignature,
     pub eth1_data: Eth1Data,
     pub graffiti: B256,
@@ -28,7 +31,25 @@ pub struct BlindedBeaconBlockBody<T: EthSpec> {
     pub blob_kzg_commitments: KzgCommitments<T>,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+pub struct BlindedBeaconBlockBodyElectra<T: EthSpec> {
+    pub randao_reveal: BlsSignature,
+    pub eth1_data: Eth1Data,
+    pub graffiti: B256,
+    pub proposer_slashings: VariableList<ProposerSlashing, T::MaxProposerSlashings>,
+    pub attester_slashings: VariableList<AttesterSlashingElectra<T>, T::MaxAttesterSlashings>,
+    pub attestations: VariableList<AttestationElectra<T>, T::MaxAttestations>,
+    pub deposits: VariableList<Deposit, T::MaxDeposits>,
+    pub voluntary_exits: VariableList<SignedVoluntaryExit, T::MaxVoluntaryExits>,
+    pub sync_aggregate: SyncAggregate<T>,
+    pub execution_payload_header: ExecutionPayloadHeader<T>,
+    pub bls_to_execution_changes:
+        VariableList<SignedBlsToExecutionChange, T::MaxBlsToExecutionChanges>,
+    pub blob_kzg_commitments: KzgCommitments<T>,
+    pub execution_requests: ExecutionRequests<T>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 pub struct Eth1Data {
     pub deposit_root: B256,
     #[serde(with = "serde_utils::quoted_u64")]
@@ -36,7 +57,7 @@ pub struct Eth1Data {
     pub block_hash: B256,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 pub struct BeaconBlockHeader {
     #[serde(with = "serde_utils::quoted_u64")]
     pub slot: u64,
@@ -47,39 +68,39 @@ pub struct BeaconBlockHeader {
     pub body_root: B256,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 pub struct SignedBeaconBlockHeader {
     pub message: BeaconBlockHeader,
     pub signature: BlsSignature,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 pub struct BlsToExecutionChange {
     #[serde(with = "as_str")]
     pub validator_index: u64,
     pub from_bls_pubkey: BlsPublicKey,
-    pub to_execution_address: Address,
+    pub to_execution_address: EAddress,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 pub struct SignedBlsToExecutionChange {
     pub message: BlsToExecutionChange,
     pub signature: BlsSignature,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 pub struct ProposerSlashing {
     pub signed_header_1: SignedBeaconBlockHeader,
     pub signed_header_2: SignedBeaconBlockHeader,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 pub struct AttesterSlashing<T: EthSpec> {
     pub attestation_1: IndexedAttestation<T>,
     pub attestation_2: IndexedAttestation<T>,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 #[serde(bound = "T: EthSpec")]
 pub struct IndexedAttestation<T: EthSpec> {
     /// Lists validator registry indices, not committee indices.
@@ -89,7 +110,23 @@ pub struct IndexedAttestation<T: EthSpec> {
     pub signature: BlsSignature,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+pub struct AttesterSlashingElectra<T: EthSpec> {
+    pub attestation_1: IndexedAttestationElectra<T>,
+    pub attestation_2: IndexedAttestationElectra<T>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+#[serde(bound = "T: EthSpec")]
+pub struct IndexedAttestationElectra<T: EthSpec> {
+    /// Lists validator registry indices, not committee indices.
+    #[serde(with = "quoted_variable_list_u64")]
+    pub attesting_indices: VariableList<u64, T::MaxValidatorsPerSlot>,
+    pub data: AttestationData,
+    pub signature: BlsSignature,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 pub struct AttestationData {
     #[serde(with = "serde_utils::quoted_u64")]
     pub slot: u64,
@@ -102,14 +139,14 @@ pub struct AttestationData {
     pub target: Checkpoint,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 pub struct Checkpoint {
     #[serde(with = "serde_utils::quoted_u64")]
     pub epoch: u64,
     pub root: B256,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 #[serde(bound = "T: EthSpec")]
 pub struct Attestation<T: EthSpec> {
     pub aggregation_bits: BitList<T::MaxValidatorsPerCommittee>,
@@ -117,13 +154,22 @@ pub struct Attestation<T: EthSpec> {
     pub signature: BlsSignature,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+#[serde(bound = "T: EthSpec")]
+pub struct AttestationElectra<T: EthSpec> {
+    pub aggregation_bits: BitList<T::MaxValidatorsPerSlot>,
+    pub data: AttestationData,
+    pub signature: BlsSignature,
+    pub committee_bits: BitVector<T::MaxCommitteesPerSlot>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 pub struct Deposit {
     pub proof: FixedVector<B256, typenum::U33>, // put this in EthSpec?
     pub data: DepositData,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 pub struct DepositData {
     pub pubkey: BlsPublicKey,
     pub withdrawal_credentials: B256,
@@ -132,13 +178,13 @@ pub struct DepositData {
     pub signature: BlsSignature,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 pub struct SignedVoluntaryExit {
     pub message: VoluntaryExit,
     pub signature: BlsSignature,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 pub struct VoluntaryExit {
     /// Earliest epoch when voluntary exit can be processed.
     #[serde(with = "serde_utils::quoted_u64")]
@@ -147,7 +193,7 @@ pub struct VoluntaryExit {
     pub validator_index: u64,
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 #[serde(bound = "T: EthSpec")]
 pub struct SyncAggregate<T: EthSpec> {
     pub sync_committee_bits: BitVector<T::SyncCommitteeSize>,
@@ -3,6 +3,7 @@ use ethereum_types::{Address as EAddress, U256 as EU256};
 use serde::{Deserialize, Serialize};
 use ssz_derive::{Decode, Encode};
 use ssz_types::{FixedVector, VariableList};
+use tree_hash::TreeHash;
 use tree_hash_derive::TreeHash;
 
 use super::{spec::EthSpec, utils::*};
@@ -44,6 +45,44 @@ pub struct ExecutionPayload<T: EthSpec> {
     pub excess_blob_gas: u64,
 }
 
+impl<T: EthSpec> ExecutionPayload<T> {
+    /// Header of this payload, with the transactions and withdrawals replaced
+    /// by their SSZ roots
+    pub fn to_header(&self) -> ExecutionPayloadHeader<T> {
+        let withdrawals: VariableList<_, T::MaxWithdrawalsPerPayload> = self
+            .withdrawals
+            .iter()
+            .map(|withdrawal| WithdrawalTreeHash {
+                index: withdrawal.index,
+                validator_index: withdrawal.validator_index,
+                address: EAddress::from(withdrawal.address.0 .0),
+                amount: withdrawal.amount,
+            })
+            .collect::<Vec<_>>()
+            .into();
+
+        ExecutionPayloadHeader {
+            parent_hash: self.parent_hash,
+            fee_recipient: EAddress::from(self.fee_recipient.0 .0),
+            state_root: self.state_root,
+            receipts_root: self.receipts_root,
+            logs_bloom: self.logs_bloom.clone(),
+            prev_randao: self.prev_randao,
+            block_number: self.block_number,
+            gas_limit: self.gas_limit,
+            gas_used: self.gas_used,
+            timestamp: self.timestamp,
+            extra_data: self.extra_data.clone(),
+            base_fee_per_gas: EU256(*self.base_fee_per_gas.as_limbs()),
+            block_hash: self.block_hash,
+            transactions_root: B256::from(self.transactions.tree_hash_root().0),
+            withdrawals_root: B256::from(withdrawals.tree_hash_root().0),
+            blob_gas_used: self.blob_gas_used,
+            excess_blob_gas: self.excess_blob_gas,
+        }
+    }
+}
+
 pub type Transactions<T> = VariableList<
     Transaction<<T as EthSpec>::MaxBytesPerTransaction>,
     <T as EthSpec>::MaxTransactionsPerPayload,
@@ -61,6 +100,15 @@ pub struct Withdrawal {
     pub amount: u64,
 }
 
+/// Same as `Withdrawal`, with the field types used for tree hashing
+#[derive(TreeHash)]
+struct WithdrawalTreeHash {
+    index: u64,
+    validator_index: u64,
+    address: EAddress,
+    amount: u64,
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 pub struct ExecutionPayloadHeader<T: EthSpec> {
     pub parent_hash: B256,
@@ -91,11 +139,18 @@ pub struct ExecutionPayloadHeader<T: EthSpec> {
     pub excess_blob_gas: u64,
 }
 
+impl<T: EthSpec> ExecutionPayloadHeader<T> {
+    pub fn base_fee_per_gas(&self) -> U256 {
+        U256::from_limbs(self.base_fee_per_gas.0)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use ssz_types::VariableList;
     use tree_hash::TreeHash;
 
+    use super::*;
     use crate::pbs::types::{execution_payload::Transactions, spec::DenebSpec, EMPTY_TX_ROOT_HASH};
 
     #[test]
@@ -109,4 +164,21 @@ mod tests {
             "0x7ffe241ea60187fdb0187bfa22de35d1f9bed7ab061d9401fd47e34a54fbede1"
         );
     }
+
+    #[test]
+    fn test_to_header() {
+        let mut payload = ExecutionPayload::<DenebSpec> {
+            gas_limit: 30_000_000,
+            base_fee_per_gas: U256::from(7),
+            ..Default::default()
+        };
+        let header = payload.to_header();
+
+        assert_eq!(header.gas_limit, 30_000_000);
+        assert_eq!(header.base_fee_per_gas(), U256::from(7));
+        assert_eq!(header.transactions_root, B256::from(EMPTY_TX_ROOT_HASH));
+
+        payload.withdrawals.push(Withdrawal::default()).unwrap();
+        assert_ne!(payload.to_header().withdrawals_root, header.withdrawals_root);
+    }
 }
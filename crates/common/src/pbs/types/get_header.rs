@@ -1,17 +1,19 @@
 use alloy::{
-    primitives::{B256, U256},
+    primitives::{Address, B256, U256},
     rpc::types::beacon::{BlsPublicKey, BlsSignature},
 };
 use ethereum_types::U256 as EU256;
 use serde::{Deserialize, Serialize};
+use ssz::{Decode, DecodeError, Encode};
 use ssz_derive::{Decode, Encode};
 use tree_hash_derive::TreeHash;
 
 use super::{
     execution_payload::ExecutionPayloadHeader,
-    kzg::KzgCommitments,
-    spec::DenebSpec,
-    utils::{as_dec_str, VersionedResponse},
+    execution_requests::ExecutionRequests,
+    kzg::{KzgCommitment, KzgCommitments},
+    spec::{DenebSpec, ElectraSpec},
+    utils::{as_dec_str, Version},
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
@@ -19,33 +21,140 @@ pub struct GetHeaderParams {
     pub slot: u64,
     pub parent_hash: B256,
     pub pubkey: BlsPublicKey,
+    /// Percentage to multiply the bid value with before comparing it to the
+    /// local block value, from the `builder_boost_factor` query param
+    #[serde(default)]
+    pub builder_boost_factor: Option<u64>,
+    /// Value in wei of the locally built block, from the `local_block_value`
+    /// query param
+    #[serde(default)]
+    pub local_block_value: Option<U256>,
 }
 
+/// Optional query params of get_header
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy)]
+pub struct GetHeaderQuery {
+    pub builder_boost_factor: Option<u64>,
+    pub local_block_value: Option<U256>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "version", content = "data")]
 /// Returned by relay in get_header
-pub type GetHeaderReponse = VersionedResponse<SignedExecutionPayloadHeader>;
+pub enum GetHeaderReponse {
+    #[serde(rename = "deneb")]
+    Deneb(SignedExecutionPayloadHeaderDeneb),
+    #[serde(rename = "electra")]
+    Electra(SignedExecutionPayloadHeaderElectra),
+}
+
+impl Default for GetHeaderReponse {
+    fn default() -> Self {
+        Self::Deneb(Default::default())
+    }
+}
 
 impl GetHeaderReponse {
+    pub fn from_ssz_bytes(data: &[u8], version: Version) -> Result<Self, DecodeError> {
+        match version {
+            Version::Deneb => Decode::from_ssz_bytes(data).map(Self::Deneb),
+            Version::Electra => Decode::from_ssz_bytes(data).map(Self::Electra),
+        }
+    }
+
+    pub fn as_ssz_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Deneb(bid) => bid.as_ssz_bytes(),
+            Self::Electra(bid) => bid.as_ssz_bytes(),
+        }
+    }
+
+    pub fn version(&self) -> Version {
+        match self {
+            Self::Deneb(_) => Version::Deneb,
+            Self::Electra(_) => Version::Electra,
+        }
+    }
+
     pub fn block_hash(&self) -> B256 {
-        self.data.message.header.block_hash
+        match self {
+            Self::Deneb(bid) => bid.message.header.block_hash,
+            Self::Electra(bid) => bid.message.header.block_hash,
+        }
+    }
+
+    pub fn parent_hash(&self) -> B256 {
+        match self {
+            Self::Deneb(bid) => bid.message.header.parent_hash,
+            Self::Electra(bid) => bid.message.header.parent_hash,
+        }
+    }
+
+    pub fn fee_recipient(&self) -> Address {
+        match self {
+            Self::Deneb(bid) => Address::from(bid.message.header.fee_recipient.0),
+            Self::Electra(bid) => Address::from(bid.message.header.fee_recipient.0),
+        }
+    }
+
+    pub fn gas_limit(&self) -> u64 {
+        match self {
+            Self::Deneb(bid) => bid.message.header.gas_limit,
+            Self::Electra(bid) => bid.message.header.gas_limit,
+        }
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        match self {
+            Self::Deneb(bid) => bid.message.header.timestamp,
+            Self::Electra(bid) => bid.message.header.timestamp,
+        }
+    }
+
+    pub fn transactions_root(&self) -> B256 {
+        match self {
+            Self::Deneb(bid) => bid.message.header.transactions_root,
+            Self::Electra(bid) => bid.message.header.transactions_root,
+        }
+    }
+
+    pub fn blob_kzg_commitments(&self) -> &[KzgCommitment] {
+        match self {
+            Self::Deneb(bid) => &bid.message.blob_kzg_commitments,
+            Self::Electra(bid) => &bid.message.blob_kzg_commitments,
+        }
     }
 
     pub fn pubkey(&self) -> BlsPublicKey {
-        self.data.message.pubkey
+        match self {
+            Self::Deneb(bid) => bid.message.pubkey,
+            Self::Electra(bid) => bid.message.pubkey,
+        }
     }
 
     pub fn value(&self) -> U256 {
-        self.data.message.value()
+        match self {
+            Self::Deneb(bid) => bid.message.value(),
+            Self::Electra(bid) => bid.message.value(),
+        }
+    }
+
+    pub fn signature(&self) -> &BlsSignature {
+        match self {
+            Self::Deneb(bid) => &bid.signature,
+            Self::Electra(bid) => &bid.signature,
+        }
     }
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
-pub struct SignedExecutionPayloadHeader {
-    pub message: ExecutionPayloadHeaderMessage,
+pub struct SignedExecutionPayloadHeaderDeneb {
+    pub message: ExecutionPayloadHeaderMessageDeneb,
     pub signature: BlsSignature,
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
-pub struct ExecutionPayloadHeaderMessage {
+pub struct ExecutionPayloadHeaderMessageDeneb {
     pub header: ExecutionPayloadHeader<DenebSpec>,
     pub blob_kzg_commitments: KzgCommitments<DenebSpec>,
     #[serde(with = "as_dec_str")]
@@ -53,7 +162,34 @@ pub struct ExecutionPayloadHeaderMessage {
     pub pubkey: BlsPublicKey,
 }
 
-impl ExecutionPayloadHeaderMessage {
+impl ExecutionPayloadHeaderMessageDeneb {
+    pub fn value(&self) -> U256 {
+        U256::from_limbs(self.value.0)
+    }
+
+    // FIMXE: only used in test
+    pub fn set_value(&mut self, value: U256) {
+        self.value = EU256::from_little_endian(&value.to_le_bytes::<32>())
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct SignedExecutionPayloadHeaderElectra {
+    pub message: ExecutionPayloadHeaderMessageElectra,
+    pub signature: BlsSignature,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+pub struct ExecutionPayloadHeaderMessageElectra {
+    pub header: ExecutionPayloadHeader<ElectraSpec>,
+    pub blob_kzg_commitments: KzgCommitments<ElectraSpec>,
+    pub execution_requests: ExecutionRequests<ElectraSpec>,
+    #[serde(with = "as_dec_str")]
+    value: EU256,
+    pub pubkey: BlsPublicKey,
+}
+
+impl ExecutionPayloadHeaderMessageElectra {
     pub fn value(&self) -> U256 {
         U256::from_limbs(self.value.0)
     }
@@ -69,7 +205,7 @@ mod tests {
     use alloy::primitives::U256;
 
     use super::GetHeaderReponse;
-    use crate::{signature::verify_signed_builder_message, types::Chain};
+    use crate::{pbs::types::Version, signature::verify_signed_builder_message, types::Chain};
 
     #[test]
     fn test_get_header() {
@@ -111,7 +247,11 @@ mod tests {
             }
         }"#;
 
-        let parsed = serde_json::from_str::<GetHeaderReponse>(&data).unwrap().data;
+        let GetHeaderReponse::Deneb(parsed) =
+            serde_json::from_str::<GetHeaderReponse>(&data).unwrap()
+        else {
+            panic!("expected a deneb bid")
+        };
 
         assert_eq!(parsed.message.value(), U256::from(4293912964927787u64));
 
@@ -123,4 +263,25 @@ mod tests {
         )
         .is_ok())
     }
+
+    #[test]
+    fn test_get_header_electra() {
+        let mut bid = GetHeaderReponse::Electra(Default::default());
+        if let GetHeaderReponse::Electra(bid) = &mut bid {
+            bid.message.set_value(U256::from(1));
+        }
+
+        let data = serde_json::to_string(&bid).unwrap();
+        let parsed = serde_json::from_str::<GetHeaderReponse>(&data).unwrap();
+
+        assert_eq!(parsed.version(), Version::Electra);
+        assert_eq!(parsed.value(), U256::from(1));
+
+        let bytes = bid.as_ssz_bytes();
+        let parsed = GetHeaderReponse::from_ssz_bytes(&bytes, Version::Electra).unwrap();
+        assert_eq!(parsed.value(), U256::from(1));
+        assert!(GetHeaderReponse::from_ssz_bytes(&bytes, Version::Deneb).is_err());
+        assert!(data.contains(r#""version":"electra""#));
+        assert!(data.contains("execution_requests"));
+    }
 }
@@ -3,12 +3,14 @@ use std::{
     str::FromStr,
 };
 
+use c_kzg::{ethereum_kzg_settings, Blob, Bytes48};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use ssz_derive::{Decode, Encode};
 use ssz_types::VariableList;
+use thiserror::Error;
 use tree_hash::{PackedEncoding, TreeHash};
 
-use super::spec::EthSpec;
+use super::{spec::EthSpec, BlobsBundle};
 
 pub const BYTES_PER_COMMITMENT: usize = 48;
 #[derive(Clone, Encode, Decode, Eq, PartialEq)]
@@ -158,3 +160,77 @@ impl FromStr for KzgProof {
         }
     }
 }
+
+// VERIFICATION
+#[derive(Debug, Error)]
+pub enum KzgError {
+    #[error("invalid blobs bundle: {0}")]
+    InvalidBundle(c_kzg::Error),
+
+    #[error("blob KZG proofs don't match the commitments")]
+    InvalidProofs,
+}
+
+/// Verifies that the blobs in the bundle match their commitments and proofs,
+/// as a single batch against the Ethereum trusted setup
+pub fn verify_blobs_bundle<T: EthSpec>(bundle: &BlobsBundle<T>) -> Result<(), KzgError> {
+    let blobs = bundle
+        .blobs
+        .iter()
+        .map(|blob| Blob::from_bytes(blob))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(KzgError::InvalidBundle)?;
+    let commitments: Vec<_> = bundle.commitments.iter().map(|c| Bytes48::from(c.0)).collect();
+    let proofs: Vec<_> = bundle.proofs.iter().map(|p| Bytes48::from(p.0)).collect();
+
+    let valid = c_kzg::KzgProof::verify_blob_kzg_proof_batch(
+        &blobs,
+        &commitments,
+        &proofs,
+        ethereum_kzg_settings(),
+    )
+    .map_err(KzgError::InvalidBundle)?;
+
+    if valid {
+        Ok(())
+    } else {
+        Err(KzgError::InvalidProofs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ssz_types::FixedVector;
+
+    use super::*;
+    use crate::pbs::DenebSpec;
+
+    /// The commitment and proof of the zero blob are both the point at infinity
+    fn zero_blob_bundle() -> BlobsBundle<DenebSpec> {
+        let mut infinity = [0; 48];
+        infinity[0] = 0xc0;
+
+        BlobsBundle {
+            commitments: VariableList::new(vec![KzgCommitment(infinity)]).unwrap(),
+            proofs: VariableList::new(vec![KzgProof(infinity)]).unwrap(),
+            blobs: VariableList::new(vec![FixedVector::default()]).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_verify_blobs_bundle() {
+        assert!(verify_blobs_bundle(&BlobsBundle::<DenebSpec>::default()).is_ok());
+        assert!(verify_blobs_bundle(&zero_blob_bundle()).is_ok());
+    }
+
+    #[test]
+    fn test_verify_blobs_bundle_invalid() {
+        let mut bundle = zero_blob_bundle();
+        bundle.blobs[0][31] = 1;
+        assert!(matches!(verify_blobs_bundle(&bundle), Err(KzgError::InvalidProofs)));
+
+        let mut bundle = zero_blob_bundle();
+        bundle.proofs[0].0[0] = 0;
+        assert!(matches!(verify_blobs_bundle(&bundle), Err(KzgError::InvalidBundle(_))));
+    }
+}
@@ -2,11 +2,22 @@ mod beacon_block;
 mod blinded_block_body;
 mod blobs_bundle;
 mod execution_payload;
+mod execution_requests;
 mod get_header;
 mod kzg;
 mod spec;
 mod utils;
 
-pub use beacon_block::{SignedBlindedBeaconBlock, SubmitBlindedBlockResponse};
-pub use execution_payload::EMPTY_TX_ROOT_HASH;
-pub use get_header::{GetHeaderParams, GetHeaderReponse, SignedExecutionPayloadHeader};
+pub use beacon_block::{
+    PayloadAndBlobs, SignedBlindedBeaconBlock, SignedBlindedBeaconBlockDeneb,
+    SignedBlindedBeaconBlockElectra, SubmitBlindedBlockResponse,
+};
+pub use blobs_bundle::BlobsBundle;
+pub use execution_payload::{ExecutionPayload, ExecutionPayloadHeader, EMPTY_TX_ROOT_HASH};
+pub use get_header::{
+    GetHeaderParams, GetHeaderQuery, GetHeaderReponse, SignedExecutionPayloadHeaderDeneb,
+    SignedExecutionPayloadHeaderElectra,
+};
+pub use kzg::{verify_blobs_bundle, KzgCommitment, KzgError};
+pub use spec::{DenebSpec, ElectraSpec, EthSpec};
+pub use utils::Version;
@@ -18,6 +18,12 @@ pub trait EthSpec {
     type MaxBytesPerTransaction: typenum::Unsigned + std::fmt::Debug;
     type MaxTransactionsPerPayload: typenum::Unsigned + std::fmt::Debug;
     type BytesPerBlob: typenum::Unsigned + std::fmt::Debug;
+    // Electra
+    type MaxCommitteesPerSlot: typenum::Unsigned + std::fmt::Debug;
+    type MaxValidatorsPerSlot: typenum::Unsigned + std::fmt::Debug;
+    type MaxDepositRequestsPerPayload: typenum::Unsigned + std::fmt::Debug;
+    type MaxWithdrawalRequestsPerPayload: typenum::Unsigned + std::fmt::Debug;
+    type MaxConsolidationRequestsPerPayload: typenum::Unsigned + std::fmt::Debug;
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
@@ -39,4 +45,36 @@ impl EthSpec for DenebSpec {
     type MaxBytesPerTransaction = typenum::U1073741824;
     type MaxTransactionsPerPayload = typenum::U1048576;
     type BytesPerBlob = typenum::U131072;
+    // not used before Electra
+    type MaxCommitteesPerSlot = typenum::U64;
+    type MaxValidatorsPerSlot = typenum::U131072;
+    type MaxDepositRequestsPerPayload = typenum::U8192;
+    type MaxWithdrawalRequestsPerPayload = typenum::U16;
+    type MaxConsolidationRequestsPerPayload = typenum::U2;
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct ElectraSpec;
+
+impl EthSpec for ElectraSpec {
+    type MaxValidatorsPerCommittee = typenum::U2048;
+    type MaxProposerSlashings = typenum::U16;
+    type MaxAttesterSlashings = typenum::U1;
+    type MaxAttestations = typenum::U8;
+    type MaxDeposits = typenum::U16;
+    type MaxVoluntaryExits = typenum::U16;
+    type SyncCommitteeSize = typenum::U512;
+    type MaxExtraDataBytes = typenum::U32;
+    type MaxBlobCommitmentsPerBlock = typenum::U4096;
+    type BytesPerLogsBloom = typenum::U256;
+    type MaxBlsToExecutionChanges = typenum::U16;
+    type MaxWithdrawalsPerPayload = typenum::U16;
+    type MaxBytesPerTransaction = typenum::U1073741824;
+    type MaxTransactionsPerPayload = typenum::U1048576;
+    type BytesPerBlob = typenum::U131072;
+    type MaxCommitteesPerSlot = typenum::U64;
+    type MaxValidatorsPerSlot = typenum::U131072;
+    type MaxDepositRequestsPerPayload = typenum::U8192;
+    type MaxWithdrawalRequestsPerPayload = typenum::U16;
+    type MaxConsolidationRequestsPerPayload = typenum::U2;
 }
@@ -1,3 +1,5 @@
+use std::{fmt, str::FromStr};
+
 use serde::{Deserialize, Serialize};
 
 pub mod quoted_variable_list_u64 {
@@ -50,15 +52,39 @@ pub mod as_dec_str {
     }
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
-pub struct VersionedResponse<T> {
-    pub version: Version,
-    pub data: T,
-}
-
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Version {
     #[serde(rename = "deneb")]
     #[default]
     Deneb,
+    #[serde(rename = "electra")]
+    Electra,
+}
+
+impl Version {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Version::Deneb => "deneb",
+            Version::Electra => "electra",
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parses the value of the `Eth-Consensus-Version` header
+impl FromStr for Version {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "deneb" => Ok(Version::Deneb),
+            "electra" => Ok(Version::Electra),
+            other => Err(format!("unsupported fork version: {other}")),
+        }
+    }
 }
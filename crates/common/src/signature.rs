@@ -1,16 +1,20 @@
-use alloy::rpc::types::beacon::{constants::BLS_DST_SIG, BlsPublicKey, BlsSignature};
+use alloy::rpc::types::beacon::{
+    constants::BLS_DST_SIG, relay::ValidatorRegistration, BlsPublicKey, BlsSignature,
+};
 use blst::{
     min_pk::{PublicKey, SecretKey, Signature},
     BLST_ERROR,
 };
+use ethereum_types::Address as EAddress;
 use rand::RngCore;
 use ssz_derive::{Decode, Encode};
 use tree_hash::TreeHash;
 use tree_hash_derive::TreeHash;
 
 use crate::{
-    constants::{APPLICATION_BUILDER_DOMAIN, GENESIS_VALIDATORS_ROOT},
+    constants::{APPLICATION_BUILDER_DOMAIN, DOMAIN_BEACON_PROPOSER, GENESIS_VALIDATORS_ROOT},
     error::BlstErrorWrapper,
+    pbs::SignedBlindedBeaconBlock,
     types::Chain,
     utils::{alloy_pubkey_to_blst, alloy_sig_to_blst},
 };
@@ -65,13 +69,15 @@ struct ForkData {
     genesis_validators_root: [u8; 32],
 }
 
-#[allow(dead_code)]
-fn compute_builder_domain(chain: Chain) -> [u8; 32] {
+fn compute_domain(
+    domain_type: [u8; 4],
+    fork_version: [u8; 4],
+    genesis_validators_root: [u8; 32],
+) -> [u8; 32] {
     let mut domain = [0u8; 32];
-    domain[..4].copy_from_slice(&APPLICATION_BUILDER_DOMAIN);
+    domain[..4].copy_from_slice(&domain_type);
 
-    let fork_version = chain.fork_version();
-    let fd = ForkData { fork_version, genesis_validators_root: GENESIS_VALIDATORS_ROOT };
+    let fd = ForkData { fork_version, genesis_validators_root };
     let fork_data_root = fd.tree_hash_root();
 
     domain[4..].copy_from_slice(&fork_data_root[..28]);
@@ -79,6 +85,28 @@ fn compute_builder_domain(chain: Chain) -> [u8; 32] {
     domain
 }
 
+#[allow(dead_code)]
+fn compute_builder_domain(chain: Chain) -> [u8; 32] {
+    compute_domain(APPLICATION_BUILDER_DOMAIN, chain.fork_version(), GENESIS_VALIDATORS_ROOT)
+}
+
+/// Domain of the blocks proposed at the given slot, `None` if the fork
+/// schedule or the genesis validators root of the chain are not known
+pub fn compute_beacon_proposer_domain(chain: Chain, slot: u64) -> Option<[u8; 32]> {
+    let fork_version = chain.fork_version_at_slot(slot)?;
+    let genesis_validators_root = chain.genesis_validators_root()?;
+    Some(compute_domain(DOMAIN_BEACON_PROPOSER, fork_version, genesis_validators_root))
+}
+
+pub fn verify_proposer_signature(
+    domain: [u8; 32],
+    pubkey: &BlsPublicKey,
+    block: &SignedBlindedBeaconBlock,
+) -> Result<(), BlstErrorWrapper> {
+    let signing_root = compute_signing_root(block.message_root().0, domain);
+    verify_signature(pubkey, &signing_root, block.signature())
+}
+
 pub fn verify_signed_builder_message<T: TreeHash>(
     chain: Chain,
     pubkey: &BlsPublicKey,
@@ -91,6 +119,32 @@ pub fn verify_signed_builder_message<T: TreeHash>(
     verify_signature(pubkey, &signing_root, signature)
 }
 
+/// Same as `ValidatorRegistrationMessage`, with the field types used for tree
+/// hashing
+#[derive(TreeHash)]
+struct ValidatorRegistrationTreeHash {
+    fee_recipient: EAddress,
+    gas_limit: u64,
+    timestamp: u64,
+    pubkey: BlsPublicKey,
+}
+
+/// Verifies a registration signed by the validator, in the builder domain
+pub fn verify_validator_registration(
+    chain: Chain,
+    registration: &ValidatorRegistration,
+) -> Result<(), BlstErrorWrapper> {
+    let message = &registration.message;
+    let msg = ValidatorRegistrationTreeHash {
+        fee_recipient: EAddress::from(message.fee_recipient.0 .0),
+        gas_limit: message.gas_limit,
+        timestamp: message.timestamp,
+        pubkey: message.pubkey,
+    };
+
+    verify_signed_builder_message(chain, &message.pubkey, &msg, &registration.signature)
+}
+
 pub fn sign_builder_message(
     chain: Chain,
     secret_key: &SecretKey,
@@ -112,8 +166,10 @@ pub fn sign_builder_root(
 #[cfg(test)]
 mod tests {
 
-    use super::compute_builder_domain;
-    use crate::types::Chain;
+    use alloy::primitives::b256;
+
+    use super::*;
+    use crate::utils::blst_pubkey_to_alloy;
 
     #[test]
     fn test_builder_domains() {
@@ -122,4 +178,48 @@ mod tests {
         assert_eq!(compute_builder_domain(Chain::Rhea), Chain::Rhea.builder_domain());
         assert_eq!(compute_builder_domain(Chain::Helder), Chain::Helder.builder_domain());
     }
+
+    #[test]
+    fn test_beacon_proposer_domains() {
+        // Deneb on mainnet and Electra on Holesky
+        assert_eq!(
+            compute_beacon_proposer_domain(Chain::Mainnet, 0),
+            Some(b256!("000000006a95a1a967855d676d48be69883b712607f952d5198d0f5677564636").0)
+        );
+        assert_eq!(
+            compute_beacon_proposer_domain(Chain::Holesky, 115968 * 32),
+            Some(b256!("00000000019e21ada5c73dd2b07fd515e7cd6d5f1eeb22e1fc0cfcfac4e03667").0)
+        );
+        assert_eq!(compute_beacon_proposer_domain(Chain::Helder, 0), None);
+    }
+
+    #[test]
+    fn test_verify_proposer_signature() {
+        let secret_key = random_secret();
+        let pubkey = blst_pubkey_to_alloy(&secret_key.sk_to_pk());
+        let domain = compute_beacon_proposer_domain(Chain::Holesky, 0).unwrap();
+
+        let mut block = SignedBlindedBeaconBlock::default();
+        let signing_root = compute_signing_root(block.message_root().0, domain);
+        let SignedBlindedBeaconBlock::Deneb(inner) = &mut block else { unreachable!() };
+        inner.signature = sign_message(&secret_key, &signing_root);
+
+        assert!(verify_proposer_signature(domain, &pubkey, &block).is_ok());
+
+        let other_domain = compute_beacon_proposer_domain(Chain::Mainnet, 0).unwrap();
+        assert!(verify_proposer_signature(other_domain, &pubkey, &block).is_err());
+    }
+
+    #[test]
+    fn test_verify_validator_registration() {
+        let data = include_str!("../../../tests/data/registration_holesky.json");
+        let mut registrations: Vec<ValidatorRegistration> = serde_json::from_str(data).unwrap();
+
+        for registration in &registrations {
+            assert!(verify_validator_registration(Chain::Holesky, registration).is_ok());
+        }
+
+        registrations[0].message.gas_limit += 1;
+        assert!(verify_validator_registration(Chain::Holesky, &registrations[0]).is_err());
+    }
 }
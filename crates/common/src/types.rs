@@ -2,11 +2,17 @@ use derive_more::{Deref, From, Into, Display};
 
 use serde::{Deserialize, Serialize};
 
-use crate::constants::{
-    HELDER_BUILDER_DOMAIN, HELDER_FORK_VERSION, HELDER_GENESIS_TIME_SECONDS,
-    HOLESKY_BUILDER_DOMAIN, HOLESKY_FORK_VERSION, HOLESKY_GENESIS_TIME_SECONDS,
-    MAINNET_BUILDER_DOMAIN, MAINNET_FORK_VERSION, MAINNET_GENESIS_TIME_SECONDS,
-    RHEA_BUILDER_DOMAIN, RHEA_FORK_VERSION, RHEA_GENESIS_TIME_SECONDS,
+use crate::{
+    constants::{
+        HELDER_BUILDER_DOMAIN, HELDER_FORK_VERSION, HELDER_GENESIS_TIME_SECONDS,
+        HOLESKY_BUILDER_DOMAIN, HOLESKY_DENEB_FORK_VERSION, HOLESKY_ELECTRA_FORK_EPOCH,
+        HOLESKY_ELECTRA_FORK_VERSION, HOLESKY_FORK_VERSION, HOLESKY_GENESIS_TIME_SECONDS,
+        HOLESKY_GENESIS_VALIDATORS_ROOT, MAINNET_BUILDER_DOMAIN, MAINNET_DENEB_FORK_VERSION,
+        MAINNET_ELECTRA_FORK_EPOCH, MAINNET_ELECTRA_FORK_VERSION, MAINNET_FORK_VERSION,
+        MAINNET_GENESIS_TIME_SECONDS, MAINNET_GENESIS_VALIDATORS_ROOT, RHEA_BUILDER_DOMAIN,
+        RHEA_FORK_VERSION, RHEA_GENESIS_TIME_SECONDS, SLOTS_PER_EPOCH,
+    },
+    pbs::Version,
 };
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -44,6 +50,44 @@ impl Chain {
             Chain::Helder => HELDER_GENESIS_TIME_SECONDS,
         }
     }
+
+    /// Epoch of the Electra hard fork, `None` if not scheduled
+    pub fn electra_fork_epoch(&self) -> Option<u64> {
+        match self {
+            Chain::Mainnet => Some(MAINNET_ELECTRA_FORK_EPOCH),
+            Chain::Holesky => Some(HOLESKY_ELECTRA_FORK_EPOCH),
+            Chain::Rhea | Chain::Helder => None,
+        }
+    }
+
+    /// Fork active at the given slot, used to pick the builder API types
+    pub fn fork_at_slot(&self, slot: u64) -> Version {
+        match self.electra_fork_epoch() {
+            Some(epoch) if slot / SLOTS_PER_EPOCH >= epoch => Version::Electra,
+            _ => Version::Deneb,
+        }
+    }
+
+    /// Genesis validators root, `None` if not known for this chain
+    pub fn genesis_validators_root(&self) -> Option<[u8; 32]> {
+        match self {
+            Chain::Mainnet => Some(MAINNET_GENESIS_VALIDATORS_ROOT),
+            Chain::Holesky => Some(HOLESKY_GENESIS_VALIDATORS_ROOT),
+            Chain::Rhea | Chain::Helder => None,
+        }
+    }
+
+    /// Fork version active at the given slot, `None` if not known for this
+    /// chain
+    pub fn fork_version_at_slot(&self, slot: u64) -> Option<[u8; 4]> {
+        match (self, self.fork_at_slot(slot)) {
+            (Chain::Mainnet, Version::Deneb) => Some(MAINNET_DENEB_FORK_VERSION),
+            (Chain::Mainnet, Version::Electra) => Some(MAINNET_ELECTRA_FORK_VERSION),
+            (Chain::Holesky, Version::Deneb) => Some(HOLESKY_DENEB_FORK_VERSION),
+            (Chain::Holesky, Version::Electra) => Some(HOLESKY_ELECTRA_FORK_VERSION),
+            (Chain::Rhea | Chain::Helder, _) => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Display, PartialEq, Eq, Hash, Deref, From, Into, Serialize, Deserialize)]
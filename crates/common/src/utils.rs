@@ -1,23 +1,50 @@
 use std::{
     env,
-    time::{SystemTime, UNIX_EPOCH},
+    future::{pending, Future},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use alloy::{
     primitives::U256,
     rpc::types::beacon::{BlsPublicKey, BlsSignature},
 };
-use axum::http::HeaderValue;
+use axum::{
+    http::{HeaderName, HeaderValue},
+    Router,
+};
+use axum_server::{tls_rustls::RustlsConfig, Handle};
 use blst::min_pk::{PublicKey, Signature};
+use opentelemetry::{
+    global,
+    propagation::{Extractor, Injector},
+    trace::{TraceError, TracerProvider as _},
+    KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    runtime,
+    trace::{self as sdktrace, TracerProvider},
+    Resource,
+};
 use rand::{distributions::Alphanumeric, Rng};
 use reqwest::header::HeaderMap;
-use tracing::Level;
+use rustls::ServerConfig;
+use tokio::{
+    net::TcpListener,
+    signal::unix::{signal, SignalKind},
+    sync::oneshot,
+    time::sleep,
+};
+use tracing::{error, info, warn, Level, Span};
 use tracing_appender::{non_blocking::WorkerGuard, rolling::Rotation};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use tracing_subscriber::{fmt::Layer, prelude::*, EnvFilter};
 
 use crate::{
     config::{default_log_level, RollingDuration, CB_BASE_LOG_PATH},
-    pbs::HEAVER_VERSION_VALUE,
+    pbs::{Version, HEADER_CONSENSUS_VERSION, HEAVER_VERSION_VALUE},
     types::Chain,
 };
 
@@ -30,6 +57,8 @@ pub const MAX_LOG_FILES_ENV: &str = "MAX_LOG_FILES";
 
 pub const RUST_LOG_ENV: &str = "RUST_LOG";
 
+pub const OTLP_ENDPOINT_ENV: &str = "CB_OTLP_ENDPOINT";
+
 pub fn timestamp_of_slot_start_millis(slot: u64, chain: Chain) -> u64 {
     let seconds_since_genesis = chain.genesis_time_sec() + slot * SECONDS_PER_SLOT;
     seconds_since_genesis * MILLIS_PER_SECOND
@@ -38,6 +67,10 @@ pub fn ms_into_slot(slot: u64, chain: Chain) -> u64 {
     let slot_start_ms = timestamp_of_slot_start_millis(slot, chain);
     utcnow_ms().saturating_sub(slot_start_ms)
 }
+/// Slot at the current time, 0 before genesis
+pub fn current_slot(chain: Chain) -> u64 {
+    utcnow_sec().saturating_sub(chain.genesis_time_sec()) / SECONDS_PER_SLOT
+}
 
 /// Seconds
 pub fn utcnow_sec() -> u64 {
@@ -126,7 +159,27 @@ pub const fn default_u256() -> U256 {
 }
 
 // LOGGING
-pub fn initialize_tracing_log(module_id: &str) -> WorkerGuard {
+/// Flushes the buffered file logs and the spans not exported yet when dropped
+pub struct TracingGuard {
+    _log_guard: WorkerGuard,
+    tracer_provider: Option<TracerProvider>,
+}
+
+impl Drop for TracingGuard {
+    fn drop(&mut self) {
+        if let Some(tracer_provider) = self.tracer_provider.take() {
+            if let Err(err) = tracer_provider.shutdown() {
+                eprintln!("Failed to export spans: {err}");
+            }
+        }
+    }
+}
+
+/// Logs to stdout and to a rolling log file. If `CB_OTLP_ENDPOINT` is set,
+/// spans are also exported to an OpenTelemetry collector, and the trace
+/// context is propagated with `traceparent` headers. Must be called from a
+/// tokio runtime
+pub fn initialize_tracing_log(module_id: &str) -> TracingGuard {
     // Log all events to a rolling log file.
     let mut builder =
         tracing_appender::rolling::Builder::new().filename_prefix(module_id.to_lowercase());
@@ -177,9 +230,163 @@ pub fn initialize_tracing_log(module_id: &str) -> WorkerGuard {
         .with_writer(default_writer)
         .with_filter(file_log_filter);
 
-    tracing_subscriber::registry().with(stdout_log.and_then(file_log)).init();
+    let tracer_provider = env::var(OTLP_ENDPOINT_ENV).ok().and_then(|endpoint| {
+        match otlp_tracer_provider(module_id, &endpoint) {
+            Ok(tracer_provider) => Some(tracer_provider),
+            Err(err) => {
+                eprintln!("Failed to start OTLP exporter to {endpoint}: {err}");
+                None
+            }
+        }
+    });
+    let otlp_layer = tracer_provider.as_ref().map(|tracer_provider| {
+        let otlp_log_filter = format_crates_filter(Level::INFO.as_str(), file_log_level.as_str());
+        tracing_opentelemetry::layer()
+            .with_tracer(tracer_provider.tracer(module_id.to_lowercase()))
+            .with_filter(otlp_log_filter)
+    });
+
+    tracing_subscriber::registry().with(stdout_log.and_then(file_log).and_then(otlp_layer)).init();
+
+    TracingGuard { _log_guard: guard, tracer_provider }
+}
+
+fn otlp_tracer_provider(module_id: &str, endpoint: &str) -> Result<TracerProvider, TraceError> {
+    let resource = Resource::new(vec![KeyValue::new("service.name", module_id.to_lowercase())]);
+    let tracer_provider = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint))
+        .with_trace_config(sdktrace::Config::default().with_resource(resource))
+        .install_batch(runtime::Tokio)?;
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    Ok(tracer_provider)
+}
+
+// TRACE CONTEXT
+const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// Adds the `traceparent` header of the current span to an outgoing request.
+/// Does nothing unless the OTLP exporter is enabled
+pub fn inject_trace_context(headers: &mut HeaderMap) {
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(headers))
+    });
+}
+
+/// Makes the span a child of the one that sent the request, if the request
+/// has a `traceparent` header
+pub fn set_trace_parent(span: &Span, headers: &HeaderMap) {
+    // an empty context would detach the span from its current parent
+    if !headers.contains_key(TRACEPARENT_HEADER) {
+        return;
+    }
+
+    let context =
+        global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)));
+    span.set_parent(context);
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) =
+            (HeaderName::from_bytes(key.as_bytes()), HeaderValue::from_str(&value))
+        {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+// SHUTDOWN
+/// Resolves when the process receives SIGTERM or SIGINT
+pub async fn wait_for_shutdown_signal() {
+    let terminate = async {
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(err) => {
+                error!(?err, "failed to listen for SIGTERM");
+                pending::<()>().await;
+            }
+        }
+    };
+
+    let interrupt = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            error!(?err, "failed to listen for SIGINT");
+            pending::<()>().await;
+        }
+    };
+
+    tokio::select! {
+        _ = terminate => info!("Received SIGTERM, shutting down"),
+        _ = interrupt => info!("Received SIGINT, shutting down"),
+    }
+}
+
+/// Serves `app` until `shutdown` resolves, then stops accepting connections
+/// and waits up to `drain_timeout` for in-flight requests to finish. If `tls`
+/// is set, connections are served over https
+pub async fn serve_with_shutdown<F>(
+    listener: TcpListener,
+    app: Router,
+    shutdown: F,
+    drain_timeout: Duration,
+    tls: Option<Arc<ServerConfig>>,
+) -> std::io::Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    if let Some(tls) = tls {
+        let handle = Handle::new();
+        let shutdown_handle = handle.clone();
+        tokio::spawn(async move {
+            shutdown.await;
+            shutdown_handle.graceful_shutdown(Some(drain_timeout));
+        });
+
+        return axum_server::from_tcp_rustls(listener.into_std()?, RustlsConfig::from_config(tls))
+            .handle(handle)
+            .serve(app.into_make_service())
+            .await;
+    }
 
-    guard
+    let (draining_tx, draining_rx) = oneshot::channel();
+    let server = axum::serve(listener, app).with_graceful_shutdown(async move {
+        shutdown.await;
+        let _ = draining_tx.send(());
+    });
+
+    let drain_deadline = async move {
+        match draining_rx.await {
+            Ok(()) => sleep(drain_timeout).await,
+            Err(_) => pending().await,
+        }
+    };
+
+    tokio::select! {
+        res = async move { server.await } => res,
+        _ = drain_deadline => {
+            warn!(?drain_timeout, "drain timeout elapsed, not waiting for in-flight requests");
+            Ok(())
+        }
+    }
 }
 
 // all commit boost crates
@@ -235,3 +442,100 @@ pub fn get_user_agent_with_version(req_headers: &HeaderMap) -> eyre::Result<Head
     let ua = get_user_agent(req_headers);
     Ok(HeaderValue::from_str(&format!("commit-boost/{HEAVER_VERSION_VALUE} {}", ua))?)
 }
+
+/// Parses the fork from the `Eth-Consensus-Version` header, if present
+pub fn get_consensus_version(headers: &HeaderMap) -> eyre::Result<Option<Version>> {
+    let Some(value) = headers.get(HEADER_CONSENSUS_VERSION) else {
+        return Ok(None);
+    };
+
+    value.to_str()?.parse::<Version>().map(Some).map_err(|err| eyre::eyre!(err))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use axum::routing::get;
+    use opentelemetry::trace::TraceContextExt;
+
+    use super::*;
+
+    async fn start_slow_server(
+        port: u16,
+        drain_timeout: Duration,
+    ) -> (oneshot::Sender<()>, tokio::task::JoinHandle<std::io::Result<()>>) {
+        let app = Router::new().route(
+            "/",
+            get(|| async {
+                sleep(Duration::from_millis(300)).await;
+                "done"
+            }),
+        );
+        let listener = TcpListener::bind(("127.0.0.1", port)).await.unwrap();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let server = tokio::spawn(serve_with_shutdown(
+            listener,
+            app,
+            async move {
+                let _ = shutdown_rx.await;
+            },
+            drain_timeout,
+            None,
+        ));
+
+        (shutdown_tx, server)
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_finishes_in_flight_requests() {
+        let (shutdown_tx, server) = start_slow_server(24001, Duration::from_secs(5)).await;
+
+        let request = tokio::spawn(reqwest::get("http://127.0.0.1:24001/"));
+        sleep(Duration::from_millis(100)).await;
+        shutdown_tx.send(()).unwrap();
+
+        let res = request.await.unwrap().unwrap();
+        assert_eq!(res.text().await.unwrap(), "done");
+        assert!(server.await.unwrap().is_ok());
+
+        // no new connections are accepted
+        assert!(reqwest::get("http://127.0.0.1:24001/").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drain_timeout() {
+        let (shutdown_tx, server) = start_slow_server(24002, Duration::from_millis(50)).await;
+
+        let request = tokio::spawn(reqwest::get("http://127.0.0.1:24002/"));
+        sleep(Duration::from_millis(100)).await;
+
+        let start = Instant::now();
+        shutdown_tx.send(()).unwrap();
+        assert!(server.await.unwrap().is_ok());
+        assert!(start.elapsed() < Duration::from_millis(250));
+        request.abort();
+    }
+
+    #[test]
+    fn test_trace_context_roundtrip() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let tracer = TracerProvider::builder().build().tracer("test");
+        let subscriber =
+            tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let mut headers = HeaderMap::new();
+            let trace_id = {
+                let _span = tracing::info_span!("sender").entered();
+                inject_trace_context(&mut headers);
+                Span::current().context().span().span_context().trace_id()
+            };
+            assert!(headers.contains_key(TRACEPARENT_HEADER));
+
+            let receiver = tracing::info_span!("receiver");
+            set_trace_parent(&receiver, &headers);
+            assert_eq!(receiver.context().span().span_context().trace_id(), trace_id);
+        });
+    }
+}
@@ -7,7 +7,11 @@ use axum::{
     response::{IntoResponse, Response},
     routing::get,
 };
-use cb_common::config::ModuleMetricsConfig;
+use cb_common::{
+    config::ModuleMetricsConfig,
+    utils::{serve_with_shutdown, wait_for_shutdown_signal},
+    SHUTDOWN_DRAIN_TIMEOUT,
+};
 use prometheus::{Encoder, Registry, TextEncoder};
 use tokio::net::TcpListener;
 use tracing::{error, info, trace};
@@ -45,9 +49,17 @@ impl MetricsProvider {
         let address = SocketAddr::from(([0, 0, 0, 0], self.config.server_port));
         let listener = TcpListener::bind(&address).await?;
 
-        axum::serve(listener, router).await?;
+        serve_with_shutdown(
+            listener,
+            router,
+            wait_for_shutdown_signal(),
+            SHUTDOWN_DRAIN_TIMEOUT,
+            None,
+        )
+        .await?;
 
-        Err(eyre::eyre!("Metrics server stopped"))
+        info!("Metrics server stopped");
+        Ok(())
     }
 }
 
@@ -1,8 +1,11 @@
 use alloy::rpc::types::beacon::relay::ValidatorRegistration;
 use async_trait::async_trait;
 use axum::{http::HeaderMap, Router};
-use cb_common::pbs::{
-    GetHeaderParams, GetHeaderReponse, SignedBlindedBeaconBlock, SubmitBlindedBlockResponse,
+use cb_common::{
+    beacon::{HeadEvent, PayloadAttributesEvent},
+    pbs::{
+        GetHeaderParams, GetHeaderReponse, SignedBlindedBeaconBlock, SubmitBlindedBlockResponse,
+    },
 };
 
 use crate::{
@@ -48,6 +51,15 @@ pub trait BuilderApi<S: BuilderApiState>: 'static {
     ) -> eyre::Result<()> {
         mev_boost::register_validator(registrations, req_headers, state).await
     }
+
+    /// Called on each head event of the beacon node, after the state moved to
+    /// the slot of the head. Only called if following the beacon node events
+    async fn on_head(_head: HeadEvent, _state: PbsState<S>) {}
+
+    /// Called on each payload attributes event of the beacon node, after the
+    /// state moved to the proposal slot. Only called if following the beacon
+    /// node events
+    async fn on_payload_attributes(_event: PayloadAttributesEvent, _state: PbsState<S>) {}
 }
 
 pub struct DefaultBuilderApi;
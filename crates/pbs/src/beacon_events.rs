@@ -0,0 +1,55 @@
+//! Follows the beacon node events, so that the module moves to a new slot even
+//! when none of its validators is proposing
+
+use cb_common::beacon::{BeaconClient, BeaconEvent};
+use tracing::{debug, info};
+
+use crate::{
+    api::BuilderApi,
+    state::{BuilderApiState, PbsState},
+};
+
+/// Moves the state to the slot of each head and payload attributes event, which
+/// also clears old bids, and passes the events to the `BuilderApi` hooks
+pub async fn follow_beacon_events<S: BuilderApiState, A: BuilderApi<S>>(
+    beacon_client: BeaconClient,
+    state: PbsState<S>,
+) {
+    info!("Following beacon node events");
+    let mut events = beacon_client.subscribe_events();
+
+    while let Some(event) = events.recv().await {
+        let state = state.with_latest_config();
+
+        match event {
+            BeaconEvent::Head(head) => {
+                debug!(slot = head.slot, block = %head.block, "new head");
+                state.get_or_update_slot_uuid(head.slot);
+                state.set_head(head.clone());
+                A::on_head(head, state).await;
+            }
+
+            BeaconEvent::PayloadAttributes(event) => {
+                debug!(
+                    proposal_slot = event.proposal_slot,
+                    proposer_index = event.proposer_index,
+                    "new payload attributes"
+                );
+                state.get_or_update_slot_uuid(event.proposal_slot);
+
+                // cache the proposer pubkey before its blinded block is submitted
+                if !state.pbs_config().skip_proposer_sigverify {
+                    let beacon_client = beacon_client.clone();
+                    let proposer_index = event.proposer_index;
+                    tokio::spawn(async move {
+                        if let Err(err) = beacon_client.get_validator_pubkey(proposer_index).await {
+                            debug!(?err, proposer_index, "failed to prefetch proposer pubkey");
+                        }
+                    });
+                }
+
+                A::on_payload_attributes(event, state).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,322 @@
+//! Filters and scorers that choose which of the validated bids is returned
+//! from get_header
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use alloy::{primitives::U256, rpc::types::beacon::BlsPublicKey};
+use cb_common::{
+    config::BidSelectionConfig,
+    pbs::{GetHeaderParams, GetHeaderReponse},
+};
+use tracing::debug;
+
+const BPS: u64 = 10_000;
+
+/// A validated bid and the relay it was received from
+#[derive(Debug, Clone)]
+pub struct RelayBid {
+    pub relay_id: Arc<String>,
+    /// Validator pubkey of the get_header request the bid was received for
+    pub validator_pubkey: BlsPublicKey,
+    pub bid: GetHeaderReponse,
+}
+
+/// Decides whether a bid can be returned for a get_header request
+pub trait BidFilter: Send + Sync + 'static {
+    /// Name used in logs when a bid is filtered out
+    fn name(&self) -> &'static str;
+
+    /// Whether to keep the bid
+    fn keep(&self, params: &GetHeaderParams, bid: &RelayBid) -> bool;
+}
+
+/// Adjusts the score bids are compared with
+pub trait BidScorer: Send + Sync + 'static {
+    /// New score of the bid, given the score from the previous scorers which
+    /// starts at the bid value
+    fn score(&self, params: &GetHeaderParams, bid: &RelayBid, score: U256) -> U256;
+}
+
+/// Filters run in order on each bid, then the remaining bids are scored by all
+/// the scorers in order, and the one with the highest score wins. Without
+/// scorers this is the bid with the highest value
+#[derive(Clone, Default)]
+pub struct BidPipeline {
+    filters: Vec<Arc<dyn BidFilter>>,
+    scorers: Vec<Arc<dyn BidScorer>>,
+}
+
+impl BidPipeline {
+    /// Built-in filters and scorers enabled in the config
+    pub fn from_config(config: &BidSelectionConfig) -> Self {
+        let mut pipeline = Self::default();
+
+        if !config.relay_allowlist.is_empty() {
+            pipeline = pipeline.with_filter(RelayAllowlist::new(config.relay_allowlist.clone()));
+        }
+        if !config.relay_haircuts_bps.is_empty() {
+            pipeline = pipeline.with_scorer(RelayHaircut::new(config.relay_haircuts_bps.clone()));
+        }
+        if !config.preferred_relays.is_empty() {
+            pipeline = pipeline.with_scorer(PreferRelays::new(
+                config.preferred_relays.clone(),
+                config.preferred_relays_margin_bps,
+            ));
+        }
+
+        pipeline
+    }
+
+    pub fn with_filter(mut self, filter: impl BidFilter) -> Self {
+        self.filters.push(Arc::new(filter));
+        self
+    }
+
+    pub fn with_scorer(mut self, scorer: impl BidScorer) -> Self {
+        self.scorers.push(Arc::new(scorer));
+        self
+    }
+
+    /// Appends the filters and scorers of `other`, run after the ones already
+    /// in the pipeline
+    pub fn extend(mut self, other: &BidPipeline) -> Self {
+        self.filters.extend(other.filters.iter().cloned());
+        self.scorers.extend(other.scorers.iter().cloned());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty() && self.scorers.is_empty()
+    }
+
+    /// Drops the bids rejected by any filter
+    pub fn filter(&self, params: &GetHeaderParams, bids: Vec<RelayBid>) -> Vec<RelayBid> {
+        bids.into_iter()
+            .filter(|bid| match self.filters.iter().find(|filter| !filter.keep(params, bid)) {
+                Some(filter) => {
+                    debug!(
+                        relay_id = bid.relay_id.as_str(),
+                        block_hash = %bid.bid.block_hash(),
+                        filter = filter.name(),
+                        "bid filtered out"
+                    );
+                    false
+                }
+                None => true,
+            })
+            .collect()
+    }
+
+    pub fn score(&self, params: &GetHeaderParams, bid: &RelayBid) -> U256 {
+        self.scorers.iter().fold(bid.bid.value(), |score, scorer| scorer.score(params, bid, score))
+    }
+
+    /// Bid with the highest score, the first one received wins ties
+    pub fn best<'a>(
+        &self,
+        params: &GetHeaderParams,
+        bids: impl IntoIterator<Item = &'a RelayBid>,
+    ) -> Option<&'a RelayBid> {
+        bids.into_iter()
+            .map(|bid| (self.score(params, bid), bid))
+            .fold(None, |best: Option<(U256, &RelayBid)>, (score, bid)| match best {
+                Some((best_score, _)) if best_score >= score => best,
+                _ => Some((score, bid)),
+            })
+            .map(|(_, bid)| bid)
+    }
+}
+
+/// Only keeps bids from a set of relays
+#[derive(Debug, Clone)]
+pub struct RelayAllowlist {
+    relay_ids: HashSet<String>,
+}
+
+impl RelayAllowlist {
+    pub fn new(relay_ids: impl IntoIterator<Item = String>) -> Self {
+        Self { relay_ids: relay_ids.into_iter().collect() }
+    }
+}
+
+impl BidFilter for RelayAllowlist {
+    fn name(&self) -> &'static str {
+        "relay_allowlist"
+    }
+
+    fn keep(&self, _params: &GetHeaderParams, bid: &RelayBid) -> bool {
+        self.relay_ids.contains(bid.relay_id.as_str())
+    }
+}
+
+/// Lowers the score of the bids of some relays by a share of it, e.g. to
+/// account for relays that are less likely to deliver the payload
+#[derive(Debug, Clone)]
+pub struct RelayHaircut {
+    haircuts_bps: HashMap<String, u64>,
+}
+
+impl RelayHaircut {
+    pub fn new(haircuts_bps: HashMap<String, u64>) -> Self {
+        Self { haircuts_bps }
+    }
+}
+
+impl BidScorer for RelayHaircut {
+    fn score(&self, _params: &GetHeaderParams, bid: &RelayBid, score: U256) -> U256 {
+        match self.haircuts_bps.get(bid.relay_id.as_str()) {
+            Some(haircut) => {
+                score.saturating_mul(U256::from(BPS.saturating_sub(*haircut))) / U256::from(BPS)
+            }
+            None => score,
+        }
+    }
+}
+
+/// Raises the score of the bids of some relays by a margin, so they win over
+/// other bids that are less than the margin higher
+#[derive(Debug, Clone)]
+pub struct PreferRelays {
+    relay_ids: HashSet<String>,
+    margin_bps: u64,
+}
+
+impl PreferRelays {
+    pub fn new(relay_ids: impl IntoIterator<Item = String>, margin_bps: u64) -> Self {
+        Self { relay_ids: relay_ids.into_iter().collect(), margin_bps }
+    }
+}
+
+impl BidScorer for PreferRelays {
+    fn score(&self, _params: &GetHeaderParams, bid: &RelayBid, score: U256) -> U256 {
+        if self.relay_ids.contains(bid.relay_id.as_str()) {
+            score.saturating_mul(U256::from(BPS + self.margin_bps)) / U256::from(BPS)
+        } else {
+            score
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::B256;
+    use cb_common::pbs::SignedExecutionPayloadHeaderDeneb;
+
+    use super::*;
+
+    fn bid(relay_id: &str, value_wei: u64) -> RelayBid {
+        let mut bid = SignedExecutionPayloadHeaderDeneb::default();
+        bid.message.set_value(U256::from(value_wei));
+        RelayBid {
+            relay_id: Arc::new(relay_id.to_string()),
+            validator_pubkey: Default::default(),
+            bid: GetHeaderReponse::Deneb(bid),
+        }
+    }
+
+    fn params() -> GetHeaderParams {
+        GetHeaderParams {
+            slot: 1,
+            parent_hash: B256::ZERO,
+            pubkey: Default::default(),
+            builder_boost_factor: None,
+            local_block_value: None,
+        }
+    }
+
+    fn best_relay(pipeline: &BidPipeline, bids: Vec<RelayBid>) -> Option<String> {
+        let bids = pipeline.filter(&params(), bids);
+        pipeline.best(&params(), &bids).map(|bid| bid.relay_id.to_string())
+    }
+
+    #[test]
+    fn test_default_pipeline() {
+        let pipeline = BidPipeline::from_config(&BidSelectionConfig::default());
+        assert!(pipeline.is_empty());
+
+        let bids = vec![bid("a", 100), bid("b", 200), bid("c", 200)];
+        assert_eq!(best_relay(&pipeline, bids).as_deref(), Some("b"));
+        assert_eq!(best_relay(&pipeline, vec![]), None);
+    }
+
+    #[test]
+    fn test_relay_allowlist() {
+        let config = BidSelectionConfig {
+            relay_allowlist: vec!["a".to_string(), "c".to_string()],
+            ..Default::default()
+        };
+        let pipeline = BidPipeline::from_config(&config);
+
+        let bids = vec![bid("a", 100), bid("b", 200), bid("c", 150)];
+        assert_eq!(best_relay(&pipeline, bids).as_deref(), Some("c"));
+        assert_eq!(best_relay(&pipeline, vec![bid("b", 200)]), None);
+    }
+
+    #[test]
+    fn test_relay_haircut() {
+        let config = BidSelectionConfig {
+            relay_haircuts_bps: HashMap::from([("b".to_string(), 1000)]),
+            ..Default::default()
+        };
+        let pipeline = BidPipeline::from_config(&config);
+
+        // 10% off 1000 is lower than 950
+        assert_eq!(pipeline.score(&params(), &bid("b", 1000)), U256::from(900));
+        let bids = vec![bid("a", 950), bid("b", 1000)];
+        assert_eq!(best_relay(&pipeline, bids).as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn test_prefer_relays() {
+        let config = BidSelectionConfig {
+            preferred_relays: vec!["a".to_string()],
+            preferred_relays_margin_bps: 100,
+            ..Default::default()
+        };
+        let pipeline = BidPipeline::from_config(&config);
+
+        // within 1%
+        let bids = vec![bid("a", 9_950), bid("b", 10_000)];
+        assert_eq!(best_relay(&pipeline, bids).as_deref(), Some("a"));
+
+        // more than 1% higher
+        let bids = vec![bid("a", 9_800), bid("b", 10_000)];
+        assert_eq!(best_relay(&pipeline, bids).as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn test_custom_stages() {
+        struct MinSlot(u64);
+        impl BidFilter for MinSlot {
+            fn name(&self) -> &'static str {
+                "min_slot"
+            }
+
+            fn keep(&self, params: &GetHeaderParams, _bid: &RelayBid) -> bool {
+                params.slot >= self.0
+            }
+        }
+
+        struct Flat;
+        impl BidScorer for Flat {
+            fn score(&self, _params: &GetHeaderParams, bid: &RelayBid, score: U256) -> U256 {
+                if bid.relay_id.as_str() == "a" {
+                    score + U256::from(1000)
+                } else {
+                    score
+                }
+            }
+        }
+
+        let custom = BidPipeline::default().with_scorer(Flat);
+        let pipeline = BidPipeline::from_config(&BidSelectionConfig::default()).extend(&custom);
+        let bids = vec![bid("a", 500), bid("b", 1000)];
+        assert_eq!(best_relay(&pipeline, bids.clone()).as_deref(), Some("a"));
+
+        let pipeline = pipeline.with_filter(MinSlot(2));
+        assert_eq!(best_relay(&pipeline, bids), None);
+    }
+}
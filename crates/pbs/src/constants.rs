@@ -6,3 +6,17 @@ pub(crate) const GET_HEADER_ENDPOINT_TAG: &str = "get_header";
 /// For metrics recorded when a request times out
 pub(crate) const TIMEOUT_ERROR_CODE: u16 = 555;
 pub(crate) const TIMEOUT_ERROR_CODE_STR: &str = "555";
+
+/// How often to check for tripped relays whose cooldown expired
+pub(crate) const RELAY_PROBE_INTERVAL_MS: u64 = 1000;
+
+/// How long new submit_block calls are still accepted after a shutdown
+/// signal, for headers handed out before it. Part of `SHUTDOWN_DRAIN_TIMEOUT`
+pub(crate) const SHUTDOWN_SUBMIT_WINDOW_MS: u64 = 4000;
+
+/// Delay before the first retry of a registration batch, doubled for each
+/// further retry
+pub(crate) const REGISTRATION_RETRY_DELAY_MS: u64 = 250;
+
+/// Longest delay between retries of a registration batch, one slot
+pub(crate) const REGISTRATION_MAX_RETRY_DELAY_MS: u64 = 12_000;
@@ -1,9 +1,9 @@
 use alloy::{
-    primitives::{B256, U256},
+    primitives::{Address, Bytes, B256, U256},
     rpc::types::beacon::BlsPublicKey,
 };
 use axum::{http::StatusCode, response::IntoResponse};
-use cb_common::error::BlstErrorWrapper;
+use cb_common::{error::BlstErrorWrapper, pbs::Version};
 use thiserror::Error;
 
 #[derive(Debug)]
@@ -11,6 +11,8 @@ use thiserror::Error;
 pub enum PbsClientError {
     NoResponse,
     NoPayload,
+    DecodeError(String),
+    InvalidBlock(ValidationError),
 }
 
 impl PbsClientError {
@@ -18,15 +20,20 @@ impl PbsClientError {
         match self {
             PbsClientError::NoResponse => StatusCode::SERVICE_UNAVAILABLE,
             PbsClientError::NoPayload => StatusCode::BAD_GATEWAY,
+            PbsClientError::DecodeError(_) | PbsClientError::InvalidBlock(_) => {
+                StatusCode::BAD_REQUEST
+            }
         }
     }
 }
 
 impl IntoResponse for PbsClientError {
     fn into_response(self) -> axum::response::Response {
-        let msg = match self {
-            PbsClientError::NoResponse => "no response from relays",
-            PbsClientError::NoPayload => "no payload from relays",
+        let msg = match &self {
+            PbsClientError::NoResponse => "no response from relays".to_string(),
+            PbsClientError::NoPayload => "no payload from relays".to_string(),
+            PbsClientError::DecodeError(err) => format!("failed to decode request: {err}"),
+            PbsClientError::InvalidBlock(err) => format!("invalid blinded block: {err}"),
         };
 
         (self.status_code(), msg).into_response()
@@ -44,6 +51,9 @@ pub enum PbsError {
     #[error("serde decode error: {0}")]
     SerdeDecodeError(#[from] serde_json::Error),
 
+    #[error("ssz decode error: {0}")]
+    SszDecodeError(String),
+
     #[error("relay response error. Code: {code}, err: {error_msg}")]
     RelayResponse { error_msg: String, code: u16 },
 
@@ -55,6 +65,15 @@ impl PbsError {
     pub fn is_timeout(&self) -> bool {
         matches!(self, PbsError::Reqwest(err) if err.is_timeout())
     }
+
+    /// Whether the same request could succeed if sent again
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            PbsError::Reqwest(err) => err.is_timeout() || err.is_connect(),
+            PbsError::RelayResponse { code, .. } => *code >= 500,
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, Error, PartialEq, Eq)]
@@ -71,6 +90,9 @@ pub enum ValidationError {
     #[error("block hash mismatch: expected {expected} got {got}")]
     BlockHashMismatch { expected: B256, got: B256 },
 
+    #[error("fork version mismatch: expected {expected} got {got}")]
+    VersionMismatch { expected: Version, got: Version },
+
     #[error("mismatch in KZG commitments: exepcted_blobs: {expected_blobs} got_blobs: {got_blobs} got_commitments: {got_commitments} got_proofs: {got_proofs}")]
     KzgCommitments {
         expected_blobs: usize,
@@ -82,12 +104,84 @@ pub enum ValidationError {
     #[error("mismatch in KZG blob commitment: expected: {expected} got: {got} index: {index}")]
     KzgMismatch { expected: String, got: String, index: usize },
 
+    #[error("failed KZG proof verification: {0}")]
+    KzgProofs(String),
+
+    #[error("payload parent hash mismatch: expected {expected} got {got}")]
+    PayloadParentHashMismatch { expected: B256, got: B256 },
+
+    #[error("payload fee recipient mismatch: expected {expected} got {got}")]
+    PayloadFeeRecipientMismatch { expected: Address, got: Address },
+
+    #[error("payload state root mismatch: expected {expected} got {got}")]
+    PayloadStateRootMismatch { expected: B256, got: B256 },
+
+    #[error("payload receipts root mismatch: expected {expected} got {got}")]
+    PayloadReceiptsRootMismatch { expected: B256, got: B256 },
+
+    #[error("payload logs bloom mismatch")]
+    PayloadLogsBloomMismatch,
+
+    #[error("payload prev randao mismatch: expected {expected} got {got}")]
+    PayloadPrevRandaoMismatch { expected: B256, got: B256 },
+
+    #[error("payload block number mismatch: expected {expected} got {got}")]
+    PayloadBlockNumberMismatch { expected: u64, got: u64 },
+
+    #[error("payload gas limit mismatch: expected {expected} got {got}")]
+    PayloadGasLimitMismatch { expected: u64, got: u64 },
+
+    #[error("payload gas used mismatch: expected {expected} got {got}")]
+    PayloadGasUsedMismatch { expected: u64, got: u64 },
+
+    #[error("payload timestamp mismatch: expected {expected} got {got}")]
+    PayloadTimestampMismatch { expected: u64, got: u64 },
+
+    #[error("payload extra data mismatch: expected {expected} got {got}")]
+    PayloadExtraDataMismatch { expected: Bytes, got: Bytes },
+
+    #[error("payload base fee mismatch: expected {expected} got {got}")]
+    PayloadBaseFeeMismatch { expected: U256, got: U256 },
+
+    #[error("payload transactions root mismatch: expected {expected} got {got}")]
+    PayloadTransactionsRootMismatch { expected: B256, got: B256 },
+
+    #[error("payload withdrawals root mismatch: expected {expected} got {got}")]
+    PayloadWithdrawalsRootMismatch { expected: B256, got: B256 },
+
+    #[error("payload blob gas used mismatch: expected {expected} got {got}")]
+    PayloadBlobGasUsedMismatch { expected: u64, got: u64 },
+
+    #[error("payload excess blob gas mismatch: expected {expected} got {got}")]
+    PayloadExcessBlobGasMismatch { expected: u64, got: u64 },
+
     #[error("bid below minimum: min: {min} got {got}")]
     BidTooLow { min: U256, got: U256 },
 
+    #[error("fee recipient mismatch: expected {expected} got {got}")]
+    FeeRecipientMismatch { expected: Address, got: Address },
+
+    #[error("gas limit mismatch: expected {expected} got {got}")]
+    GasLimitMismatch { expected: u64, got: u64 },
+
+    #[error("timestamp mismatch: expected {expected} got {got}")]
+    TimestampMismatch { expected: u64, got: u64 },
+
     #[error("empty tx root")]
     EmptyTxRoot,
 
     #[error("failed signature verification: {0:?}")]
     Sigverify(#[from] BlstErrorWrapper),
+
+    #[error("block slot {slot} is in the past, current slot is {current}")]
+    PastSlot { slot: u64, current: u64 },
+
+    #[error("block slot mismatch: expected {expected} got {got}")]
+    SlotMismatch { expected: u64, got: u64 },
+
+    #[error("failed proposer signature verification: {0:?}")]
+    ProposerSigverify(BlstErrorWrapper),
+
+    #[error("unknown pubkey of proposer {proposer_index}: {reason}")]
+    UnknownProposer { proposer_index: u64, reason: String },
 }
@@ -0,0 +1,448 @@
+//! Health scoring of relays, used to trip relays that keep failing into a
+//! cooldown instead of calling them on every slot
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use cb_common::config::RelayHealthConfig;
+use dashmap::DashMap;
+use reqwest::StatusCode;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::metrics::{
+    RELAY_AVG_LATENCY, RELAY_CIRCUIT_STATE, RELAY_SUCCESS_RATE, RELAY_TIMEOUT_RATE,
+};
+
+/// Outcome of a single request to a relay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestOutcome {
+    /// The relay responded, even if with a client error
+    Success(Duration),
+    /// The relay responded with a server error, or the request failed before
+    /// getting a response
+    Failure(Option<Duration>),
+    /// The request timed out
+    Timeout,
+}
+
+impl RequestOutcome {
+    pub fn from_response(code: StatusCode, latency: Duration) -> Self {
+        if code.is_server_error() {
+            Self::Failure(Some(latency))
+        } else {
+            Self::Success(latency)
+        }
+    }
+
+    pub fn from_error(err: &reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Self::Timeout
+        } else {
+            Self::Failure(None)
+        }
+    }
+
+    fn latency(&self) -> Option<Duration> {
+        match self {
+            Self::Success(latency) => Some(*latency),
+            Self::Failure(latency) => *latency,
+            Self::Timeout => None,
+        }
+    }
+}
+
+/// Outcome of a request sent to several endpoints of the same relay. It's a
+/// success if any endpoint succeeded, so that one URL being down doesn't count
+/// against the whole relay
+#[derive(Debug, Default)]
+pub struct EndpointOutcomes {
+    outcome: Mutex<Option<RequestOutcome>>,
+}
+
+impl EndpointOutcomes {
+    pub fn add(&self, outcome: RequestOutcome) {
+        let mut current = self.outcome.lock().expect("poisoned");
+        if !matches!(*current, Some(RequestOutcome::Success(_))) {
+            *current = Some(outcome);
+        }
+    }
+
+    /// Records a single outcome for the relay, if any endpoint got one
+    pub fn record(&self, health: &RelayHealth, relay_id: &str) {
+        if let Some(outcome) = self.outcome.lock().expect("poisoned").take() {
+            health.record(relay_id, outcome);
+        }
+    }
+}
+
+/// State of the circuit breaker of a relay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests are sent to the relay
+    Closed,
+    /// The relay is skipped until the cooldown expires
+    Open { until: Instant },
+    /// The cooldown expired and the relay is being probed with `get_status`
+    HalfOpen,
+}
+
+impl CircuitState {
+    fn metric_value(&self) -> i64 {
+        match self {
+            Self::Closed => 0,
+            Self::Open { .. } => 1,
+            Self::HalfOpen => 2,
+        }
+    }
+}
+
+/// Scores of a relay computed over the most recent requests
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RelayStats {
+    pub requests: usize,
+    pub success_rate: f64,
+    pub timeout_rate: f64,
+    pub avg_latency: Option<Duration>,
+    pub state: CircuitState,
+}
+
+/// Totals of the requests sent to a relay since startup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RelayCounters {
+    pub requests: u64,
+    pub successes: u64,
+    pub failures: u64,
+    pub timeouts: u64,
+    /// Times the relay was tripped by the circuit breaker
+    pub trips: u64,
+}
+
+#[derive(Debug)]
+struct RelayScore {
+    window: VecDeque<RequestOutcome>,
+    state: CircuitState,
+    counters: RelayCounters,
+}
+
+impl Default for RelayScore {
+    fn default() -> Self {
+        Self {
+            window: VecDeque::new(),
+            state: CircuitState::Closed,
+            counters: RelayCounters::default(),
+        }
+    }
+}
+
+impl RelayScore {
+    fn stats(&self) -> RelayStats {
+        let requests = self.window.len();
+
+        let mut successes = 0;
+        let mut timeouts = 0;
+        let mut latencies = Vec::with_capacity(requests);
+        for outcome in self.window.iter() {
+            match outcome {
+                RequestOutcome::Success(_) => successes += 1,
+                RequestOutcome::Timeout => timeouts += 1,
+                RequestOutcome::Failure(_) => {}
+            }
+            latencies.extend(outcome.latency());
+        }
+
+        let rate = |n: usize| if requests == 0 { 0.0 } else { n as f64 / requests as f64 };
+        let avg_latency = (!latencies.is_empty())
+            .then(|| latencies.iter().sum::<Duration>() / latencies.len() as u32);
+
+        RelayStats {
+            requests,
+            success_rate: if requests == 0 { 1.0 } else { rate(successes) },
+            timeout_rate: rate(timeouts),
+            avg_latency,
+            state: self.state,
+        }
+    }
+}
+
+/// Tracks the health of each relay, cheap to clone and safe to share across
+/// threads
+#[derive(Debug, Clone)]
+pub struct RelayHealth {
+    config: Arc<RelayHealthConfig>,
+    scores: Arc<DashMap<String, RelayScore>>,
+}
+
+impl RelayHealth {
+    pub fn new(config: RelayHealthConfig) -> Self {
+        Self { config: Arc::new(config), scores: Arc::new(DashMap::new()) }
+    }
+
+    /// Tracker with new thresholds, sharing the scores recorded so far
+    pub fn with_config(&self, config: RelayHealthConfig) -> Self {
+        Self { config: Arc::new(config), scores: self.scores.clone() }
+    }
+
+    /// Records the outcome of a request, tripping the relay if it crossed any
+    /// of the thresholds
+    pub fn record(&self, relay_id: &str, outcome: RequestOutcome) {
+        let mut score = self.scores.entry(relay_id.to_string()).or_default();
+
+        score.counters.requests += 1;
+        match outcome {
+            RequestOutcome::Success(_) => score.counters.successes += 1,
+            RequestOutcome::Failure(_) => score.counters.failures += 1,
+            RequestOutcome::Timeout => score.counters.timeouts += 1,
+        }
+
+        score.window.push_back(outcome);
+        while score.window.len() > self.config.window_size {
+            score.window.pop_front();
+        }
+
+        let stats = score.stats();
+        RELAY_SUCCESS_RATE.with_label_values(&[relay_id]).set(stats.success_rate);
+        RELAY_TIMEOUT_RATE.with_label_values(&[relay_id]).set(stats.timeout_rate);
+        if let Some(latency) = stats.avg_latency {
+            RELAY_AVG_LATENCY.with_label_values(&[relay_id]).set(latency.as_secs_f64());
+        }
+
+        if !self.config.enable_circuit_breaker || score.state != CircuitState::Closed {
+            return;
+        }
+
+        if let Some(reason) = self.trip_reason(&stats) {
+            warn!(
+                relay_id,
+                reason,
+                success_rate = stats.success_rate,
+                timeout_rate = stats.timeout_rate,
+                avg_latency = ?stats.avg_latency,
+                cooldown_secs = self.config.cooldown_secs,
+                "relay tripped, skipping it until cooldown expires"
+            );
+            score.counters.trips += 1;
+            self.set_state(relay_id, &mut score, CircuitState::Open { until: self.cooldown_end() });
+        }
+    }
+
+    /// Whether requests should be sent to the relay
+    pub fn is_available(&self, relay_id: &str) -> bool {
+        !self.config.enable_circuit_breaker ||
+            self.scores.get(relay_id).map_or(true, |score| score.state == CircuitState::Closed)
+    }
+
+    /// Moves the relay to half open if its cooldown expired. Returns true if
+    /// the relay should be probed
+    pub fn start_probe(&self, relay_id: &str) -> bool {
+        let Some(mut score) = self.scores.get_mut(relay_id) else {
+            return false;
+        };
+
+        match score.state {
+            CircuitState::Open { until } if until <= Instant::now() => {
+                self.set_state(relay_id, &mut score, CircuitState::HalfOpen);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Records the result of a probe, closing the circuit on success and
+    /// restarting the cooldown on failure. Returns true if the relay was
+    /// re-enabled
+    pub fn record_probe(&self, relay_id: &str, success: bool) -> bool {
+        let Some(mut score) = self.scores.get_mut(relay_id) else {
+            return false;
+        };
+
+        if score.state != CircuitState::HalfOpen {
+            return false;
+        }
+
+        if success {
+            info!(relay_id, "relay passed probe, sending requests again");
+            // start from a clean window so old failures don't trip it again
+            score.window.clear();
+            self.set_state(relay_id, &mut score, CircuitState::Closed);
+        } else {
+            warn!(relay_id, "relay failed probe, restarting cooldown");
+            self.set_state(relay_id, &mut score, CircuitState::Open { until: self.cooldown_end() });
+        }
+
+        success
+    }
+
+    pub fn stats(&self, relay_id: &str) -> Option<RelayStats> {
+        self.scores.get(relay_id).map(|score| score.stats())
+    }
+
+    pub fn counters(&self, relay_id: &str) -> Option<RelayCounters> {
+        self.scores.get(relay_id).map(|score| score.counters)
+    }
+
+    fn trip_reason(&self, stats: &RelayStats) -> Option<&'static str> {
+        if stats.requests < self.config.min_requests {
+            return None;
+        }
+
+        if 1.0 - stats.success_rate > self.config.max_error_rate {
+            return Some("error rate");
+        }
+
+        if stats.timeout_rate > self.config.max_timeout_rate {
+            return Some("timeout rate");
+        }
+
+        if let (Some(max_ms), Some(latency)) = (self.config.max_avg_latency_ms, stats.avg_latency) {
+            if latency > Duration::from_millis(max_ms) {
+                return Some("latency");
+            }
+        }
+
+        None
+    }
+
+    fn cooldown_end(&self) -> Instant {
+        Instant::now() + Duration::from_secs(self.config.cooldown_secs)
+    }
+
+    fn set_state(&self, relay_id: &str, score: &mut RelayScore, state: CircuitState) {
+        score.state = state;
+        RELAY_CIRCUIT_STATE.with_label_values(&[relay_id]).set(state.metric_value());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RELAY: &str = "test-relay";
+
+    fn health(cooldown_secs: u64) -> RelayHealth {
+        RelayHealth::new(RelayHealthConfig {
+            enable_circuit_breaker: true,
+            window_size: 10,
+            min_requests: 4,
+            max_error_rate: 0.5,
+            max_timeout_rate: 0.3,
+            max_avg_latency_ms: Some(500),
+            cooldown_secs,
+        })
+    }
+
+    fn ok(ms: u64) -> RequestOutcome {
+        RequestOutcome::Success(Duration::from_millis(ms))
+    }
+
+    #[test]
+    fn test_endpoint_outcomes() {
+        let health = health(60);
+        let outcomes = EndpointOutcomes::default();
+        outcomes.add(RequestOutcome::Timeout);
+        outcomes.add(ok(100));
+        outcomes.add(RequestOutcome::Failure(None));
+        outcomes.record(&health, RELAY);
+
+        let stats = health.stats(RELAY).unwrap();
+        assert_eq!(stats.requests, 1);
+        assert_eq!(stats.success_rate, 1.0);
+
+        // nothing left to record
+        outcomes.record(&health, RELAY);
+        assert_eq!(health.stats(RELAY).unwrap().requests, 1);
+    }
+
+    #[test]
+    fn test_stats() {
+        let health = health(60);
+        assert!(health.stats(RELAY).is_none());
+
+        health.record(RELAY, ok(100));
+        health.record(RELAY, ok(300));
+        health.record(RELAY, RequestOutcome::Timeout);
+
+        let stats = health.stats(RELAY).unwrap();
+        assert_eq!(stats.requests, 3);
+        assert!((stats.success_rate - 2.0 / 3.0).abs() < f64::EPSILON);
+        assert!((stats.timeout_rate - 1.0 / 3.0).abs() < f64::EPSILON);
+        assert_eq!(stats.avg_latency, Some(Duration::from_millis(200)));
+        // below min requests
+        assert!(health.is_available(RELAY));
+    }
+
+    #[test]
+    fn test_window() {
+        let health = health(60);
+        for _ in 0..20 {
+            health.record(RELAY, ok(100));
+        }
+        assert_eq!(health.stats(RELAY).unwrap().requests, 10);
+    }
+
+    #[test]
+    fn test_trip_on_timeouts() {
+        let health = health(60);
+        for _ in 0..3 {
+            health.record(RELAY, ok(100));
+        }
+        assert!(health.is_available(RELAY));
+
+        health.record(RELAY, RequestOutcome::Timeout);
+        health.record(RELAY, RequestOutcome::Timeout);
+        assert!(!health.is_available(RELAY));
+        assert!(matches!(health.stats(RELAY).unwrap().state, CircuitState::Open { .. }));
+
+        // cooldown not expired
+        assert!(!health.start_probe(RELAY));
+    }
+
+    #[test]
+    fn test_trip_on_latency() {
+        let health = health(60);
+        for _ in 0..4 {
+            health.record(RELAY, ok(600));
+        }
+        assert!(!health.is_available(RELAY));
+    }
+
+    #[test]
+    fn test_disabled() {
+        let health = RelayHealth::new(RelayHealthConfig::default());
+        for _ in 0..50 {
+            health.record(RELAY, RequestOutcome::Failure(None));
+        }
+        assert_eq!(health.stats(RELAY).unwrap().success_rate, 0.0);
+        assert!(health.is_available(RELAY));
+    }
+
+    #[test]
+    fn test_probe() {
+        let health = health(0);
+        for _ in 0..4 {
+            health.record(RELAY, RequestOutcome::Failure(None));
+        }
+        assert!(!health.is_available(RELAY));
+
+        // failed probe restarts the cooldown
+        assert!(health.start_probe(RELAY));
+        assert_eq!(health.stats(RELAY).unwrap().state, CircuitState::HalfOpen);
+        assert!(!health.start_probe(RELAY));
+        assert!(!health.record_probe(RELAY, false));
+        assert!(!health.is_available(RELAY));
+
+        assert!(health.start_probe(RELAY));
+        assert!(health.record_probe(RELAY, true));
+        assert!(health.is_available(RELAY));
+        assert_eq!(health.stats(RELAY).unwrap().requests, 0);
+
+        // counters are kept across probes
+        let counters = health.counters(RELAY).unwrap();
+        assert_eq!(counters.requests, 4);
+        assert_eq!(counters.failures, 4);
+        assert_eq!(counters.trips, 1);
+    }
+}
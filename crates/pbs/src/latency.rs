@@ -0,0 +1,67 @@
+//! Latency history of the get_header requests to each relay, used to schedule
+//! adaptive timing games
+
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use dashmap::DashMap;
+
+/// Most recent get_header latencies of each relay, in milliseconds. Sampled
+/// like `RELAY_LATENCY`, so requests that timed out are not included
+#[derive(Debug, Clone, Default)]
+pub struct RelayLatencies {
+    latencies: Arc<DashMap<String, VecDeque<u64>>>,
+}
+
+impl RelayLatencies {
+    /// Records a latency, keeping at most `window_size` per relay
+    pub fn record(&self, relay_id: &str, latency: Duration, window_size: usize) {
+        let mut window = self.latencies.entry(relay_id.to_string()).or_default();
+        window.push_back(latency.as_millis() as u64);
+        while window.len() > window_size {
+            window.pop_front();
+        }
+    }
+
+    /// Latency under which `percentile` percent of the recent requests
+    /// returned, None if fewer than `min_samples` were recorded
+    pub fn percentile(&self, relay_id: &str, percentile: f64, min_samples: usize) -> Option<u64> {
+        let window = self.latencies.get(relay_id)?;
+        if window.is_empty() || window.len() < min_samples {
+            return None;
+        }
+
+        let mut sorted: Vec<u64> = window.iter().copied().collect();
+        sorted.sort_unstable();
+
+        // nearest rank
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.saturating_sub(1).min(sorted.len() - 1)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let latencies = RelayLatencies::default();
+        for ms in (1..=100).rev() {
+            latencies.record("relay", Duration::from_millis(ms), 100);
+        }
+
+        assert_eq!(latencies.percentile("relay", 95.0, 20), Some(95));
+        assert_eq!(latencies.percentile("relay", 50.0, 20), Some(50));
+        assert_eq!(latencies.percentile("relay", 100.0, 20), Some(100));
+        assert_eq!(latencies.percentile("relay", 0.0, 20), Some(1));
+
+        // not enough data
+        assert_eq!(latencies.percentile("relay", 95.0, 101), None);
+        assert_eq!(latencies.percentile("other", 95.0, 0), None);
+
+        // older latencies are dropped
+        latencies.record("relay", Duration::from_millis(500), 10);
+        assert_eq!(latencies.percentile("relay", 100.0, 10), Some(500));
+        assert_eq!(latencies.percentile("relay", 90.0, 10), Some(9));
+    }
+}
@@ -1,13 +1,28 @@
 mod api;
+mod beacon_events;
+mod bids;
 mod constants;
 mod error;
+mod health;
+mod latency;
 mod metrics;
 mod mev_boost;
+mod registrations;
+mod relay_check;
 mod routes;
 mod service;
+mod single_flight;
 mod state;
 
 pub use api::*;
+pub use bids::{
+    BidFilter, BidPipeline, BidScorer, PreferRelays, RelayAllowlist, RelayBid, RelayHaircut,
+};
+pub use health::{CircuitState, RelayCounters, RelayHealth, RelayStats, RequestOutcome};
+pub use latency::RelayLatencies;
 pub use mev_boost::*;
+pub use registrations::{RegistrationStore, RegistrationUpdate};
+pub use relay_check::{check_relays, CheckOutcome, RelayCheckReport};
 pub use service::PbsService;
-pub use state::{BuilderApiState, PbsState};
+pub use single_flight::SingleFlight;
+pub use state::{BuilderApiState, PbsState, SharedResult};
@@ -5,8 +5,9 @@
 
 use lazy_static::lazy_static;
 use prometheus::{
-    register_histogram_vec_with_registry, register_int_counter_vec_with_registry, HistogramVec,
-    IntCounterVec, Registry,
+    register_gauge_vec_with_registry, register_histogram_vec_with_registry,
+    register_int_counter_vec_with_registry, register_int_gauge_vec_with_registry, GaugeVec,
+    HistogramVec, IntCounterVec, IntGaugeVec, Registry,
 };
 
 lazy_static! {
@@ -23,11 +24,65 @@ lazy_static! {
     )
     .unwrap();
 
-    /// Latency by relay by endpoint
+    /// Latency by relay by endpoint, and by url for relays with several
     pub static ref RELAY_LATENCY: HistogramVec = register_histogram_vec_with_registry!(
         "relay_latency",
         "HTTP latency by relay",
-        &["endpoint", "relay_id"],
+        &["endpoint", "relay_id", "relay_endpoint"],
+        PBS_METRICS_REGISTRY
+    )
+    .unwrap();
+
+    /// Time to verify the KZG proofs of the blobs bundle returned by relay
+    pub static ref BLOBS_BUNDLE_VERIFICATION_LATENCY: HistogramVec = register_histogram_vec_with_registry!(
+        "blobs_bundle_verification_latency",
+        "Time to verify the KZG proofs of the blobs bundle by relay",
+        &["relay_id"],
+        PBS_METRICS_REGISTRY
+    )
+    .unwrap();
+
+    /// Registration batches sent by relay, after retries
+    pub static ref RELAY_REGISTRATION_BATCHES: IntCounterVec = register_int_counter_vec_with_registry!(
+        "relay_registration_batches_total",
+        "Registration batches sent by relay, by result",
+        &["result", "relay_id"],
+        PBS_METRICS_REGISTRY
+    )
+    .unwrap();
+
+    /// Rate of successful requests over the relay health window
+    pub static ref RELAY_SUCCESS_RATE: GaugeVec = register_gauge_vec_with_registry!(
+        "relay_success_rate",
+        "Rate of successful requests by relay",
+        &["relay_id"],
+        PBS_METRICS_REGISTRY
+    )
+    .unwrap();
+
+    /// Rate of timed out requests over the relay health window
+    pub static ref RELAY_TIMEOUT_RATE: GaugeVec = register_gauge_vec_with_registry!(
+        "relay_timeout_rate",
+        "Rate of timed out requests by relay",
+        &["relay_id"],
+        PBS_METRICS_REGISTRY
+    )
+    .unwrap();
+
+    /// Average latency in seconds over the relay health window
+    pub static ref RELAY_AVG_LATENCY: GaugeVec = register_gauge_vec_with_registry!(
+        "relay_avg_latency",
+        "Average HTTP latency by relay",
+        &["relay_id"],
+        PBS_METRICS_REGISTRY
+    )
+    .unwrap();
+
+    /// Circuit breaker state: 0 closed, 1 open, 2 half open
+    pub static ref RELAY_CIRCUIT_STATE: IntGaugeVec = register_int_gauge_vec_with_registry!(
+        "relay_circuit_state",
+        "Circuit breaker state by relay",
+        &["relay_id"],
         PBS_METRICS_REGISTRY
     )
     .unwrap();
@@ -1,28 +1,47 @@
-use std::time::{Duration, Instant};
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
 
 use alloy::{
-    primitives::{utils::format_ether, B256, U256},
+    primitives::{utils::format_ether, Address, B256, U256},
     rpc::types::beacon::BlsPublicKey,
 };
 use axum::http::{HeaderMap, HeaderValue};
 use cb_common::{
-    config::PbsConfig,
+    beacon::BeaconClient,
+    config::AdaptiveTimingConfig,
     pbs::{
-        GetHeaderParams, GetHeaderReponse, RelayClient, SignedExecutionPayloadHeader,
-        EMPTY_TX_ROOT_HASH, HEADER_SLOT_UUID_KEY, HEADER_START_TIME_UNIX_MS,
+        BidArchive, BidArchiveRecord, BuilderEvent, EncodingType, GetHeaderParams,
+        GetHeaderReponse, RelayClient, RelayEndpoint, Version, ACCEPT_SSZ_OR_JSON,
+        DEFAULT_BUILDER_BOOST_FACTOR, EMPTY_TX_ROOT_HASH, HEADER_CONSENSUS_VERSION,
+        HEADER_SLOT_UUID_KEY, HEADER_START_TIME_UNIX_MS,
     },
     signature::verify_signed_builder_message,
     types::Chain,
-    utils::{get_user_agent_with_version, ms_into_slot, utcnow_ms},
+    utils::{
+        get_consensus_version, get_user_agent_with_version, inject_trace_context, ms_into_slot,
+        timestamp_of_slot_start_millis, utcnow_ms,
+    },
+};
+use futures::{
+    future::{join_all, BoxFuture, Shared},
+    stream::FuturesUnordered,
+    FutureExt, StreamExt,
+};
+use reqwest::{
+    header::{ACCEPT, USER_AGENT},
+    StatusCode,
 };
-use futures::future::join_all;
-use reqwest::{header::USER_AGENT, StatusCode};
-use tokio::time::sleep;
-use tracing::{debug, error, warn, Instrument};
+use tokio::time::{sleep, sleep_until};
+use tracing::{debug, error, info, warn, Instrument};
 
 use crate::{
+    bids::RelayBid,
     constants::{GET_HEADER_ENDPOINT_TAG, TIMEOUT_ERROR_CODE, TIMEOUT_ERROR_CODE_STR},
     error::{PbsError, ValidationError},
+    health::{EndpointOutcomes, RelayHealth, RequestOutcome},
+    latency::RelayLatencies,
     metrics::{RELAY_LATENCY, RELAY_STATUS_CODE},
     state::{BuilderApiState, PbsState},
 };
@@ -34,10 +53,15 @@ pub async fn get_header<S: BuilderApiState>(
     req_headers: HeaderMap,
     state: PbsState<S>,
 ) -> eyre::Result<Option<GetHeaderReponse>> {
+    // validators in a mux may override the global config
+    let mux = state.mux(&params.pubkey);
+    let timeout_get_header_ms = mux
+        .and_then(|mux| mux.timeout_get_header_ms)
+        .unwrap_or(state.pbs_config().timeout_get_header_ms);
+    let min_bid_wei = mux.and_then(|mux| mux.min_bid_wei).unwrap_or(state.pbs_config().min_bid_wei);
+
     let ms_into_slot = ms_into_slot(params.slot, state.config.chain);
-    let max_timeout_ms = state
-        .pbs_config()
-        .timeout_get_header_ms
+    let max_timeout_ms = timeout_get_header_ms
         .min(state.pbs_config().late_in_slot_time_ms.saturating_sub(ms_into_slot));
 
     if max_timeout_ms == 0 {
@@ -50,21 +74,53 @@ pub async fn get_header<S: BuilderApiState>(
         return Ok(None);
     }
 
+    let expected = expected_header(&params, &state);
+    // this may query the beacon node, so it runs in the background while the
+    // relays are queried
+    let expected_gas_limit = tokio::spawn(fetch_expected_gas_limit(&params, &state))
+        .map(|res| res.ok().flatten())
+        .boxed()
+        .shared();
+
     let (_, slot_uuid) = state.get_slot_and_uuid();
 
     // prepare headers, except for start time which is set in `send_one_get_header`
     let mut send_headers = HeaderMap::new();
     send_headers.insert(HEADER_SLOT_UUID_KEY, HeaderValue::from_str(&slot_uuid.to_string())?);
     send_headers.insert(USER_AGENT, get_user_agent_with_version(&req_headers)?);
+    send_headers.insert(
+        HEADER_CONSENSUS_VERSION,
+        HeaderValue::from_static(state.config.chain.fork_at_slot(params.slot).as_str()),
+    );
+
+    if let Some(mux) = mux {
+        debug!(mux_id = %mux.id, "using mux relays for validator");
+    }
+
+    let relays = state.healthy_relays(state.validator_relays(&params.pubkey));
+    if relays.is_empty() {
+        warn!("all relays are tripped, skipping relay requests");
+        return Ok(None);
+    }
+
+    let ctx = GetHeaderContext {
+        chain: state.config.chain,
+        skip_sigverify: state.pbs_config().skip_sigverify,
+        min_bid_wei,
+        expected,
+        expected_gas_limit,
+        health: state.relay_health().clone(),
+        latencies: state.relay_latencies().clone(),
+        adaptive_timing: state.pbs_config().adaptive_timing_games.clone(),
+        bid_archive: state.config.bid_archive.clone(),
+    };
 
-    let relays = state.relays();
     let mut handles = Vec::with_capacity(relays.len());
     for relay in relays.iter() {
         handles.push(send_timed_get_header(
             params,
-            relay.clone(),
-            state.config.chain,
-            state.pbs_config(),
+            (*relay).clone(),
+            ctx.clone(),
             send_headers.clone(),
             ms_into_slot,
             max_timeout_ms,
@@ -77,166 +133,487 @@ pub async fn get_header<S: BuilderApiState>(
         let relay_id = relays[i].id.as_ref();
 
         match res {
-            Ok(Some(res)) => relay_bids.push(res),
+            Ok(Some(bid)) => relay_bids.push(RelayBid {
+                relay_id: relays[i].id.clone(),
+                validator_pubkey: params.pubkey,
+                bid,
+            }),
             Ok(_) => {}
             Err(err) if err.is_timeout() => error!(err = "Timed Out", relay_id),
             Err(err) => error!(?err, relay_id),
         }
     }
 
-    Ok(state.add_bids(params.slot, relay_bids))
+    let Some(best_bid) = state.add_bids(&params, relay_bids) else {
+        return Ok(None);
+    };
+
+    let builder_boost_factor =
+        state.pbs_config().builder_boost_factor(&params.pubkey, params.builder_boost_factor);
+    let use_builder = use_builder_bid(
+        best_bid.value(),
+        builder_boost_factor,
+        params.local_block_value,
+        state.pbs_config().min_premium_wei,
+    );
+
+    state.publish_event(BuilderEvent::BidDecision {
+        slot: params.slot,
+        block_hash: best_bid.block_hash(),
+        bid_value: best_bid.value(),
+        builder_boost_factor,
+        local_block_value: params.local_block_value,
+        use_builder,
+    });
+
+    if use_builder {
+        if let Some(bid_archive) = &state.config.bid_archive {
+            bid_archive.record(BidArchiveRecord::BidReturned {
+                slot: params.slot,
+                validator_pubkey: params.pubkey,
+                block_hash: best_bid.block_hash(),
+                value: best_bid.value(),
+            });
+        }
+
+        Ok(Some(best_bid))
+    } else {
+        info!(
+            bid_value_eth = format_ether(best_bid.value()),
+            local_value_eth = params.local_block_value.map(format_ether),
+            builder_boost_factor,
+            "best bid doesn't clear local block value, forcing local building"
+        );
+        Ok(None)
+    }
+}
+
+/// Whether the best bid should be returned to the beacon node instead of
+/// building locally. The bid value is scaled by the boost factor (as a
+/// percentage) and must exceed the local block value by at least the
+/// configured premium
+fn use_builder_bid(
+    bid_value: U256,
+    builder_boost_factor: u64,
+    local_block_value: Option<U256>,
+    min_premium_wei: U256,
+) -> bool {
+    if builder_boost_factor == 0 {
+        return false;
+    }
+
+    let Some(local_block_value) = local_block_value else {
+        return true;
+    };
+
+    let boosted_value = bid_value.saturating_mul(U256::from(builder_boost_factor)) /
+        U256::from(DEFAULT_BUILDER_BOOST_FACTOR);
+    boosted_value > local_block_value.saturating_add(min_premium_wei)
+}
+
+/// Settings shared by all the header requests for a validator
+#[derive(Clone)]
+struct GetHeaderContext {
+    chain: Chain,
+    skip_sigverify: bool,
+    min_bid_wei: U256,
+    expected: ExpectedHeader,
+    expected_gas_limit: Shared<BoxFuture<'static, Option<u64>>>,
+    health: RelayHealth,
+    latencies: RelayLatencies,
+    adaptive_timing: AdaptiveTimingConfig,
+    bid_archive: Option<BidArchive>,
+}
+
+/// Values a header must have, each is `None` if its check is disabled or the
+/// value is unknown
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct ExpectedHeader {
+    fee_recipient: Option<Address>,
+    gas_limit: Option<u64>,
+    timestamp: Option<u64>,
+}
+
+/// Expected fee recipient and gas limit come from the validator's stored
+/// registration, so they're skipped if it hasn't registered yet. The gas limit
+/// is left unset here, see `fetch_expected_gas_limit`
+fn expected_header<S: BuilderApiState>(
+    params: &GetHeaderParams,
+    state: &PbsState<S>,
+) -> ExpectedHeader {
+    let pbs_config = state.pbs_config();
+    let mut expected = ExpectedHeader::default();
+
+    if pbs_config.validate_timestamp {
+        let slot_start_ms = timestamp_of_slot_start_millis(params.slot, state.config.chain);
+        expected.timestamp = Some(slot_start_ms / 1000);
+    }
+
+    if !pbs_config.validate_fee_recipient && !pbs_config.validate_gas_limit {
+        return expected;
+    }
+
+    let Some(registration) = state.registrations().get(&params.pubkey) else {
+        debug!("no registration for validator, skipping fee recipient and gas limit checks");
+        return expected;
+    };
+
+    if pbs_config.validate_fee_recipient {
+        expected.fee_recipient = Some(registration.message.fee_recipient);
+    }
+
+    expected
+}
+
+/// Expected gas limit, from the registered target and the gas limit of the
+/// parent block
+fn fetch_expected_gas_limit<S: BuilderApiState>(
+    params: &GetHeaderParams,
+    state: &PbsState<S>,
+) -> impl Future<Output = Option<u64>> + Send + 'static {
+    let target_gas_limit = state
+        .pbs_config()
+        .validate_gas_limit
+        .then(|| state.registrations().get(&params.pubkey))
+        .flatten()
+        .map(|registration| registration.message.gas_limit);
+    let beacon_client = state.config.beacon_client.clone();
+    let parent_hash = params.parent_hash;
+
+    async move {
+        let target_gas_limit = target_gas_limit?;
+        let parent = parent_gas_limit(beacon_client, parent_hash).await?;
+        Some(expected_gas_limit(parent, target_gas_limit))
+    }
+}
+
+/// Gas limit of the parent block, if it's the head of the beacon node
+async fn parent_gas_limit(beacon_client: Option<BeaconClient>, parent_hash: B256) -> Option<u64> {
+    let Some(beacon_client) = beacon_client else {
+        debug!("no beacon node configured, skipping gas limit check");
+        return None;
+    };
+
+    match beacon_client.get_execution_block("head").await {
+        Ok(head) if head.block_hash == parent_hash => Some(head.gas_limit),
+        Ok(head) => {
+            debug!(
+                head = %head.block_hash,
+                %parent_hash,
+                "beacon node head is not the parent block, skipping gas limit check"
+            );
+            None
+        }
+        Err(err) => {
+            warn!(?err, "failed to get parent block, skipping gas limit check");
+            None
+        }
+    }
+}
+
+/// Gas limit of a block built towards `target_gas_limit`, each block can move
+/// the gas limit by less than 1/1024 of the parent's
+fn expected_gas_limit(parent_gas_limit: u64, target_gas_limit: u64) -> u64 {
+    const MIN_GAS_LIMIT: u64 = 5000;
+
+    let max_delta = (parent_gas_limit / 1024).saturating_sub(1);
+    let target_gas_limit = target_gas_limit.max(MIN_GAS_LIMIT);
+
+    if parent_gas_limit < target_gas_limit {
+        parent_gas_limit.saturating_add(max_delta).min(target_gas_limit)
+    } else {
+        parent_gas_limit.saturating_sub(max_delta).max(target_gas_limit)
+    }
 }
 
 #[tracing::instrument(skip_all, name = "handler", fields(relay_id = relay.id.as_ref()))]
 async fn send_timed_get_header(
     params: GetHeaderParams,
     relay: RelayClient,
-    chain: Chain,
-    pbs_config: &PbsConfig,
+    ctx: GetHeaderContext,
     headers: HeaderMap,
     ms_into_slot: u64,
-    mut timeout_left_ms: u64,
+    timeout_left_ms: u64,
 ) -> Result<Option<GetHeaderReponse>, PbsError> {
-    let url = relay.get_header_url(params.slot, params.parent_hash, params.pubkey);
+    let req_config = RequestConfig { timeout_ms: timeout_left_ms, headers };
 
     if relay.config.enable_timing_games {
-        if let Some(target_ms) = relay.config.target_first_request_ms {
-            // sleep until target time in slot
-
-            let delay = target_ms.saturating_sub(ms_into_slot);
-            if delay > 0 {
-                debug!(target_ms, ms_into_slot, "TG: waiting to send first header request");
-                timeout_left_ms = timeout_left_ms.saturating_sub(delay);
-                sleep(Duration::from_millis(delay)).await;
-            } else {
-                debug!(target_ms, ms_into_slot, "TG: request already late enough in slot");
-            }
-        }
+        let schedule = timing_games_schedule(&relay, &ctx, ms_into_slot, timeout_left_ms);
+        return send_scheduled_get_headers(params, relay, ctx, req_config, schedule).await;
+    }
 
-        if let Some(send_freq_ms) = relay.config.frequency_get_header_ms {
-            let mut handles = Vec::new();
-
-            debug!(send_freq_ms, timeout_left_ms, "TG: sending multiple header requests");
-
-            loop {
-                handles.push(tokio::spawn(
-                    send_one_get_header(
-                        params,
-                        relay.clone(),
-                        chain,
-                        pbs_config.skip_sigverify,
-                        pbs_config.min_bid_wei,
-                        RequestConfig {
-                            timeout_ms: timeout_left_ms,
-                            url: url.clone(),
-                            headers: headers.clone(),
-                        },
-                    )
-                    .in_current_span(),
-                ));
-
-                if timeout_left_ms > send_freq_ms {
-                    // enough time for one more
-                    timeout_left_ms = timeout_left_ms.saturating_sub(send_freq_ms);
-                    sleep(Duration::from_millis(send_freq_ms)).await;
-                } else {
-                    break;
-                }
-            }
+    send_one_get_header(params, relay, ctx, req_config).await.map(|(_, maybe_header)| maybe_header)
+}
 
-            let results = join_all(handles).await;
-            let mut n_headers = 0;
-
-            if let Some((_, maybe_header)) = results
-                .into_iter()
-                .filter_map(|res| {
-                    // ignore join error and timeouts, log other errors
-                    res.ok().and_then(|inner_res| match inner_res {
-                        Ok(maybe_header) => {
-                            n_headers += 1;
-                            Some(maybe_header)
-                        }
-                        Err(err) if err.is_timeout() => None,
-                        Err(err) => {
-                            error!(?err, "TG: error sending header request");
-                            None
-                        }
-                    })
-                })
-                .max_by_key(|(start_time, _)| *start_time)
-            {
-                debug!(n_headers, "TG: received headers from relay");
-                return Ok(maybe_header);
-            } else {
-                // all requests failed
-                warn!("TG: no headers received");
-
-                return Err(PbsError::RelayResponse {
-                    error_msg: "no headers received".to_string(),
-                    code: TIMEOUT_ERROR_CODE,
-                });
+/// Times to send the header requests to a relay, in ms from now
+fn timing_games_schedule(
+    relay: &RelayClient,
+    ctx: &GetHeaderContext,
+    ms_into_slot: u64,
+    timeout_left_ms: u64,
+) -> Vec<u64> {
+    let frequency_ms = relay.config.frequency_get_header_ms;
+
+    if relay.config.adaptive_timing_games {
+        let config = &ctx.adaptive_timing;
+        match ctx.latencies.percentile(&relay.id, config.latency_percentile, config.min_samples) {
+            Some(latency_ms) => {
+                debug!(latency_ms, percentile = config.latency_percentile, "TG: adaptive schedule");
+                let lead_ms = latency_ms.saturating_add(config.safety_margin_ms);
+                return adaptive_schedule(timeout_left_ms, lead_ms, frequency_ms);
             }
+            None => debug!("TG: not enough latency data, using static settings"),
         }
     }
 
-    // if no timing games or no repeated send, just send one request
-    send_one_get_header(
-        params,
-        relay,
-        chain,
-        pbs_config.skip_sigverify,
-        pbs_config.min_bid_wei,
-        RequestConfig { timeout_ms: timeout_left_ms, url, headers },
+    static_schedule(
+        relay.config.target_first_request_ms,
+        frequency_ms,
+        ms_into_slot,
+        timeout_left_ms,
     )
-    .await
-    .map(|(_, maybe_header)| maybe_header)
 }
 
+/// First request at the target time in slot, then one every `frequency_ms`
+/// while there's more than `frequency_ms` left before the deadline
+fn static_schedule(
+    target_first_request_ms: Option<u64>,
+    frequency_ms: Option<u64>,
+    ms_into_slot: u64,
+    timeout_left_ms: u64,
+) -> Vec<u64> {
+    let first =
+        target_first_request_ms.map_or(0, |target_ms| target_ms.saturating_sub(ms_into_slot));
+    let mut schedule = vec![first];
+
+    if let Some(frequency_ms) = frequency_ms.filter(|ms| *ms > 0) {
+        let mut next = first;
+        while timeout_left_ms.saturating_sub(next) > frequency_ms {
+            next += frequency_ms;
+            schedule.push(next);
+        }
+    }
+
+    schedule
+}
+
+/// Last request `lead_ms` before the deadline, preceded by one every
+/// `frequency_ms`
+fn adaptive_schedule(timeout_left_ms: u64, lead_ms: u64, frequency_ms: Option<u64>) -> Vec<u64> {
+    let last = timeout_left_ms.saturating_sub(lead_ms);
+    let mut schedule = vec![last];
+
+    if let Some(frequency_ms) = frequency_ms.filter(|ms| *ms > 0) {
+        let mut prev = last;
+        while prev >= frequency_ms {
+            prev -= frequency_ms;
+            schedule.push(prev);
+        }
+        schedule.reverse();
+    }
+
+    schedule
+}
+
+/// Sends a request at each time of the schedule and returns the header of the
+/// last request that succeeded
+async fn send_scheduled_get_headers(
+    params: GetHeaderParams,
+    relay: RelayClient,
+    ctx: GetHeaderContext,
+    req_config: RequestConfig,
+    schedule: Vec<u64>,
+) -> Result<Option<GetHeaderReponse>, PbsError> {
+    let start = Instant::now();
+
+    if let [delay] = schedule[..] {
+        if delay > 0 {
+            debug!(delay, "TG: waiting to send header request");
+            sleep(Duration::from_millis(delay)).await;
+        }
+
+        let req_config =
+            RequestConfig { timeout_ms: req_config.timeout_ms.saturating_sub(delay), ..req_config };
+        return send_one_get_header(params, relay, ctx, req_config)
+            .await
+            .map(|(_, maybe_header)| maybe_header);
+    }
+
+    debug!(?schedule, timeout_ms = req_config.timeout_ms, "TG: sending multiple header requests");
+
+    let mut handles = Vec::with_capacity(schedule.len());
+    for offset in schedule {
+        sleep_until(start + Duration::from_millis(offset)).await;
+
+        handles.push(tokio::spawn(
+            send_one_get_header(params, relay.clone(), ctx.clone(), RequestConfig {
+                timeout_ms: req_config.timeout_ms.saturating_sub(offset),
+                headers: req_config.headers.clone(),
+            })
+            .in_current_span(),
+        ));
+    }
+
+    let results = join_all(handles).await;
+    let mut n_headers = 0;
+
+    if let Some((_, maybe_header)) = results
+        .into_iter()
+        .filter_map(|res| {
+            // ignore join error and timeouts, log other errors
+            res.ok().and_then(|inner_res| match inner_res {
+                Ok(maybe_header) => {
+                    n_headers += 1;
+                    Some(maybe_header)
+                }
+                Err(err) if err.is_timeout() => None,
+                Err(err) => {
+                    error!(?err, "TG: error sending header request");
+                    None
+                }
+            })
+        })
+        .max_by_key(|(start_time, _)| *start_time)
+    {
+        debug!(n_headers, "TG: received headers from relay");
+        Ok(maybe_header)
+    } else {
+        // all requests failed
+        warn!("TG: no headers received");
+
+        Err(PbsError::RelayResponse {
+            error_msg: "no headers received".to_string(),
+            code: TIMEOUT_ERROR_CODE,
+        })
+    }
+}
+
+#[derive(Clone)]
 struct RequestConfig {
-    url: String,
     timeout_ms: u64,
     headers: HeaderMap,
 }
 
+/// Sends the request to all the endpoints of the relay at once and returns the
+/// first valid header. Returns no header only if no endpoint returned one, and
+/// an error if all of them failed
 async fn send_one_get_header(
     params: GetHeaderParams,
     relay: RelayClient,
-    chain: Chain,
-    skip_sigverify: bool,
-    min_bid_wei: U256,
+    ctx: GetHeaderContext,
+    req_config: RequestConfig,
+) -> Result<(u64, Option<GetHeaderReponse>), PbsError> {
+    let outcomes = EndpointOutcomes::default();
+    let res = race_endpoints_get_header(params, &relay, &ctx, req_config, &outcomes).await;
+    outcomes.record(&ctx.health, &relay.id);
+    res
+}
+
+async fn race_endpoints_get_header(
+    params: GetHeaderParams,
+    relay: &RelayClient,
+    ctx: &GetHeaderContext,
+    req_config: RequestConfig,
+    outcomes: &EndpointOutcomes,
+) -> Result<(u64, Option<GetHeaderReponse>), PbsError> {
+    if let [endpoint] = relay.endpoints() {
+        return send_endpoint_get_header(params, relay, endpoint, ctx, req_config, outcomes).await;
+    }
+
+    let mut requests: FuturesUnordered<_> = relay
+        .endpoints()
+        .iter()
+        .map(|endpoint| {
+            send_endpoint_get_header(params, relay, endpoint, ctx, req_config.clone(), outcomes)
+        })
+        .collect();
+
+    let mut no_header = None;
+    let mut last_err = None;
+    while let Some(res) = requests.next().await {
+        match res {
+            Ok((start_time, Some(header))) => return Ok((start_time, Some(header))),
+            Ok(no_content) => no_header = Some(no_content),
+            Err(err) => last_err = Some(err),
+        }
+    }
+
+    match (no_header, last_err) {
+        (Some(no_content), _) => Ok(no_content),
+        (None, Some(err)) => Err(err),
+        (None, None) => unreachable!("relays have at least one endpoint"),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(endpoint = endpoint.id.as_str()))]
+async fn send_endpoint_get_header(
+    params: GetHeaderParams,
+    relay: &RelayClient,
+    endpoint: &RelayEndpoint,
+    ctx: &GetHeaderContext,
     mut req_config: RequestConfig,
+    outcomes: &EndpointOutcomes,
 ) -> Result<(u64, Option<GetHeaderReponse>), PbsError> {
+    let url = endpoint.get_header_url(params.slot, params.parent_hash, params.pubkey);
     // the timestamp in the header is the consensus block time which is fixed,
     // use the beginning of the request as proxy to make sure we use only the
     // last one received
     let start_request_time = utcnow_ms();
     req_config.headers.insert(HEADER_START_TIME_UNIX_MS, HeaderValue::from(start_request_time));
 
+    let timeout = Duration::from_millis(req_config.timeout_ms);
+    let mut encoding = relay.encoding();
+
     let start_request = Instant::now();
-    let res = match relay
-        .client
-        .get(req_config.url)
-        .timeout(Duration::from_millis(req_config.timeout_ms))
-        .headers(req_config.headers)
-        .send()
-        .await
-    {
-        Ok(res) => res,
-        Err(err) => {
-            RELAY_STATUS_CODE
-                .with_label_values(&[TIMEOUT_ERROR_CODE_STR, GET_HEADER_ENDPOINT_TAG, &relay.id])
-                .inc();
-            return Err(err.into());
+    let res = loop {
+        let mut headers = req_config.headers.clone();
+        if encoding == EncodingType::Ssz {
+            headers.insert(ACCEPT, HeaderValue::from_static(ACCEPT_SSZ_OR_JSON));
         }
+        inject_trace_context(&mut headers);
+
+        let res = match relay
+            .client
+            .get(&url)
+            .timeout(timeout.saturating_sub(start_request.elapsed()))
+            .headers(headers)
+            .send()
+            .await
+        {
+            Ok(res) => res,
+            Err(err) => {
+                RELAY_STATUS_CODE
+                    .with_label_values(&[
+                        TIMEOUT_ERROR_CODE_STR,
+                        GET_HEADER_ENDPOINT_TAG,
+                        &relay.id,
+                    ])
+                    .inc();
+                outcomes.add(RequestOutcome::from_error(&err));
+                return Err(err.into());
+            }
+        };
+
+        if relay.check_ssz_rejected(encoding, res.status()) {
+            encoding = EncodingType::Json;
+            continue;
+        }
+
+        break res;
     };
 
     let request_latency = start_request.elapsed();
     RELAY_LATENCY
-        .with_label_values(&[GET_HEADER_ENDPOINT_TAG, &relay.id])
+        .with_label_values(&[GET_HEADER_ENDPOINT_TAG, &relay.id, &endpoint.id])
         .observe(request_latency.as_secs_f64());
+    ctx.latencies.record(&relay.id, request_latency, ctx.adaptive_timing.window_size);
 
     let code = res.status();
     RELAY_STATUS_CODE.with_label_values(&[code.as_str(), GET_HEADER_ENDPOINT_TAG, &relay.id]).inc();
+    outcomes.add(RequestOutcome::from_response(code, request_latency));
+
+    let response_encoding = EncodingType::from_content_type(res.headers());
+    let response_version = get_consensus_version(res.headers()).ok().flatten();
 
     let response_bytes = res.bytes().await?;
     if !code.is_success() {
@@ -256,7 +633,16 @@ async fn send_one_get_header(
         return Ok((start_request_time, None));
     }
 
-    let get_header_response: GetHeaderReponse = serde_json::from_slice(&response_bytes)?;
+    let get_header_response = match response_encoding {
+        EncodingType::Json => serde_json::from_slice(&response_bytes)?,
+        EncodingType::Ssz => {
+            // relays should always set the version, otherwise use the one
+            // scheduled for the slot
+            let version = response_version.unwrap_or_else(|| ctx.chain.fork_at_slot(params.slot));
+            GetHeaderReponse::from_ssz_bytes(&response_bytes, version)
+                .map_err(|err| PbsError::SszDecodeError(format!("{err:?}")))?
+        }
+    };
 
     debug!(
         latency = ?request_latency,
@@ -265,39 +651,74 @@ async fn send_one_get_header(
         "received new header"
     );
 
-    validate_header(
-        &get_header_response.data,
-        chain,
+    // the parent block is fetched while the relays are queried
+    let time_left = timeout.saturating_sub(start_request.elapsed());
+    let gas_limit = tokio::time::timeout(time_left, ctx.expected_gas_limit.clone()).await;
+    let mut expected = ctx.expected;
+    expected.gas_limit = gas_limit.unwrap_or_else(|_| {
+        debug!("parent block not fetched in time, skipping gas limit check");
+        None
+    });
+
+    let validation = validate_header(
+        &get_header_response,
+        ctx.chain,
+        ctx.chain.fork_at_slot(params.slot),
         relay.pubkey(),
         params.parent_hash,
-        skip_sigverify,
-        min_bid_wei,
-    )?;
+        ctx.skip_sigverify,
+        ctx.min_bid_wei,
+    )
+    .and_then(|_| validate_header_fields(&get_header_response, &expected));
+
+    if let Some(bid_archive) = &ctx.bid_archive {
+        bid_archive.record(BidArchiveRecord::Header {
+            slot: params.slot,
+            parent_hash: params.parent_hash,
+            validator_pubkey: params.pubkey,
+            relay_id: relay.id.to_string(),
+            block_hash: get_header_response.block_hash(),
+            value: get_header_response.value(),
+            latency_ms: request_latency.as_millis() as u64,
+            request_time_ms: start_request_time,
+            validation_error: validation.as_ref().err().map(ToString::to_string),
+        });
+    }
+
+    validation?;
 
     Ok((start_request_time, Some(get_header_response)))
 }
 
 fn validate_header(
-    signed_header: &SignedExecutionPayloadHeader,
+    signed_header: &GetHeaderReponse,
     chain: Chain,
+    expected_version: Version,
     expected_relay_pubkey: BlsPublicKey,
     parent_hash: B256,
     skip_sig_verify: bool,
     minimum_bid_wei: U256,
 ) -> Result<(), ValidationError> {
-    let block_hash = signed_header.message.header.block_hash;
-    let received_relay_pubkey = signed_header.message.pubkey;
-    let tx_root = signed_header.message.header.transactions_root;
-    let value = signed_header.message.value();
+    let block_hash = signed_header.block_hash();
+    let received_relay_pubkey = signed_header.pubkey();
+    let tx_root = signed_header.transactions_root();
+    let value = signed_header.value();
+
+    if signed_header.version() != expected_version {
+        return Err(ValidationError::VersionMismatch {
+            expected: expected_version,
+            got: signed_header.version(),
+        });
+    }
 
     if block_hash == B256::ZERO {
         return Err(ValidationError::EmptyBlockhash);
     }
 
-    if parent_hash != signed_header.message.header.parent_hash {
+    if parent_hash != signed_header.parent_hash() {
         return Err(ValidationError::ParentHashMismatch {
             expected: parent_hash,
-            got: signed_header.message.header.parent_hash,
+            got: signed_header.parent_hash(),
         });
     }
 
@@ -317,37 +738,82 @@ fn validate_header(
     }
 
     if !skip_sig_verify {
-        verify_signed_builder_message(
-            chain,
-            &received_relay_pubkey,
-            &signed_header.message,
-            &signed_header.signature,
-        )
+        match signed_header {
+            GetHeaderReponse::Deneb(bid) => verify_signed_builder_message(
+                chain,
+                &received_relay_pubkey,
+                &bid.message,
+                &bid.signature,
+            ),
+            GetHeaderReponse::Electra(bid) => verify_signed_builder_message(
+                chain,
+                &received_relay_pubkey,
+                &bid.message,
+                &bid.signature,
+            ),
+        }
         .map_err(ValidationError::Sigverify)?;
     }
 
     Ok(())
 }
 
+fn validate_header_fields(
+    signed_header: &GetHeaderReponse,
+    expected: &ExpectedHeader,
+) -> Result<(), ValidationError> {
+    if let Some(expected) = expected.fee_recipient {
+        if signed_header.fee_recipient() != expected {
+            return Err(ValidationError::FeeRecipientMismatch {
+                expected,
+                got: signed_header.fee_recipient(),
+            });
+        }
+    }
+
+    if let Some(expected) = expected.gas_limit {
+        if signed_header.gas_limit() != expected {
+            return Err(ValidationError::GasLimitMismatch {
+                expected,
+                got: signed_header.gas_limit(),
+            });
+        }
+    }
+
+    if let Some(expected) = expected.timestamp {
+        if signed_header.timestamp() != expected {
+            return Err(ValidationError::TimestampMismatch {
+                expected,
+                got: signed_header.timestamp(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use alloy::{
-        primitives::{B256, U256},
+        primitives::{Address, B256, U256},
         rpc::types::beacon::BlsPublicKey,
     };
     use blst::min_pk;
     use cb_common::{
-        pbs::{SignedExecutionPayloadHeader, EMPTY_TX_ROOT_HASH},
+        pbs::{GetHeaderReponse, SignedExecutionPayloadHeaderDeneb, Version, EMPTY_TX_ROOT_HASH},
         signature::sign_builder_message,
         types::Chain,
     };
 
-    use super::validate_header;
+    use super::{
+        adaptive_schedule, expected_gas_limit, static_schedule, use_builder_bid, validate_header,
+        validate_header_fields, ExpectedHeader,
+    };
     use crate::error::ValidationError;
 
     #[test]
     fn test_validate_header() {
-        let mut mock_header = SignedExecutionPayloadHeader::default();
+        let mut mock_header = SignedExecutionPayloadHeaderDeneb::default();
 
         let parent_hash = B256::from_slice(&[1; 32]);
         let chain = Chain::Holesky;
@@ -365,8 +831,25 @@ mod tests {
 
         assert_eq!(
             validate_header(
-                &mock_header,
+                &GetHeaderReponse::Deneb(mock_header.clone()),
+                chain,
+                Version::Electra,
+                BlsPublicKey::default(),
+                parent_hash,
+                false,
+                min_bid
+            ),
+            Err(ValidationError::VersionMismatch {
+                expected: Version::Electra,
+                got: Version::Deneb
+            })
+        );
+
+        assert_eq!(
+            validate_header(
+                &GetHeaderReponse::Deneb(mock_header.clone()),
                 chain,
+                Version::Deneb,
                 BlsPublicKey::default(),
                 parent_hash,
                 false,
@@ -379,8 +862,9 @@ mod tests {
 
         assert_eq!(
             validate_header(
-                &mock_header,
+                &GetHeaderReponse::Deneb(mock_header.clone()),
                 chain,
+                Version::Deneb,
                 BlsPublicKey::default(),
                 parent_hash,
                 false,
@@ -396,8 +880,9 @@ mod tests {
 
         assert_eq!(
             validate_header(
-                &mock_header,
+                &GetHeaderReponse::Deneb(mock_header.clone()),
                 chain,
+                Version::Deneb,
                 BlsPublicKey::default(),
                 parent_hash,
                 false,
@@ -410,8 +895,9 @@ mod tests {
 
         assert_eq!(
             validate_header(
-                &mock_header,
+                &GetHeaderReponse::Deneb(mock_header.clone()),
                 chain,
+                Version::Deneb,
                 BlsPublicKey::default(),
                 parent_hash,
                 false,
@@ -426,8 +912,9 @@ mod tests {
 
         assert_eq!(
             validate_header(
-                &mock_header,
+                &GetHeaderReponse::Deneb(mock_header.clone()),
                 chain,
+                Version::Deneb,
                 BlsPublicKey::default(),
                 parent_hash,
                 false,
@@ -437,13 +924,135 @@ mod tests {
         );
 
         assert!(matches!(
-            validate_header(&mock_header, chain, pubkey, parent_hash, false, min_bid),
+            validate_header(
+                &GetHeaderReponse::Deneb(mock_header.clone()),
+                chain,
+                Version::Deneb,
+                pubkey,
+                parent_hash,
+                false,
+                min_bid
+            ),
             Err(ValidationError::Sigverify(_))
         ));
-        assert!(validate_header(&mock_header, chain, pubkey, parent_hash, true, min_bid).is_ok());
+        assert!(validate_header(
+            &GetHeaderReponse::Deneb(mock_header.clone()),
+            chain,
+            Version::Deneb,
+            pubkey,
+            parent_hash,
+            true,
+            min_bid
+        )
+        .is_ok());
 
         mock_header.signature = sign_builder_message(chain, &secret_key, &mock_header.message);
 
-        assert!(validate_header(&mock_header, chain, pubkey, parent_hash, false, min_bid).is_ok())
+        assert!(validate_header(
+            &GetHeaderReponse::Deneb(mock_header.clone()),
+            chain,
+            Version::Deneb,
+            pubkey,
+            parent_hash,
+            false,
+            min_bid
+        )
+        .is_ok())
+    }
+
+    #[test]
+    fn test_use_builder_bid() {
+        let bid = U256::from(100);
+        let no_premium = U256::ZERO;
+
+        // no local value, only a boost factor of 0 forces local building
+        assert!(use_builder_bid(bid, 100, None, no_premium));
+        assert!(!use_builder_bid(bid, 0, None, no_premium));
+
+        assert!(use_builder_bid(bid, 100, Some(U256::from(99)), no_premium));
+        assert!(!use_builder_bid(bid, 100, Some(U256::from(100)), no_premium));
+        assert!(!use_builder_bid(bid, 90, Some(U256::from(95)), no_premium));
+        assert!(use_builder_bid(bid, 110, Some(U256::from(105)), no_premium));
+
+        assert!(!use_builder_bid(bid, 100, Some(U256::from(90)), U256::from(10)));
+        assert!(use_builder_bid(bid, 100, Some(U256::from(89)), U256::from(10)));
+    }
+
+    #[test]
+    fn test_validate_header_fields() {
+        let mut mock_header = SignedExecutionPayloadHeaderDeneb::default();
+        mock_header.message.header.fee_recipient.0 = [1; 20];
+        mock_header.message.header.gas_limit = 30_000_000;
+        mock_header.message.header.timestamp = 1_700_000_000;
+        let header = GetHeaderReponse::Deneb(mock_header);
+
+        assert!(validate_header_fields(&header, &ExpectedHeader::default()).is_ok());
+
+        let expected = ExpectedHeader {
+            fee_recipient: Some(Address::repeat_byte(1)),
+            gas_limit: Some(30_000_000),
+            timestamp: Some(1_700_000_000),
+        };
+        assert!(validate_header_fields(&header, &expected).is_ok());
+
+        assert_eq!(
+            validate_header_fields(&header, &ExpectedHeader {
+                fee_recipient: Some(Address::repeat_byte(2)),
+                ..expected
+            }),
+            Err(ValidationError::FeeRecipientMismatch {
+                expected: Address::repeat_byte(2),
+                got: Address::repeat_byte(1)
+            })
+        );
+
+        assert_eq!(
+            validate_header_fields(&header, &ExpectedHeader {
+                gas_limit: Some(36_000_000),
+                ..expected
+            }),
+            Err(ValidationError::GasLimitMismatch { expected: 36_000_000, got: 30_000_000 })
+        );
+
+        assert_eq!(
+            validate_header_fields(&header, &ExpectedHeader {
+                timestamp: Some(1_700_000_012),
+                ..expected
+            }),
+            Err(ValidationError::TimestampMismatch { expected: 1_700_000_012, got: 1_700_000_000 })
+        );
+    }
+
+    #[test]
+    fn test_expected_gas_limit() {
+        // already at target
+        assert_eq!(expected_gas_limit(30_000_000, 30_000_000), 30_000_000);
+
+        // moves by at most parent / 1024 - 1
+        assert_eq!(expected_gas_limit(30_000_000, 36_000_000), 30_029_295);
+        assert_eq!(expected_gas_limit(30_000_000, 20_000_000), 29_970_705);
+
+        // stops at the target
+        assert_eq!(expected_gas_limit(30_000_000, 30_010_000), 30_010_000);
+        assert_eq!(expected_gas_limit(30_000_000, 29_990_000), 29_990_000);
+    }
+
+    #[test]
+    fn test_static_schedule() {
+        // example from the config docs, request at 100ms with 950ms timeout
+        assert_eq!(static_schedule(Some(200), Some(300), 100, 950), vec![100, 400, 700]);
+        // request at 1500ms with 500ms left
+        assert_eq!(static_schedule(Some(200), Some(300), 1500, 500), vec![0, 300]);
+        assert_eq!(static_schedule(Some(200), None, 100, 950), vec![100]);
+        assert_eq!(static_schedule(None, Some(0), 100, 950), vec![0]);
+    }
+
+    #[test]
+    fn test_adaptive_schedule() {
+        // last request 200ms before the deadline
+        assert_eq!(adaptive_schedule(950, 200, Some(300)), vec![150, 450, 750]);
+        assert_eq!(adaptive_schedule(950, 200, None), vec![750]);
+        // slower than the time left, send right away
+        assert_eq!(adaptive_schedule(500, 800, Some(300)), vec![0]);
     }
 }
@@ -5,5 +5,7 @@ mod submit_block;
 
 pub use get_header::get_header;
 pub use register_validator::register_validator;
+pub(crate) use register_validator::{rebroadcast_registrations, send_stored_registrations};
 pub use status::get_status;
+pub(crate) use status::probe_tripped_relays;
 pub use submit_block::submit_block;
@@ -1,64 +1,355 @@
-use std::time::{Duration, Instant};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
 use alloy::rpc::types::beacon::relay::ValidatorRegistration;
 use axum::http::{HeaderMap, HeaderValue};
 use cb_common::{
-    pbs::{RelayClient, HEADER_START_TIME_UNIX_MS},
-    utils::{get_user_agent_with_version, utcnow_ms},
+    config::{RegistrationsConfig, RuntimeMuxConfig},
+    pbs::{RelayClient, RelayEndpoint, HEADER_START_TIME_UNIX_MS},
+    utils::{get_user_agent_with_version, inject_trace_context, utcnow_ms},
 };
 use eyre::bail;
-use futures::future::join_all;
+use futures::{future::join_all, stream, StreamExt};
 use reqwest::header::USER_AGENT;
-use tracing::{debug, error};
+use tokio::time::sleep;
+use tracing::{debug, error, info, warn};
 
 use crate::{
-    constants::{REGISTER_VALIDATOR_ENDPOINT_TAG, TIMEOUT_ERROR_CODE_STR},
+    constants::{
+        REGISTER_VALIDATOR_ENDPOINT_TAG, REGISTRATION_MAX_RETRY_DELAY_MS,
+        REGISTRATION_RETRY_DELAY_MS, TIMEOUT_ERROR_CODE_STR,
+    },
     error::PbsError,
-    metrics::{RELAY_LATENCY, RELAY_STATUS_CODE},
+    health::{EndpointOutcomes, RelayHealth, RequestOutcome},
+    metrics::{RELAY_LATENCY, RELAY_REGISTRATION_BATCHES, RELAY_STATUS_CODE},
+    registrations::verify_registrations,
     state::{BuilderApiState, PbsState},
 };
 
 /// Implements https://ethereum.github.io/builder-specs/#/Builder/registerValidator
-/// Returns 200 if at least one relay returns 200, else 503
+/// Registrations are verified, and by default only the ones that are new or
+/// updated since relays last accepted them are forwarded. Returns 200 if at
+/// least one relay of each mux returns 200, else 503
 pub async fn register_validator<S: BuilderApiState>(
     registrations: Vec<ValidatorRegistration>,
     req_headers: HeaderMap,
     state: PbsState<S>,
 ) -> eyre::Result<()> {
-    // prepare headers
+    let send_headers = registration_headers(&req_headers)?;
+
+    let config = &state.pbs_config().registrations;
+    let forward_empty = registrations.is_empty();
+    let (registrations, invalid) = if config.skip_sigverify {
+        (registrations, 0)
+    } else {
+        // thousands of signatures can take seconds to verify
+        let chain = state.config.chain;
+        tokio::task::spawn_blocking(move || verify_registrations(chain, registrations)).await?
+    };
+
+    let update = state.registrations().diff(registrations);
+    debug!(
+        changed = update.changed.len(),
+        unchanged = update.unchanged.len(),
+        invalid,
+        "checked registrations"
+    );
+
+    let mut registrations = update.changed.clone();
+    if !config.only_forward_changes {
+        registrations.extend(update.unchanged.iter().cloned());
+    }
+
+    if registrations.is_empty() && !forward_empty {
+        if invalid > 0 {
+            bail!("all {invalid} registrations have an invalid signature");
+        }
+
+        debug!("no new registrations, skipping relays");
+        state.registrations().store(&update.unchanged);
+        return Ok(());
+    }
+
+    send_registrations(registrations, send_headers, &state, None).await?;
+
+    // only stored once accepted, so they are not skipped when the beacon node
+    // retries after a failure
+    state.registrations().store(&update.changed);
+    state.registrations().store(&update.unchanged);
+    Ok(())
+}
+
+/// Periodically sends all the stored registrations to relays, so that relays
+/// which missed an update or lost their state get them again
+pub(crate) async fn rebroadcast_registrations<S: BuilderApiState>(state: PbsState<S>) {
+    let interval_secs = state.pbs_config().registrations.rebroadcast_interval_secs;
+    if interval_secs == 0 {
+        return;
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+    // the first tick completes immediately, when nothing is stored yet
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+
+        let state = state.with_latest_config();
+        let registrations = state.registrations().all();
+        if registrations.is_empty() {
+            continue;
+        }
+
+        info!(count = registrations.len(), "rebroadcasting registrations");
+        let res = match registration_headers(&HeaderMap::new()) {
+            Ok(headers) => send_registrations(registrations, headers, &state, None).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = res {
+            warn!(?err, "failed to rebroadcast registrations");
+        }
+    }
+}
+
+/// Sends all the stored registrations to a relay, e.g. after it was re-enabled
+/// by the circuit breaker
+pub(crate) async fn send_stored_registrations<S: BuilderApiState>(
+    state: &PbsState<S>,
+    relay: &RelayClient,
+) {
+    let registrations = state.registrations().all();
+    if registrations.is_empty() {
+        return;
+    }
+
+    info!(
+        relay_id = relay.id.as_ref(),
+        count = registrations.len(),
+        "sending stored registrations"
+    );
+    let res = match registration_headers(&HeaderMap::new()) {
+        Ok(headers) => send_registrations(registrations, headers, state, Some(&relay.id)).await,
+        Err(err) => Err(err),
+    };
+    if let Err(err) = res {
+        warn!(?err, relay_id = relay.id.as_ref(), "failed to send stored registrations");
+    }
+}
+
+fn registration_headers(req_headers: &HeaderMap) -> eyre::Result<HeaderMap> {
     let mut send_headers = HeaderMap::new();
     send_headers
         .insert(HEADER_START_TIME_UNIX_MS, HeaderValue::from_str(&utcnow_ms().to_string())?);
-    send_headers.insert(USER_AGENT, get_user_agent_with_version(&req_headers)?);
+    send_headers.insert(USER_AGENT, get_user_agent_with_version(req_headers)?);
+    Ok(send_headers)
+}
 
-    let relays = state.relays();
-    let mut handles = Vec::with_capacity(relays.len());
-    for relay in relays {
-        handles.push(send_register_validator(
-            registrations.clone(),
-            relay,
-            send_headers.clone(),
-            state.pbs_config().timeout_register_validator_ms,
-        ));
+/// Sends registrations to the healthy relays of their mux, or only to the
+/// given relay if set. Registrations are split in batches, and a mux succeeds
+/// if each of its batches was accepted by at least one relay
+async fn send_registrations<S: BuilderApiState>(
+    registrations: Vec<ValidatorRegistration>,
+    send_headers: HeaderMap,
+    state: &PbsState<S>,
+    only_relay: Option<&str>,
+) -> eyre::Result<()> {
+    let config = &state.pbs_config().registrations;
+
+    // group registrations by mux, validators without one use the global relays
+    let mut groups: HashMap<Option<&str>, (Option<&RuntimeMuxConfig>, Vec<_>)> = HashMap::new();
+    for registration in registrations {
+        let mux = state.mux(&registration.message.pubkey);
+        groups
+            .entry(mux.map(|mux| mux.id.as_str()))
+            .or_insert_with(|| (mux, Vec::new()))
+            .1
+            .push(registration);
+    }
+    if groups.is_empty() {
+        // still forward empty batches to the global relays
+        groups.insert(None, (None, Vec::new()));
+    }
+
+    let mut group_handles = Vec::with_capacity(groups.len());
+    for (mux_id, (mux, registrations)) in groups {
+        let mut relays = state.healthy_relays(mux.map_or(state.relays(), |mux| &mux.relays));
+        if let Some(relay_id) = only_relay {
+            relays.retain(|relay| relay.id.as_str() == relay_id);
+            if relays.is_empty() {
+                continue;
+            }
+        }
+
+        let timeout_ms = mux
+            .and_then(|mux| mux.timeout_register_validator_ms)
+            .unwrap_or(state.pbs_config().timeout_register_validator_ms);
+        let batches = into_batches(registrations, config.batch_size);
+
+        let headers = &send_headers;
+        group_handles.push(async move {
+            let mut handles = Vec::with_capacity(relays.len());
+            for relay in relays {
+                handles.push(send_batches(
+                    &batches,
+                    relay,
+                    headers,
+                    timeout_ms,
+                    state.relay_health(),
+                    config,
+                ));
+            }
+
+            // await for all so we avoid cancelling any pending registrations
+            let results = join_all(handles).await;
+            let accepted =
+                (0..batches.len()).filter(|&i| results.iter().any(|accepted| accepted[i])).count();
+            if accepted < batches.len() {
+                warn!(
+                    mux_id = mux_id.unwrap_or("default"),
+                    accepted,
+                    total = batches.len(),
+                    "some registration batches were not accepted by any relay"
+                );
+            }
+
+            (mux_id, accepted == batches.len())
+        });
+    }
+
+    let mut failed_muxes = Vec::new();
+    for (mux_id, success) in join_all(group_handles).await {
+        if !success {
+            failed_muxes.push(mux_id.unwrap_or("default"));
+        }
     }
 
-    // await for all so we avoid cancelling any pending registrations
-    let results = join_all(handles).await;
-    if results.iter().any(|res| res.is_ok()) {
+    if failed_muxes.is_empty() {
         Ok(())
     } else {
-        bail!("No relay passed register_validator successfully")
+        bail!("No relay passed register_validator successfully for muxes: {failed_muxes:?}")
+    }
+}
+
+/// Splits registrations in batches of at most `batch_size`, or a single one if
+/// it's 0. There's always at least one batch, so empty requests are forwarded
+fn into_batches(
+    registrations: Vec<ValidatorRegistration>,
+    batch_size: usize,
+) -> Vec<Vec<ValidatorRegistration>> {
+    if batch_size == 0 || registrations.len() <= batch_size {
+        return vec![registrations];
     }
+
+    registrations.chunks(batch_size).map(|batch| batch.to_vec()).collect()
 }
 
+/// Sends batches to a relay, with at most `max_concurrent_batches` requests in
+/// flight. Returns whether each batch was accepted
 #[tracing::instrument(skip_all, name = "handler", fields(relay_id = relay.id.as_ref()))]
+async fn send_batches(
+    batches: &[Vec<ValidatorRegistration>],
+    relay: &RelayClient,
+    headers: &HeaderMap,
+    timeout_ms: u64,
+    health: &RelayHealth,
+    config: &RegistrationsConfig,
+) -> Vec<bool> {
+    stream::iter(batches)
+        .map(|batch| send_batch(batch, relay, headers, timeout_ms, health, config.batch_retries))
+        .buffered(config.max_concurrent_batches.max(1))
+        .collect()
+        .await
+}
+
+async fn send_batch(
+    batch: &[ValidatorRegistration],
+    relay: &RelayClient,
+    headers: &HeaderMap,
+    timeout_ms: u64,
+    health: &RelayHealth,
+    retries: u32,
+) -> bool {
+    let mut attempt = 0;
+    loop {
+        match send_register_validator(batch, relay, headers.clone(), timeout_ms, health).await {
+            Ok(()) => {
+                RELAY_REGISTRATION_BATCHES.with_label_values(&["accepted", &relay.id]).inc();
+                return true;
+            }
+
+            Err(err) if attempt < retries && err.is_retryable() => {
+                let delay_ms = retry_delay_ms(attempt);
+                attempt += 1;
+                warn!(?err, attempt, delay_ms, size = batch.len(), "retrying registration batch");
+                sleep(Duration::from_millis(delay_ms)).await;
+            }
+
+            Err(err) => {
+                RELAY_REGISTRATION_BATCHES.with_label_values(&["failed", &relay.id]).inc();
+                error!(?err, size = batch.len(), "failed registration batch");
+                return false;
+            }
+        }
+    }
+}
+
+/// Delay before a retry of a registration batch, doubled after each attempt up
+/// to `REGISTRATION_MAX_RETRY_DELAY_MS`
+fn retry_delay_ms(attempt: u32) -> u64 {
+    let factor = 1u64 << attempt.min(u64::BITS - 1);
+    REGISTRATION_RETRY_DELAY_MS.saturating_mul(factor).min(REGISTRATION_MAX_RETRY_DELAY_MS)
+}
+
+/// Sends the registrations to the endpoints of the relay in order, until one
+/// accepts them
 async fn send_register_validator(
-    registrations: Vec<ValidatorRegistration>,
+    registrations: &[ValidatorRegistration],
     relay: &RelayClient,
     headers: HeaderMap,
     timeout_ms: u64,
+    health: &RelayHealth,
+) -> Result<(), PbsError> {
+    let outcomes = EndpointOutcomes::default();
+    let mut last_err = None;
+    for endpoint in relay.endpoints() {
+        let res = send_endpoint_register_validator(
+            registrations,
+            relay,
+            endpoint,
+            headers.clone(),
+            timeout_ms,
+            &outcomes,
+        )
+        .await;
+
+        match res {
+            Ok(()) => {
+                last_err = None;
+                break;
+            }
+            Err(err) => {
+                debug!(?err, endpoint = endpoint.id.as_str(), "registration failed on relay url");
+                last_err = Some(err);
+            }
+        }
+    }
+
+    outcomes.record(health, &relay.id);
+    last_err.map_or(Ok(()), Err)
+}
+
+async fn send_endpoint_register_validator(
+    registrations: &[ValidatorRegistration],
+    relay: &RelayClient,
+    endpoint: &RelayEndpoint,
+    mut headers: HeaderMap,
+    timeout_ms: u64,
+    outcomes: &EndpointOutcomes,
 ) -> Result<(), PbsError> {
-    let url = relay.register_validator_url();
+    let url = endpoint.register_validator_url();
+    inject_trace_context(&mut headers);
 
     let start_request = Instant::now();
     let res = match relay
@@ -66,7 +357,7 @@ async fn send_register_validator(
         .post(url)
         .timeout(Duration::from_millis(timeout_ms))
         .headers(headers)
-        .json(&registrations)
+        .json(registrations)
         .send()
         .await
     {
@@ -79,32 +370,66 @@ async fn send_register_validator(
                     &relay.id,
                 ])
                 .inc();
+            outcomes.add(RequestOutcome::from_error(&err));
             return Err(err.into());
         }
     };
     let request_latency = start_request.elapsed();
     RELAY_LATENCY
-        .with_label_values(&[REGISTER_VALIDATOR_ENDPOINT_TAG, &relay.id])
+        .with_label_values(&[REGISTER_VALIDATOR_ENDPOINT_TAG, &relay.id, &endpoint.id])
         .observe(request_latency.as_secs_f64());
 
     let code = res.status();
     RELAY_STATUS_CODE
         .with_label_values(&[code.as_str(), REGISTER_VALIDATOR_ENDPOINT_TAG, &relay.id])
         .inc();
+    outcomes.add(RequestOutcome::from_response(code, request_latency));
 
     let response_bytes = res.bytes().await?;
     if !code.is_success() {
-        let err = PbsError::RelayResponse {
+        return Err(PbsError::RelayResponse {
             error_msg: String::from_utf8_lossy(&response_bytes).into_owned(),
             code: code.as_u16(),
-        };
-
-        // error here since we check if any success aboves
-        error!(?err, "failed registration");
-        return Err(err);
+        });
     };
 
-    debug!(?code, latency = ?request_latency, "registration successful");
+    debug!(?code, latency = ?request_latency, size = registrations.len(), "registration successful");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_batches() {
+        let data = include_str!("../../../../tests/data/registration_holesky.json");
+        let registrations: Vec<ValidatorRegistration> = serde_json::from_str(data).unwrap();
+        assert_eq!(registrations.len(), 17);
+
+        let batches = into_batches(registrations.clone(), 5);
+        assert_eq!(batches.iter().map(Vec::len).collect::<Vec<_>>(), vec![5, 5, 5, 2]);
+        assert!(batches
+            .concat()
+            .iter()
+            .map(|registration| registration.message.pubkey)
+            .eq(registrations.iter().map(|registration| registration.message.pubkey)));
+
+        assert_eq!(into_batches(registrations.clone(), 0).len(), 1);
+        assert_eq!(into_batches(registrations, 17).len(), 1);
+
+        let batches = into_batches(vec![], 5);
+        assert_eq!(batches.len(), 1);
+        assert!(batches[0].is_empty());
+    }
+
+    #[test]
+    fn test_retry_delay_ms() {
+        assert_eq!(retry_delay_ms(0), REGISTRATION_RETRY_DELAY_MS);
+        assert_eq!(retry_delay_ms(2), 4 * REGISTRATION_RETRY_DELAY_MS);
+        assert_eq!(retry_delay_ms(10), REGISTRATION_MAX_RETRY_DELAY_MS);
+        assert_eq!(retry_delay_ms(64), REGISTRATION_MAX_RETRY_DELAY_MS);
+        assert_eq!(retry_delay_ms(u32::MAX), REGISTRATION_MAX_RETRY_DELAY_MS);
+    }
+}
@@ -1,15 +1,19 @@
 use std::time::{Duration, Instant};
 
 use axum::http::HeaderMap;
-use cb_common::{pbs::RelayClient, utils::get_user_agent_with_version};
-use futures::future::select_ok;
+use cb_common::{
+    pbs::{RelayClient, RelayEndpoint},
+    utils::{get_user_agent_with_version, inject_trace_context},
+};
+use futures::future::{join_all, select_ok};
 use reqwest::header::USER_AGENT;
 use tracing::{debug, error};
 
 use crate::{
-    constants::{STATUS_ENDPOINT_TAG, TIMEOUT_ERROR_CODE_STR},
+    constants::{RELAY_PROBE_INTERVAL_MS, STATUS_ENDPOINT_TAG, TIMEOUT_ERROR_CODE_STR},
     error::PbsError,
     metrics::{RELAY_LATENCY, RELAY_STATUS_CODE},
+    mev_boost::send_stored_registrations,
     state::{BuilderApiState, PbsState},
 };
 
@@ -43,9 +47,61 @@ pub async fn get_status<S: BuilderApiState>(
     }
 }
 
+/// Periodically probes relays tripped by the circuit breaker once their
+/// cooldown expires, re-enabling the ones that pass the status check. Stored
+/// registrations are sent again to re-enabled relays, as they may have missed
+/// some while tripped
+pub async fn probe_tripped_relays<S: BuilderApiState>(state: PbsState<S>) {
+    let mut send_headers = HeaderMap::new();
+    if let Ok(user_agent) = get_user_agent_with_version(&HeaderMap::new()) {
+        send_headers.insert(USER_AGENT, user_agent);
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_millis(RELAY_PROBE_INTERVAL_MS));
+    loop {
+        interval.tick().await;
+
+        // relays may have changed since the last tick if the config was reloaded
+        let state = state.with_latest_config();
+        let state = &state;
+        let health = state.relay_health();
+        let probes =
+            state.relays().iter().filter(|relay| health.start_probe(&relay.id)).map(|relay| {
+                let headers = send_headers.clone();
+                async move {
+                    let res = send_relay_check(relay, headers).await;
+                    if health.record_probe(&relay.id, res.is_ok()) {
+                        send_stored_registrations(state, relay).await;
+                    }
+                }
+            });
+
+        join_all(probes).await;
+    }
+}
+
+/// Checks all the endpoints of the relay at once, passes if any of them does
 #[tracing::instrument(skip_all, name = "handler", fields(relay_id = relay.id.as_ref()))]
 async fn send_relay_check(relay: &RelayClient, headers: HeaderMap) -> Result<(), PbsError> {
-    let url = relay.get_status_url();
+    if let [endpoint] = relay.endpoints() {
+        return send_endpoint_check(relay, endpoint, headers).await;
+    }
+
+    let checks = relay
+        .endpoints()
+        .iter()
+        .map(|endpoint| Box::pin(send_endpoint_check(relay, endpoint, headers.clone())));
+    select_ok(checks).await.map(|_| ())
+}
+
+#[tracing::instrument(skip_all, fields(endpoint = endpoint.id.as_str()))]
+async fn send_endpoint_check(
+    relay: &RelayClient,
+    endpoint: &RelayEndpoint,
+    mut headers: HeaderMap,
+) -> Result<(), PbsError> {
+    let url = endpoint.get_status_url();
+    inject_trace_context(&mut headers);
 
     let start_request = Instant::now();
     let res = match relay
@@ -66,7 +122,7 @@ async fn send_relay_check(relay: &RelayClient, headers: HeaderMap) -> Result<(),
     };
     let request_latency = start_request.elapsed();
     RELAY_LATENCY
-        .with_label_values(&[STATUS_ENDPOINT_TAG, &relay.id])
+        .with_label_values(&[STATUS_ENDPOINT_TAG, &relay.id, &endpoint.id])
         .observe(request_latency.as_secs_f64());
 
     let code = res.status();
@@ -1,21 +1,26 @@
 use std::time::{Duration, Instant};
 
+use alloy::primitives::{Address, Bytes, B256};
 use axum::http::{HeaderMap, HeaderValue};
 use cb_common::{
     pbs::{
-        RelayClient, SignedBlindedBeaconBlock, SubmitBlindedBlockResponse, HEADER_SLOT_UUID_KEY,
-        HEADER_START_TIME_UNIX_MS,
+        verify_blobs_bundle, BidArchiveRecord, BlobsBundle, EncodingType, EthSpec,
+        ExecutionPayload, ExecutionPayloadHeader, KzgCommitment, RelayClient, RelayEndpoint,
+        SignedBlindedBeaconBlock, SubmitBlindedBlockResponse, ACCEPT_SSZ_OR_JSON,
+        HEADER_CONSENSUS_VERSION, HEADER_SLOT_UUID_KEY, HEADER_START_TIME_UNIX_MS,
     },
-    utils::{get_user_agent_with_version, utcnow_ms},
+    utils::{get_consensus_version, get_user_agent_with_version, inject_trace_context, utcnow_ms},
 };
-use futures::future::select_ok;
-use reqwest::header::USER_AGENT;
+use eyre::bail;
+use futures::{future::select_ok, TryFutureExt};
+use reqwest::header::{ACCEPT, CONTENT_TYPE, USER_AGENT};
 use tracing::{debug, warn};
 
 use crate::{
     constants::{SUBMIT_BLINDED_BLOCK_ENDPOINT_TAG, TIMEOUT_ERROR_CODE_STR},
     error::{PbsError, ValidationError},
-    metrics::{RELAY_LATENCY, RELAY_STATUS_CODE},
+    health::{EndpointOutcomes, RequestOutcome},
+    metrics::{BLOBS_BUNDLE_VERIFICATION_LATENCY, RELAY_LATENCY, RELAY_STATUS_CODE},
     state::{BuilderApiState, PbsState},
 };
 
@@ -32,67 +37,161 @@ pub async fn submit_block<S: BuilderApiState>(
     send_headers.insert(HEADER_SLOT_UUID_KEY, HeaderValue::from_str(&slot_uuid.to_string())?);
     send_headers.insert(HEADER_START_TIME_UNIX_MS, HeaderValue::from(utcnow_ms()));
     send_headers.insert(USER_AGENT, get_user_agent_with_version(&req_headers)?);
+    send_headers.insert(
+        HEADER_CONSENSUS_VERSION,
+        HeaderValue::from_static(signed_blinded_block.version().as_str()),
+    );
 
-    let relays = state.relays();
+    // tripped relays are not skipped, as they may still have the payload
+    let relays = submit_block_relays(
+        &state,
+        signed_blinded_block.slot(),
+        signed_blinded_block.block_hash(),
+    )?;
+    let outcomes: Vec<_> = relays.iter().map(|_| EndpointOutcomes::default()).collect();
     let mut handles = Vec::with_capacity(relays.len());
-    for relay in relays.iter() {
-        handles.push(Box::pin(send_submit_block(
-            &signed_blinded_block,
-            relay,
-            send_headers.clone(),
-            state.config.pbs_config.timeout_get_payload_ms,
-        )));
+    for (&relay, outcomes) in relays.iter().zip(&outcomes) {
+        // any of the relay urls may have the payload
+        for endpoint in relay.endpoints() {
+            handles.push(Box::pin(
+                send_submit_block(
+                    &signed_blinded_block,
+                    relay,
+                    endpoint,
+                    send_headers.clone(),
+                    state.config.pbs_config.timeout_get_payload_ms,
+                    outcomes,
+                )
+                .map_ok(|res| (relay.id.clone(), res)),
+            ));
+        }
+    }
+
+    // the requests still in flight are dropped once one succeeds
+    let results = select_ok(handles).await.map(|(res, _)| res);
+    for (relay, outcomes) in relays.iter().zip(&outcomes) {
+        outcomes.record(state.relay_health(), &relay.id);
     }
 
-    let results = select_ok(handles).await;
     match results {
-        Ok((res, _)) => Ok(res),
+        Ok((relay_id, res)) => {
+            if let Some(bid_archive) = &state.config.bid_archive {
+                bid_archive.record(BidArchiveRecord::PayloadDelivered {
+                    slot: signed_blinded_block.slot(),
+                    block_hash: res.block_hash(),
+                    relay_id: relay_id.to_string(),
+                });
+            }
+
+            Ok(res)
+        }
         Err(err) => Err(err.into()),
     }
 }
 
+/// Relays to send the block to. If enabled, these are only the relays that bid
+/// the block hash, so the signed block is not sent to relays that can't have
+/// the payload
+fn submit_block_relays<S: BuilderApiState>(
+    state: &PbsState<S>,
+    slot: u64,
+    block_hash: B256,
+) -> eyre::Result<Vec<&RelayClient>> {
+    let pbs_config = state.pbs_config();
+    if !pbs_config.submit_block_to_bidders_only {
+        return Ok(state.relays().iter().collect());
+    }
+
+    let bidders = state.get_relays_by_block_hash(slot, block_hash).map(|pubkeys| {
+        state.relays().iter().filter(|relay| pubkeys.contains(&relay.pubkey())).collect::<Vec<_>>()
+    });
+
+    match bidders {
+        Some(relays) if !relays.is_empty() => Ok(relays),
+        _ if pbs_config.submit_block_fallback_to_all => {
+            warn!(%block_hash, "no relay bid for block hash, sending to all relays");
+            Ok(state.relays().iter().collect())
+        }
+        _ => bail!("no relay bid for block hash {block_hash}"),
+    }
+}
+
 // submits blinded signed block and expects the execution payload + blobs bundle
 // back
-#[tracing::instrument(skip_all, name = "handler", fields(relay_id = relay.id.as_ref()))]
+#[tracing::instrument(
+    skip_all,
+    name = "handler",
+    fields(relay_id = relay.id.as_ref(), endpoint = endpoint.id.as_str())
+)]
 async fn send_submit_block(
     signed_blinded_block: &SignedBlindedBeaconBlock,
     relay: &RelayClient,
+    endpoint: &RelayEndpoint,
     headers: HeaderMap,
     timeout_ms: u64,
+    outcomes: &EndpointOutcomes,
 ) -> Result<SubmitBlindedBlockResponse, PbsError> {
-    let url = relay.submit_block_url();
+    let url = endpoint.submit_block_url();
+
+    let timeout = Duration::from_millis(timeout_ms);
+    let mut encoding = relay.encoding();
 
     let start_request = Instant::now();
-    let res = match relay
-        .client
-        .post(url)
-        .timeout(Duration::from_millis(timeout_ms))
-        .headers(headers)
-        .json(&signed_blinded_block)
-        .send()
-        .await
-    {
-        Ok(res) => res,
-        Err(err) => {
-            RELAY_STATUS_CODE
-                .with_label_values(&[
-                    TIMEOUT_ERROR_CODE_STR,
-                    SUBMIT_BLINDED_BLOCK_ENDPOINT_TAG,
-                    &relay.id,
-                ])
-                .inc();
-            return Err(err.into());
+    let res = loop {
+        let mut headers = headers.clone();
+        headers.insert(CONTENT_TYPE, encoding.header_value());
+        let body = match encoding {
+            EncodingType::Json => serde_json::to_vec(&signed_blinded_block)?,
+            EncodingType::Ssz => {
+                headers.insert(ACCEPT, HeaderValue::from_static(ACCEPT_SSZ_OR_JSON));
+                signed_blinded_block.as_ssz_bytes()
+            }
+        };
+        inject_trace_context(&mut headers);
+
+        let res = match relay
+            .client
+            .post(&url)
+            .timeout(timeout.saturating_sub(start_request.elapsed()))
+            .headers(headers)
+            .body(body)
+            .send()
+            .await
+        {
+            Ok(res) => res,
+            Err(err) => {
+                RELAY_STATUS_CODE
+                    .with_label_values(&[
+                        TIMEOUT_ERROR_CODE_STR,
+                        SUBMIT_BLINDED_BLOCK_ENDPOINT_TAG,
+                        &relay.id,
+                    ])
+                    .inc();
+                outcomes.add(RequestOutcome::from_error(&err));
+                return Err(err.into());
+            }
+        };
+
+        if relay.check_ssz_rejected(encoding, res.status()) {
+            encoding = EncodingType::Json;
+            continue;
         }
+
+        break res;
     };
     let request_latency = start_request.elapsed();
     RELAY_LATENCY
-        .with_label_values(&[SUBMIT_BLINDED_BLOCK_ENDPOINT_TAG, &relay.id])
+        .with_label_values(&[SUBMIT_BLINDED_BLOCK_ENDPOINT_TAG, &relay.id, &endpoint.id])
         .observe(request_latency.as_secs_f64());
 
     let code = res.status();
     RELAY_STATUS_CODE
         .with_label_values(&[code.as_str(), SUBMIT_BLINDED_BLOCK_ENDPOINT_TAG, &relay.id])
         .inc();
+    outcomes.add(RequestOutcome::from_response(code, request_latency));
+
+    let response_encoding = EncodingType::from_content_type(res.headers());
+    let response_version = get_consensus_version(res.headers()).ok().flatten();
 
     let response_bytes = res.bytes().await?;
     if !code.is_success() {
@@ -106,7 +205,14 @@ async fn send_submit_block(
         return Err(err);
     };
 
-    let block_response: SubmitBlindedBlockResponse = serde_json::from_slice(&response_bytes)?;
+    let block_response = match response_encoding {
+        EncodingType::Json => serde_json::from_slice(&response_bytes)?,
+        EncodingType::Ssz => {
+            let version = response_version.unwrap_or(signed_blinded_block.version());
+            SubmitBlindedBlockResponse::from_ssz_bytes(&response_bytes, version)
+                .map_err(|err| PbsError::SszDecodeError(format!("{err:?}")))?
+        }
+    };
 
     debug!(
         latency = ?request_latency,
@@ -121,31 +227,263 @@ async fn send_submit_block(
         }));
     }
 
-    if let Some(blobs) = &block_response.data.blobs_bundle {
-        let expected_committments = &signed_blinded_block.message.body.blob_kzg_commitments;
-        if expected_committments.len() != blobs.blobs.len() ||
-            expected_committments.len() != blobs.commitments.len() ||
-            expected_committments.len() != blobs.proofs.len()
-        {
-            return Err(PbsError::Validation(ValidationError::KzgCommitments {
-                expected_blobs: expected_committments.len(),
-                got_blobs: blobs.blobs.len(),
-                got_commitments: blobs.commitments.len(),
-                got_proofs: blobs.proofs.len(),
-            }));
+    if signed_blinded_block.version() != block_response.version() {
+        return Err(PbsError::Validation(ValidationError::VersionMismatch {
+            expected: signed_blinded_block.version(),
+            got: block_response.version(),
+        }));
+    }
+
+    match (signed_blinded_block, &block_response) {
+        (SignedBlindedBeaconBlock::Deneb(block), SubmitBlindedBlockResponse::Deneb(payload)) => {
+            validate_execution_payload(
+                &block.message.body.execution_payload_header,
+                &payload.execution_payload,
+            )?
         }
+        (
+            SignedBlindedBeaconBlock::Electra(block),
+            SubmitBlindedBlockResponse::Electra(payload),
+        ) => validate_execution_payload(
+            &block.message.body.execution_payload_header,
+            &payload.execution_payload,
+        )?,
+        // checked above
+        _ => unreachable!("version mismatch"),
+    }
+
+    let expected_committments = signed_blinded_block.blob_kzg_commitments();
+    match &block_response {
+        SubmitBlindedBlockResponse::Deneb(payload) => {
+            validate_blobs_commitments(expected_committments, payload.blobs_bundle.as_ref())?
+        }
+        SubmitBlindedBlockResponse::Electra(payload) => {
+            validate_blobs_commitments(expected_committments, payload.blobs_bundle.as_ref())?
+        }
+    }
+
+    verify_blobs_proofs(block_response, relay).await
+}
+
+/// Checks that the payload matches the header we signed, field by field, so a
+/// relay can't swap the payload while keeping the block hash
+fn validate_execution_payload<T: EthSpec>(
+    expected: &ExecutionPayloadHeader<T>,
+    payload: &ExecutionPayload<T>,
+) -> Result<(), ValidationError> {
+    let got = payload.to_header();
+
+    if expected.parent_hash != got.parent_hash {
+        return Err(ValidationError::PayloadParentHashMismatch {
+            expected: expected.parent_hash,
+            got: got.parent_hash,
+        });
+    }
+
+    if expected.fee_recipient != got.fee_recipient {
+        return Err(ValidationError::PayloadFeeRecipientMismatch {
+            expected: Address::from(expected.fee_recipient.0),
+            got: payload.fee_recipient,
+        });
+    }
+
+    if expected.state_root != got.state_root {
+        return Err(ValidationError::PayloadStateRootMismatch {
+            expected: expected.state_root,
+            got: got.state_root,
+        });
+    }
+
+    if expected.receipts_root != got.receipts_root {
+        return Err(ValidationError::PayloadReceiptsRootMismatch {
+            expected: expected.receipts_root,
+            got: got.receipts_root,
+        });
+    }
+
+    if expected.logs_bloom != got.logs_bloom {
+        return Err(ValidationError::PayloadLogsBloomMismatch);
+    }
 
-        for (i, comm) in expected_committments.iter().enumerate() {
-            // this is safe since we already know they are the same length
-            if *comm != blobs.commitments[i] {
-                return Err(PbsError::Validation(ValidationError::KzgMismatch {
-                    expected: format!("{comm}"),
-                    got: format!("{}", blobs.commitments[i]),
-                    index: i,
-                }));
+    if expected.prev_randao != got.prev_randao {
+        return Err(ValidationError::PayloadPrevRandaoMismatch {
+            expected: expected.prev_randao,
+            got: got.prev_randao,
+        });
+    }
+
+    if expected.block_number != got.block_number {
+        return Err(ValidationError::PayloadBlockNumberMismatch {
+            expected: expected.block_number,
+            got: got.block_number,
+        });
+    }
+
+    if expected.gas_limit != got.gas_limit {
+        return Err(ValidationError::PayloadGasLimitMismatch {
+            expected: expected.gas_limit,
+            got: got.gas_limit,
+        });
+    }
+
+    if expected.gas_used != got.gas_used {
+        return Err(ValidationError::PayloadGasUsedMismatch {
+            expected: expected.gas_used,
+            got: got.gas_used,
+        });
+    }
+
+    if expected.timestamp != got.timestamp {
+        return Err(ValidationError::PayloadTimestampMismatch {
+            expected: expected.timestamp,
+            got: got.timestamp,
+        });
+    }
+
+    if expected.extra_data != got.extra_data {
+        return Err(ValidationError::PayloadExtraDataMismatch {
+            expected: Bytes::copy_from_slice(&expected.extra_data),
+            got: Bytes::copy_from_slice(&got.extra_data),
+        });
+    }
+
+    if expected.base_fee_per_gas() != got.base_fee_per_gas() {
+        return Err(ValidationError::PayloadBaseFeeMismatch {
+            expected: expected.base_fee_per_gas(),
+            got: got.base_fee_per_gas(),
+        });
+    }
+
+    if expected.transactions_root != got.transactions_root {
+        return Err(ValidationError::PayloadTransactionsRootMismatch {
+            expected: expected.transactions_root,
+            got: got.transactions_root,
+        });
+    }
+
+    if expected.withdrawals_root != got.withdrawals_root {
+        return Err(ValidationError::PayloadWithdrawalsRootMismatch {
+            expected: expected.withdrawals_root,
+            got: got.withdrawals_root,
+        });
+    }
+
+    if expected.blob_gas_used != got.blob_gas_used {
+        return Err(ValidationError::PayloadBlobGasUsedMismatch {
+            expected: expected.blob_gas_used,
+            got: got.blob_gas_used,
+        });
+    }
+
+    if expected.excess_blob_gas != got.excess_blob_gas {
+        return Err(ValidationError::PayloadExcessBlobGasMismatch {
+            expected: expected.excess_blob_gas,
+            got: got.excess_blob_gas,
+        });
+    }
+
+    Ok(())
+}
+
+fn validate_blobs_commitments<T: EthSpec>(
+    expected_committments: &[KzgCommitment],
+    blobs_bundle: Option<&BlobsBundle<T>>,
+) -> Result<(), ValidationError> {
+    let Some(blobs) = blobs_bundle else {
+        return Ok(());
+    };
+
+    if expected_committments.len() != blobs.blobs.len() ||
+        expected_committments.len() != blobs.commitments.len() ||
+        expected_committments.len() != blobs.proofs.len()
+    {
+        return Err(ValidationError::KzgCommitments {
+            expected_blobs: expected_committments.len(),
+            got_blobs: blobs.blobs.len(),
+            got_commitments: blobs.commitments.len(),
+            got_proofs: blobs.proofs.len(),
+        });
+    }
+
+    for (i, comm) in expected_committments.iter().enumerate() {
+        // this is safe since we already know they are the same length
+        if *comm != blobs.commitments[i] {
+            return Err(ValidationError::KzgMismatch {
+                expected: format!("{comm}"),
+                got: format!("{}", blobs.commitments[i]),
+                index: i,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Batch verifies the KZG proofs of the blobs. This is CPU heavy so it runs on
+/// a blocking thread, not to stall the other relay requests
+async fn verify_blobs_proofs(
+    block_response: SubmitBlindedBlockResponse,
+    relay: &RelayClient,
+) -> Result<SubmitBlindedBlockResponse, PbsError> {
+    let relay_id = relay.id.clone();
+    let (block_response, verification) = tokio::task::spawn_blocking(move || {
+        let start = Instant::now();
+        let verification = match &block_response {
+            SubmitBlindedBlockResponse::Deneb(payload) => {
+                payload.blobs_bundle.as_ref().map(verify_blobs_bundle)
             }
+            SubmitBlindedBlockResponse::Electra(payload) => {
+                payload.blobs_bundle.as_ref().map(verify_blobs_bundle)
+            }
+        };
+
+        if verification.is_some() {
+            BLOBS_BUNDLE_VERIFICATION_LATENCY
+                .with_label_values(&[&relay_id])
+                .observe(start.elapsed().as_secs_f64());
         }
+
+        (block_response, verification)
+    })
+    .await
+    .map_err(|err| ValidationError::KzgProofs(format!("verification task failed: {err}")))?;
+
+    match verification {
+        Some(Err(err)) => Err(ValidationError::KzgProofs(err.to_string()).into()),
+        _ => Ok(block_response),
     }
+}
 
-    Ok(block_response)
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+    use cb_common::pbs::DenebSpec;
+
+    use super::*;
+
+    #[test]
+    fn test_validate_execution_payload() {
+        let payload = ExecutionPayload::<DenebSpec> {
+            block_number: 10,
+            base_fee_per_gas: U256::from(7),
+            ..Default::default()
+        };
+        let header = payload.to_header();
+        assert!(validate_execution_payload(&header, &payload).is_ok());
+
+        // same block hash, different content
+        let mut swapped = payload.clone();
+        swapped.timestamp = 1;
+        assert_eq!(
+            validate_execution_payload(&header, &swapped),
+            Err(ValidationError::PayloadTimestampMismatch { expected: 0, got: 1 })
+        );
+
+        let mut swapped = payload;
+        swapped.withdrawals.push(Default::default()).unwrap();
+        assert!(matches!(
+            validate_execution_payload(&header, &swapped),
+            Err(ValidationError::PayloadWithdrawalsRootMismatch { .. })
+        ));
+    }
 }
@@ -0,0 +1,174 @@
+//! Store of the latest registration of each validator, so that relays are only
+//! sent the ones that changed
+
+use std::sync::Arc;
+
+use alloy::rpc::types::beacon::{relay::ValidatorRegistration, BlsPublicKey};
+use cb_common::{signature::verify_validator_registration, types::Chain};
+use dashmap::{mapref::entry::Entry, DashMap};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use tracing::warn;
+
+/// Outcome of comparing a batch of registrations with the store
+#[derive(Debug, Default)]
+pub struct RegistrationUpdate {
+    /// First registration of a validator, or with a different fee recipient
+    /// or gas limit than the stored one
+    pub changed: Vec<ValidatorRegistration>,
+    /// Same fee recipient and gas limit as the stored registration, or older
+    /// than it
+    pub unchanged: Vec<ValidatorRegistration>,
+}
+
+/// Latest registration of each validator, cheap to clone and safe to share
+/// across threads
+#[derive(Debug, Clone, Default)]
+pub struct RegistrationStore {
+    registrations: Arc<DashMap<BlsPublicKey, ValidatorRegistration>>,
+}
+
+impl RegistrationStore {
+    /// Compares the registrations with the stored ones, without storing them
+    pub fn diff(&self, registrations: Vec<ValidatorRegistration>) -> RegistrationUpdate {
+        let mut update = RegistrationUpdate::default();
+
+        for registration in registrations {
+            let changed =
+                self.registrations.get(&registration.message.pubkey).map_or(true, |stored| {
+                    let stored = &stored.message;
+                    registration.message.timestamp >= stored.timestamp &&
+                        (stored.fee_recipient != registration.message.fee_recipient ||
+                            stored.gas_limit != registration.message.gas_limit)
+                });
+
+            if changed {
+                update.changed.push(registration);
+            } else {
+                update.unchanged.push(registration);
+            }
+        }
+
+        update
+    }
+
+    /// Stores the registrations, replacing older ones of the same validators.
+    /// Called once relays accepted them, so that a failed request is forwarded
+    /// again when the beacon node retries it
+    pub fn store(&self, registrations: &[ValidatorRegistration]) {
+        for registration in registrations {
+            match self.registrations.entry(registration.message.pubkey) {
+                Entry::Occupied(mut entry) => {
+                    // keep the latest signature even if nothing changed
+                    if registration.message.timestamp >= entry.get().message.timestamp {
+                        entry.insert(registration.clone());
+                    }
+                }
+
+                Entry::Vacant(entry) => {
+                    entry.insert(registration.clone());
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, pubkey: &BlsPublicKey) -> Option<ValidatorRegistration> {
+        self.registrations.get(pubkey).map(|registration| registration.clone())
+    }
+
+    pub fn all(&self) -> Vec<ValidatorRegistration> {
+        self.registrations.iter().map(|registration| registration.clone()).collect()
+    }
+}
+
+/// Drops the registrations with an invalid signature, and returns how many were
+/// dropped. Signatures are verified in parallel, this blocks so it should run
+/// on a blocking thread
+pub fn verify_registrations(
+    chain: Chain,
+    registrations: Vec<ValidatorRegistration>,
+) -> (Vec<ValidatorRegistration>, usize) {
+    let total = registrations.len();
+    let valid: Vec<_> = registrations
+        .into_par_iter()
+        .filter(|registration| match verify_validator_registration(chain, registration) {
+            Ok(()) => true,
+            Err(err) => {
+                let pubkey = registration.message.pubkey;
+                warn!(%pubkey, ?err, "invalid registration signature, dropping it");
+                false
+            }
+        })
+        .collect();
+
+    let invalid = total - valid.len();
+    (valid, invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::Address;
+
+    use super::*;
+
+    fn registrations() -> Vec<ValidatorRegistration> {
+        let data = include_str!("../../../tests/data/registration_holesky.json");
+        serde_json::from_str(data).unwrap()
+    }
+
+    #[test]
+    fn test_update() {
+        let store = RegistrationStore::default();
+        let registrations = registrations();
+
+        let (valid, invalid) = verify_registrations(Chain::Holesky, registrations.clone());
+        assert_eq!(valid.len(), registrations.len());
+        assert_eq!(invalid, 0);
+
+        let update = store.diff(registrations.clone());
+        assert_eq!(update.changed.len(), registrations.len());
+        assert!(update.unchanged.is_empty());
+
+        // nothing is stored until the registrations are accepted
+        let update = store.diff(registrations.clone());
+        assert_eq!(update.changed.len(), registrations.len());
+        store.store(&update.changed);
+
+        let update = store.diff(registrations.clone());
+        assert!(update.changed.is_empty());
+        assert_eq!(update.unchanged.len(), registrations.len());
+
+        // the test data can't be re-signed
+        let mut updated = registrations[0].clone();
+        updated.message.fee_recipient = Address::repeat_byte(1);
+        updated.message.timestamp += 1;
+        let update = store.diff(vec![updated.clone()]);
+        assert_eq!(update.changed.len(), 1);
+        store.store(&update.changed);
+        assert_eq!(
+            store.get(&updated.message.pubkey).unwrap().message.fee_recipient,
+            Address::repeat_byte(1)
+        );
+
+        // older registrations are never stored
+        let update = store.diff(vec![registrations[0].clone()]);
+        assert_eq!(update.unchanged.len(), 1);
+        store.store(&update.unchanged);
+        assert_eq!(
+            store.get(&updated.message.pubkey).unwrap().message.fee_recipient,
+            Address::repeat_byte(1)
+        );
+        assert_eq!(store.all().len(), registrations.len());
+    }
+
+    #[test]
+    fn test_invalid_signature() {
+        let mut registrations = registrations();
+        registrations[0].message.gas_limit += 1;
+        let invalid_pubkey = registrations[0].message.pubkey;
+
+        let (valid, invalid) = verify_registrations(Chain::Holesky, registrations.clone());
+        assert_eq!(invalid, 1);
+        assert_eq!(valid.len(), registrations.len() - 1);
+        assert!(valid.iter().all(|registration| registration.message.pubkey != invalid_pubkey));
+    }
+}
@@ -0,0 +1,389 @@
+//! Checks that relays are reachable and configured correctly, run once before
+//! the module starts serving requests
+
+use std::{
+    error::Error,
+    fmt,
+    net::{IpAddr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use alloy::{primitives::hex, rpc::types::beacon::BlsPublicKey};
+use cb_common::{
+    config::StartupCheckConfig,
+    pbs::{RelayClient, RelayEndpoint},
+};
+use futures::future::join_all;
+use tokio::{
+    net::{lookup_host, TcpSocket, TcpStream},
+    time::timeout,
+};
+use tracing::{info, warn};
+use url::{Host, Url};
+
+/// Outcome of a single check on a relay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckOutcome {
+    Passed,
+    Failed(String),
+    /// The check doesn't apply to the relay, or couldn't run because a
+    /// previous one failed
+    Skipped(&'static str),
+}
+
+impl CheckOutcome {
+    fn is_failed(&self) -> bool {
+        matches!(self, Self::Failed(_))
+    }
+}
+
+impl fmt::Display for CheckOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Passed => f.pad("ok"),
+            Self::Failed(_) => f.pad("FAIL"),
+            Self::Skipped(_) => f.pad("-"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RelayCheckReport {
+    pub relay_id: String,
+    /// The relay host resolves to at least one address
+    pub dns: CheckOutcome,
+    /// A TCP connection can be opened to the relay
+    pub connect: CheckOutcome,
+    /// The TLS handshake succeeds, for https relays
+    pub tls: CheckOutcome,
+    /// `get_status` returns a success status code
+    pub status: CheckOutcome,
+    /// The configured pubkey is shown on the relay landing page
+    pub pubkey: CheckOutcome,
+    /// Latency of the `get_status` call
+    pub latency: Option<Duration>,
+}
+
+impl RelayCheckReport {
+    fn new(relay_id: String) -> Self {
+        let not_run = CheckOutcome::Skipped("not run");
+        Self {
+            relay_id,
+            dns: not_run.clone(),
+            connect: not_run.clone(),
+            tls: not_run.clone(),
+            status: not_run.clone(),
+            pubkey: not_run,
+            latency: None,
+        }
+    }
+
+    fn checks(&self) -> [(&'static str, &CheckOutcome); 5] {
+        [
+            ("dns", &self.dns),
+            ("connect", &self.connect),
+            ("tls", &self.tls),
+            ("status", &self.status),
+            ("pubkey", &self.pubkey),
+        ]
+    }
+
+    /// Whether no check failed, skipped checks are ignored
+    pub fn passed(&self) -> bool {
+        !self.checks().iter().any(|(_, outcome)| outcome.is_failed())
+    }
+}
+
+/// Checks all the relays concurrently and logs a summary table
+pub async fn check_relays(
+    relays: &[RelayClient],
+    config: &StartupCheckConfig,
+) -> Vec<RelayCheckReport> {
+    info!(relays = relays.len(), "Checking relays");
+
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let reports = join_all(relays.iter().map(|relay| check_relay(relay, timeout))).await;
+
+    info!("Relay check results:\n{}", format_reports(&reports));
+    for report in reports.iter() {
+        for (check, outcome) in report.checks() {
+            if let CheckOutcome::Failed(reason) = outcome {
+                warn!(relay_id = report.relay_id.as_str(), check, reason, "relay check failed");
+            }
+        }
+    }
+
+    reports
+}
+
+/// Checks all the urls of the relay, which passes if any of them does. Returns
+/// the report of the first url that passed, or of the primary one
+async fn check_relay(relay: &RelayClient, timeout_dur: Duration) -> RelayCheckReport {
+    let mut reports = join_all(
+        relay.endpoints().iter().map(|endpoint| check_endpoint(relay, endpoint, timeout_dur)),
+    )
+    .await;
+
+    let passed = reports.iter().position(RelayCheckReport::passed);
+    if passed.is_some() {
+        for (endpoint, report) in relay.endpoints().iter().zip(&reports) {
+            if !report.passed() {
+                warn!(
+                    relay_id = relay.id.as_str(),
+                    endpoint = endpoint.id.as_str(),
+                    "relay url failed the check, using the other urls"
+                );
+            }
+        }
+    }
+
+    reports.swap_remove(passed.unwrap_or(0))
+}
+
+async fn check_endpoint(
+    relay: &RelayClient,
+    endpoint: &RelayEndpoint,
+    timeout_dur: Duration,
+) -> RelayCheckReport {
+    let mut report = RelayCheckReport::new(relay.id.to_string());
+
+    let url = match Url::parse(&endpoint.url) {
+        Ok(url) => url,
+        Err(err) => {
+            report.dns = CheckOutcome::Failed(format!("invalid url: {err}"));
+            return report;
+        }
+    };
+
+    // the relay may only be reachable through the proxy
+    if relay.config.proxy.is_some() {
+        report.dns = CheckOutcome::Skipped("proxy");
+        report.connect = CheckOutcome::Skipped("proxy");
+        return check_relay_api(relay, endpoint, url, timeout_dur, report).await;
+    }
+
+    let (Some(host), Some(port)) = (url.host(), url.port_or_known_default()) else {
+        report.dns = CheckOutcome::Failed("url has no host or port".to_string());
+        return report;
+    };
+
+    let addr = match host {
+        Host::Domain(domain) => match timeout(timeout_dur, lookup_host((domain, port))).await {
+            Ok(Ok(mut addrs)) => match addrs.next() {
+                Some(addr) => {
+                    report.dns = CheckOutcome::Passed;
+                    addr
+                }
+                None => {
+                    report.dns = CheckOutcome::Failed("no addresses found".to_string());
+                    return report;
+                }
+            },
+            Ok(Err(err)) => {
+                report.dns = CheckOutcome::Failed(err.to_string());
+                return report;
+            }
+            Err(_) => {
+                report.dns = CheckOutcome::Failed("timed out".to_string());
+                return report;
+            }
+        },
+        Host::Ipv4(ip) => {
+            report.dns = CheckOutcome::Skipped("ip address");
+            SocketAddr::from((ip, port))
+        }
+        Host::Ipv6(ip) => {
+            report.dns = CheckOutcome::Skipped("ip address");
+            SocketAddr::from((ip, port))
+        }
+    };
+
+    report.connect = match timeout(timeout_dur, connect(addr, relay.config.local_address)).await {
+        Ok(Ok(_)) => CheckOutcome::Passed,
+        Ok(Err(err)) => CheckOutcome::Failed(err.to_string()),
+        Err(_) => CheckOutcome::Failed("timed out".to_string()),
+    };
+    if report.connect.is_failed() {
+        return report;
+    }
+
+    check_relay_api(relay, endpoint, url, timeout_dur, report).await
+}
+
+/// Runs the checks that go through the relay client
+async fn check_relay_api(
+    relay: &RelayClient,
+    endpoint: &RelayEndpoint,
+    url: Url,
+    timeout_dur: Duration,
+    mut report: RelayCheckReport,
+) -> RelayCheckReport {
+    // a connect error after the TCP connection succeeded is a TLS failure,
+    // unless the connection goes through a proxy
+    let https = url.scheme() == "https";
+    let direct = relay.config.proxy.is_none();
+    let start_request = Instant::now();
+    match relay.client.get(endpoint.get_status_url()).timeout(timeout_dur).send().await {
+        Ok(res) => {
+            report.latency = Some(start_request.elapsed());
+            report.tls =
+                if https { CheckOutcome::Passed } else { CheckOutcome::Skipped("plain http") };
+            report.status = if res.status().is_success() {
+                CheckOutcome::Passed
+            } else {
+                CheckOutcome::Failed(format!("status code {}", res.status()))
+            };
+        }
+
+        Err(err) if https && direct && err.is_connect() => {
+            report.tls = CheckOutcome::Failed(error_chain(&err));
+            report.status = CheckOutcome::Skipped("tls failed");
+            return report;
+        }
+
+        Err(err) => {
+            report.tls = if https {
+                CheckOutcome::Skipped("no response")
+            } else {
+                CheckOutcome::Skipped("plain http")
+            };
+            report.status = CheckOutcome::Failed(error_chain(&err));
+        }
+    }
+
+    report.pubkey = check_advertised_pubkey(relay, url, timeout_dur).await;
+
+    report
+}
+
+/// Opens a TCP connection from `local_address` if set, as the relay client
+/// does
+async fn connect(addr: SocketAddr, local_address: Option<IpAddr>) -> std::io::Result<TcpStream> {
+    let Some(local_address) = local_address else {
+        return TcpStream::connect(addr).await;
+    };
+
+    let socket = if local_address.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+    socket.bind(SocketAddr::new(local_address, 0))?;
+    socket.connect(addr).await
+}
+
+/// Relays don't expose their pubkey in the builder API, but most show it on
+/// their landing page. Other pubkeys may be shown there too, e.g. of builders
+/// or validators, so the check is skipped rather than failed if the configured
+/// one is not found
+async fn check_advertised_pubkey(
+    relay: &RelayClient,
+    mut url: Url,
+    timeout_dur: Duration,
+) -> CheckOutcome {
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    url.set_path("/");
+    url.set_query(None);
+
+    let body = match relay.client.get(url).timeout(timeout_dur).send().await {
+        Ok(res) if res.status().is_success() => res.text().await.unwrap_or_default(),
+        _ => String::new(),
+    };
+
+    let pubkeys = find_pubkeys(&body);
+    if pubkeys.contains(&relay.pubkey()) {
+        CheckOutcome::Passed
+    } else {
+        if let Some(shown) = pubkeys.first() {
+            warn!(relay_id = relay.id.as_str(), %shown, "relay pubkey not shown on landing page");
+        }
+        CheckOutcome::Skipped("not exposed")
+    }
+}
+
+/// BLS pubkeys written as 0x-prefixed hex strings in a text
+fn find_pubkeys(text: &str) -> Vec<BlsPublicKey> {
+    const PUBKEY_HEX_LEN: usize = 96;
+
+    text.match_indices("0x")
+        .filter_map(|(start, _)| {
+            let rest = &text[start + 2..];
+            let len = rest.bytes().take_while(u8::is_ascii_hexdigit).count();
+            if len != PUBKEY_HEX_LEN {
+                return None;
+            }
+
+            hex::decode(&rest[..len]).ok().map(|bytes| BlsPublicKey::from_slice(&bytes))
+        })
+        .collect()
+}
+
+/// reqwest errors only show the outermost cause, which for connection errors
+/// hides the actual reason
+fn error_chain(err: &reqwest::Error) -> String {
+    let mut msg = err.to_string();
+    let mut source = err.source();
+    while let Some(err) = source {
+        msg.push_str(&format!(": {err}"));
+        source = err.source();
+    }
+    msg
+}
+
+fn format_reports(reports: &[RelayCheckReport]) -> String {
+    let id_width = reports.iter().map(|report| report.relay_id.len()).max().unwrap_or(0).max(5);
+
+    let mut table = format!(
+        "{:<id_width$}  {:<8} {:<8} {:<8} {:<8} {:<8} {:<8} result",
+        "relay", "dns", "connect", "tls", "status", "pubkey", "latency"
+    );
+    for report in reports {
+        let latency =
+            report.latency.map_or("-".to_string(), |latency| format!("{}ms", latency.as_millis()));
+        table.push_str(&format!(
+            "\n{:<id_width$}  {:<8} {:<8} {:<8} {:<8} {:<8} {:<8} {}",
+            report.relay_id,
+            report.dns,
+            report.connect,
+            report.tls,
+            report.status,
+            report.pubkey,
+            latency,
+            if report.passed() { "PASS" } else { "FAIL" }
+        ));
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_pubkeys() {
+        let pubkey = "0xac6e77dfe25ecd6110b8e780608cce0dab71fdd5ebea22a16c0205200f2f8e2e3ad3b71d3499c54ad14d6c21b41a37ae";
+        let text = format!("<p>Relay pubkey: <code>{pubkey}</code></p><p>0x1234</p>");
+
+        let pubkeys = find_pubkeys(&text);
+        assert_eq!(pubkeys.len(), 1);
+        assert_eq!(pubkeys[0].to_string(), pubkey);
+
+        // longer hex strings are not pubkeys
+        assert!(find_pubkeys(&format!("{pubkey}00")).is_empty());
+        assert!(find_pubkeys("no pubkey here").is_empty());
+    }
+
+    #[test]
+    fn test_report_passed() {
+        let mut report = RelayCheckReport::new("relay".to_string());
+        assert!(report.passed());
+
+        report.dns = CheckOutcome::Passed;
+        report.pubkey = CheckOutcome::Skipped("not exposed");
+        assert!(report.passed());
+
+        report.tls = CheckOutcome::Failed("invalid certificate".to_string());
+        assert!(!report.passed());
+
+        let table = format_reports(&[report]);
+        assert!(table.lines().nth(1).unwrap().ends_with("FAIL"));
+    }
+}
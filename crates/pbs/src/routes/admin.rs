@@ -0,0 +1,165 @@
+//! Admin API to inspect a running PBS module. It's served on a separate port
+//! from the builder API, so it can be kept private
+
+use std::{sync::Arc, time::Instant};
+
+use alloy::rpc::types::beacon::BlsPublicKey;
+use axum::{
+    extract::{Path, Request, State},
+    http::{header::AUTHORIZATION, StatusCode},
+    middleware::{self, Next},
+    response::Response,
+    routing::get,
+    Json, Router,
+};
+use cb_common::{
+    config::AdminApiConfig,
+    pbs::{
+        GetHeaderReponse, ADMIN_API_PATH, ADMIN_BIDS_PATH, ADMIN_COUNTERS_PATH, ADMIN_RELAYS_PATH,
+        ADMIN_SLOT_PATH,
+    },
+};
+use serde::Serialize;
+use subtle::ConstantTimeEq;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    health::{CircuitState, RelayCounters},
+    state::{BuilderApiState, PbsState},
+};
+
+pub fn create_admin_router<S: BuilderApiState>(
+    config: &AdminApiConfig,
+    state: PbsState<S>,
+) -> Router {
+    let mut admin_routes = Router::new()
+        .route(ADMIN_RELAYS_PATH, get(handle_get_relays::<S>))
+        .route(ADMIN_SLOT_PATH, get(handle_get_slot::<S>))
+        .route(ADMIN_BIDS_PATH, get(handle_get_bids::<S>))
+        .route(ADMIN_COUNTERS_PATH, get(handle_get_counters::<S>));
+
+    if let Some(token) = &config.auth_token {
+        admin_routes = admin_routes
+            .route_layer(middleware::from_fn_with_state(Arc::new(token.clone()), admin_auth));
+    }
+
+    Router::new().nest(ADMIN_API_PATH, admin_routes).with_state(state)
+}
+
+async fn admin_auth(
+    State(token): State<Arc<String>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let authorized = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|value| bool::from(value.as_bytes().ct_eq(token.as_bytes())));
+
+    if !authorized {
+        warn!(path = %req.uri().path(), "unauthorized admin request");
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    Ok(next.run(req).await)
+}
+
+#[derive(Debug, Serialize)]
+struct RelayInfo {
+    id: String,
+    url: String,
+    pubkey: BlsPublicKey,
+    /// One of "closed", "open" or "half_open"
+    circuit_state: &'static str,
+    /// Time left before a tripped relay is probed again
+    cooldown_left_ms: Option<u64>,
+    /// The following are computed over the health window
+    requests: usize,
+    success_rate: f64,
+    timeout_rate: f64,
+    avg_latency_ms: Option<u64>,
+}
+
+async fn handle_get_relays<S: BuilderApiState>(
+    State(state): State<PbsState<S>>,
+) -> Json<Vec<RelayInfo>> {
+    let state = state.with_latest_config();
+
+    let relays = state
+        .relays()
+        .iter()
+        .map(|relay| {
+            let stats = state.relay_health().stats(&relay.id);
+            let circuit_state = stats.map_or(CircuitState::Closed, |stats| stats.state);
+            let (circuit_state, cooldown_left_ms) = match circuit_state {
+                CircuitState::Closed => ("closed", None),
+                CircuitState::Open { until } => (
+                    "open",
+                    Some(until.saturating_duration_since(Instant::now()).as_millis() as u64),
+                ),
+                CircuitState::HalfOpen => ("half_open", None),
+            };
+
+            RelayInfo {
+                id: relay.id.to_string(),
+                url: relay.config.entry.url.clone(),
+                pubkey: relay.pubkey(),
+                circuit_state,
+                cooldown_left_ms,
+                requests: stats.map_or(0, |stats| stats.requests),
+                success_rate: stats.map_or(1.0, |stats| stats.success_rate),
+                timeout_rate: stats.map_or(0.0, |stats| stats.timeout_rate),
+                avg_latency_ms: stats
+                    .and_then(|stats| stats.avg_latency)
+                    .map(|latency| latency.as_millis() as u64),
+            }
+        })
+        .collect();
+
+    Json(relays)
+}
+
+#[derive(Debug, Serialize)]
+struct SlotInfo {
+    slot: u64,
+    uuid: Uuid,
+}
+
+async fn handle_get_slot<S: BuilderApiState>(State(state): State<PbsState<S>>) -> Json<SlotInfo> {
+    let (slot, uuid) = state.get_slot_and_uuid();
+    Json(SlotInfo { slot, uuid })
+}
+
+async fn handle_get_bids<S: BuilderApiState>(
+    State(state): State<PbsState<S>>,
+    Path(slot): Path<u64>,
+) -> Json<Vec<GetHeaderReponse>> {
+    Json(state.get_bids(slot))
+}
+
+#[derive(Debug, Serialize)]
+struct RelayCountersInfo {
+    id: String,
+    #[serde(flatten)]
+    counters: RelayCounters,
+}
+
+async fn handle_get_counters<S: BuilderApiState>(
+    State(state): State<PbsState<S>>,
+) -> Json<Vec<RelayCountersInfo>> {
+    let state = state.with_latest_config();
+
+    let counters = state
+        .relays()
+        .iter()
+        .map(|relay| RelayCountersInfo {
+            id: relay.id.to_string(),
+            counters: state.relay_health().counters(&relay.id).unwrap_or_default(),
+        })
+        .collect();
+
+    Json(counters)
+}
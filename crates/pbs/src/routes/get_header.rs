@@ -1,15 +1,15 @@
 use alloy::primitives::utils::format_ether;
 use axum::{
-    extract::{Path, State},
-    http::HeaderMap,
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue},
     response::IntoResponse,
 };
 use cb_common::{
-    pbs::{BuilderEvent, GetHeaderParams},
-    utils::{get_user_agent, ms_into_slot},
+    pbs::{BuilderEvent, EncodingType, GetHeaderParams, GetHeaderQuery, HEADER_CONSENSUS_VERSION},
+    utils::{get_user_agent, ms_into_slot, set_trace_parent},
 };
-use reqwest::StatusCode;
-use tracing::{error, info};
+use reqwest::{header::CONTENT_TYPE, StatusCode};
+use tracing::{error, info, Instrument, Span};
 use uuid::Uuid;
 
 use crate::{
@@ -20,21 +20,38 @@ use crate::{
     state::{BuilderApiState, PbsState},
 };
 
-#[tracing::instrument(skip_all, name = "get_header", fields(req_id = %Uuid::new_v4(), slot = params.slot))]
+#[tracing::instrument(skip_all, name = "get_header", fields(req_id = %Uuid::new_v4(), slot = params.slot, slot_uuid = tracing::field::Empty))]
 pub async fn handle_get_header<S: BuilderApiState, T: BuilderApi<S>>(
     State(state): State<PbsState<S>>,
     req_headers: HeaderMap,
-    Path(params): Path<GetHeaderParams>,
+    Path(mut params): Path<GetHeaderParams>,
+    Query(query): Query<GetHeaderQuery>,
 ) -> Result<impl IntoResponse, PbsClientError> {
+    let state = state.with_latest_config();
+    set_trace_parent(&Span::current(), &req_headers);
+
+    if state.is_shutting_down() {
+        info!("shutting down, not requesting headers");
+        BEACON_NODE_STATUS.with_label_values(&["204", GET_HEADER_ENDPOINT_TAG]).inc();
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+
+    params.builder_boost_factor = query.builder_boost_factor;
+    params.local_block_value = query.local_block_value;
+
     state.publish_event(BuilderEvent::GetHeaderRequest(params));
-    state.get_or_update_slot_uuid(params.slot);
+    let slot_uuid = state.get_or_update_slot_uuid(params.slot);
+    Span::current().record("slot_uuid", tracing::field::display(slot_uuid));
 
     let ua = get_user_agent(&req_headers);
+    let encoding = EncodingType::from_accept(&req_headers);
     let ms_into_slot = ms_into_slot(params.slot, state.config.chain);
 
     info!(ua, parent_hash=%params.parent_hash, validator_pubkey=%params.pubkey, ms_into_slot);
 
-    match T::get_header(params, req_headers, state.clone()).await {
+    // a redundant beacon node may request the same header at the same time
+    let request = T::get_header(params, req_headers, state.clone()).in_current_span();
+    match state.single_flight_get_header(&params, request).await {
         Ok(res) => {
             state.publish_event(BuilderEvent::GetHeaderResponse(Box::new(res.clone())));
 
@@ -42,7 +59,21 @@ pub async fn handle_get_header<S: BuilderApiState, T: BuilderApi<S>>(
                 info!(block_hash =% max_bid.block_hash(), value_eth = format_ether(max_bid.value()), "received header");
 
                 BEACON_NODE_STATUS.with_label_values(&["200", GET_HEADER_ENDPOINT_TAG]).inc();
-                Ok((StatusCode::OK, axum::Json(max_bid)).into_response())
+                let mut headers = HeaderMap::new();
+                headers.insert(
+                    HEADER_CONSENSUS_VERSION,
+                    HeaderValue::from_static(max_bid.version().as_str()),
+                );
+
+                match encoding {
+                    EncodingType::Json => {
+                        Ok((StatusCode::OK, headers, axum::Json(max_bid)).into_response())
+                    }
+                    EncodingType::Ssz => {
+                        headers.insert(CONTENT_TYPE, encoding.header_value());
+                        Ok((StatusCode::OK, headers, max_bid.as_ssz_bytes()).into_response())
+                    }
+                }
             } else {
                 // spec: return 204 if request is valid but no bid available
                 info!("no header available for slot");
@@ -1,9 +1,11 @@
+mod admin;
 mod get_header;
 mod register_validator;
 mod router;
 mod status;
 mod submit_block;
 
+pub use admin::create_admin_router;
 use get_header::handle_get_header;
 use register_validator::handle_register_validator;
 pub use router::create_app_router;
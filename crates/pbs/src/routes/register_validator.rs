@@ -19,6 +19,8 @@ pub async fn handle_register_validator<S: BuilderApiState, T: BuilderApi<S>>(
     req_headers: HeaderMap,
     Json(registrations): Json<Vec<ValidatorRegistration>>,
 ) -> Result<impl IntoResponse, PbsClientError> {
+    let state = state.with_latest_config();
+
     trace!(?registrations);
     state.publish_event(BuilderEvent::RegisterValidatorRequest(registrations.clone()));
 
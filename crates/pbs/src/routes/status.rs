@@ -17,6 +17,8 @@ pub async fn handle_get_status<S: BuilderApiState, T: BuilderApi<S>>(
     req_headers: HeaderMap,
     State(state): State<PbsState<S>>,
 ) -> Result<impl IntoResponse, PbsClientError> {
+    let state = state.with_latest_config();
+
     state.publish_event(BuilderEvent::GetStatusEvent);
 
     let ua = get_user_agent(&req_headers);
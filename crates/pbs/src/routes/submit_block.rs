@@ -1,50 +1,85 @@
-use axum::{extract::State, http::HeaderMap, response::IntoResponse, Json};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, HeaderValue},
+    response::IntoResponse,
+    Json,
+};
 use cb_common::{
-    pbs::{BuilderEvent, SignedBlindedBeaconBlock},
-    utils::{get_user_agent, timestamp_of_slot_start_millis, utcnow_ms},
+    pbs::{BuilderEvent, EncodingType, SignedBlindedBeaconBlock, HEADER_CONSENSUS_VERSION},
+    signature::{compute_beacon_proposer_domain, verify_proposer_signature},
+    utils::{
+        current_slot, get_consensus_version, get_user_agent, set_trace_parent,
+        timestamp_of_slot_start_millis, utcnow_ms,
+    },
 };
-use reqwest::StatusCode;
-use tracing::{error, info, trace, warn};
+use reqwest::{header::CONTENT_TYPE, StatusCode};
+use tracing::{error, info, trace, warn, Instrument, Span};
 use uuid::Uuid;
 
 use crate::{
     api::BuilderApi,
     constants::SUBMIT_BLINDED_BLOCK_ENDPOINT_TAG,
-    error::PbsClientError,
+    error::{PbsClientError, ValidationError},
     metrics::BEACON_NODE_STATUS,
     state::{BuilderApiState, PbsState},
 };
 
-#[tracing::instrument(skip_all, name = "submit_blinded_block", fields(req_id = %Uuid::new_v4(), slot = signed_blinded_block.message.slot))]
+#[tracing::instrument(skip_all, name = "submit_blinded_block", fields(req_id = %Uuid::new_v4(), slot = tracing::field::Empty, slot_uuid = tracing::field::Empty))]
 pub async fn handle_submit_block<S: BuilderApiState, T: BuilderApi<S>>(
     State(state): State<PbsState<S>>,
     req_headers: HeaderMap,
-    Json(signed_blinded_block): Json<SignedBlindedBeaconBlock>,
+    body: Bytes,
 ) -> Result<impl IntoResponse, PbsClientError> {
+    let state = state.with_latest_config();
+    set_trace_parent(&Span::current(), &req_headers);
+
+    let signed_blinded_block = decode_signed_blinded_block(&req_headers, &body, &state)?;
+    Span::current().record("slot", signed_blinded_block.slot());
+
     trace!(?signed_blinded_block);
     state.publish_event(BuilderEvent::SubmitBlockRequest(Box::new(signed_blinded_block.clone())));
 
     let now = utcnow_ms();
-    let slot = signed_blinded_block.message.slot;
-    let block_hash = signed_blinded_block.message.body.execution_payload_header.block_hash;
+    let slot = signed_blinded_block.slot();
+    let block_hash = signed_blinded_block.block_hash();
     let slot_start_ms = timestamp_of_slot_start_millis(slot, state.config.chain);
     let ua = get_user_agent(&req_headers);
-    let (curr_slot, slot_uuid) = state.get_slot_and_uuid();
+    let encoding = EncodingType::from_accept(&req_headers);
+    let (_, slot_uuid) = state.get_slot_and_uuid();
+    Span::current().record("slot_uuid", tracing::field::display(slot_uuid));
 
     info!(ua, %slot_uuid, ms_into_slot=now.saturating_sub(slot_start_ms), %block_hash);
 
-    if curr_slot != signed_blinded_block.message.slot {
-        warn!(expected = curr_slot, got = slot, "blinded beacon slot mismatch")
+    if let Err(err) = validate_signed_blinded_block(&signed_blinded_block, &state).await {
+        error!(%err, "invalid blinded block");
+        let err = PbsClientError::InvalidBlock(err);
+        BEACON_NODE_STATUS
+            .with_label_values(&[err.status_code().as_str(), SUBMIT_BLINDED_BLOCK_ENDPOINT_TAG])
+            .inc();
+        return Err(err);
     }
 
-    match T::submit_block(signed_blinded_block, req_headers, state.clone()).await {
+    let request =
+        T::submit_block(signed_blinded_block, req_headers, state.clone()).in_current_span();
+    match state.single_flight_submit_block(block_hash, request).await {
         Ok(res) => {
             trace!(?res);
             state.publish_event(BuilderEvent::SubmitBlockResponse(Box::new(res.clone())));
             info!("received unblinded block");
 
             BEACON_NODE_STATUS.with_label_values(&["200", SUBMIT_BLINDED_BLOCK_ENDPOINT_TAG]).inc();
-            Ok((StatusCode::OK, Json(res).into_response()))
+            let mut headers = HeaderMap::new();
+            headers
+                .insert(HEADER_CONSENSUS_VERSION, HeaderValue::from_static(res.version().as_str()));
+
+            match encoding {
+                EncodingType::Json => Ok((StatusCode::OK, headers, Json(res)).into_response()),
+                EncodingType::Ssz => {
+                    headers.insert(CONTENT_TYPE, encoding.header_value());
+                    Ok((StatusCode::OK, headers, res.as_ssz_bytes()).into_response())
+                }
+            }
         }
 
         Err(err) => {
@@ -78,3 +113,84 @@ pub async fn handle_submit_block<S: BuilderApiState, T: BuilderApi<S>>(
         }
     }
 }
+
+/// Decodes the block according to the `Content-Type` header, using the fork
+/// from the `Eth-Consensus-Version` header if present. The fork must match the
+/// one scheduled at the block slot
+fn decode_signed_blinded_block<S: BuilderApiState>(
+    req_headers: &HeaderMap,
+    body: &[u8],
+    state: &PbsState<S>,
+) -> Result<SignedBlindedBeaconBlock, PbsClientError> {
+    let header_version = get_consensus_version(req_headers)
+        .map_err(|err| PbsClientError::DecodeError(err.to_string()))?;
+
+    let signed_blinded_block = match EncodingType::from_content_type(req_headers) {
+        EncodingType::Json => SignedBlindedBeaconBlock::from_json(body, header_version)
+            .map_err(|err| PbsClientError::DecodeError(err.to_string()))?,
+        EncodingType::Ssz => SignedBlindedBeaconBlock::from_ssz_bytes(body, header_version)
+            .map_err(|err| PbsClientError::DecodeError(format!("{err:?}")))?,
+    };
+
+    let expected_version = state.config.chain.fork_at_slot(signed_blinded_block.slot());
+    if signed_blinded_block.version() != expected_version {
+        warn!(
+            expected = %expected_version,
+            got = %signed_blinded_block.version(),
+            "blinded block version doesn't match fork at slot"
+        );
+        return Err(PbsClientError::DecodeError(format!(
+            "expected {expected_version} block for slot {}, got {}",
+            signed_blinded_block.slot(),
+            signed_blinded_block.version()
+        )));
+    }
+
+    Ok(signed_blinded_block)
+}
+
+/// Sanity checks on the block before it's sent to relays. The proposer
+/// signature is verified against the pubkey of the proposer index from the
+/// beacon node, so it's only checked if a beacon node is configured
+async fn validate_signed_blinded_block<S: BuilderApiState>(
+    signed_blinded_block: &SignedBlindedBeaconBlock,
+    state: &PbsState<S>,
+) -> Result<(), ValidationError> {
+    let slot = signed_blinded_block.slot();
+
+    if !state.pbs_config().skip_slot_checks {
+        let current = current_slot(state.config.chain);
+        if slot < current {
+            return Err(ValidationError::PastSlot { slot, current });
+        }
+
+        // the slot is 0 if no get_header was received since startup
+        let (curr_slot, _) = state.get_slot_and_uuid();
+        if curr_slot != 0 && curr_slot != slot {
+            return Err(ValidationError::SlotMismatch { expected: curr_slot, got: slot });
+        }
+    }
+
+    if !state.pbs_config().skip_proposer_sigverify {
+        let Some(domain) = compute_beacon_proposer_domain(state.config.chain, slot) else {
+            warn!("unknown proposer domain for chain, skipping proposer signature verification");
+            return Ok(());
+        };
+
+        let Some(beacon_client) = state.config.beacon_client.as_ref() else {
+            return Ok(());
+        };
+
+        // cached by the beacon client, and prefetched from the payload
+        // attributes events if following them
+        let proposer_index = signed_blinded_block.proposer_index();
+        let pubkey = beacon_client.get_validator_pubkey(proposer_index).await.map_err(|err| {
+            ValidationError::UnknownProposer { proposer_index, reason: err.to_string() }
+        })?;
+
+        verify_proposer_signature(domain, &pubkey, signed_blinded_block)
+            .map_err(ValidationError::ProposerSigverify)?;
+    }
+
+    Ok(())
+}
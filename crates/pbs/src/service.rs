@@ -1,15 +1,29 @@
-use std::net::SocketAddr;
+use std::{future::Future, net::SocketAddr, time::Duration};
 
+use cb_common::{
+    config::reload_pbs_config,
+    utils::{serve_with_shutdown, wait_for_shutdown_signal},
+    SHUTDOWN_DRAIN_TIMEOUT,
+};
 use cb_metrics::provider::MetricsProvider;
-use eyre::{Context, Result};
+use eyre::{ensure, Context, Result};
+use futures::FutureExt;
 use prometheus::core::Collector;
-use tokio::net::TcpListener;
-use tracing::info;
+use tokio::{
+    net::TcpListener,
+    signal::unix::{signal, SignalKind},
+    time::sleep,
+};
+use tracing::{error, info, warn};
 
 use crate::{
     api::BuilderApi,
+    beacon_events::follow_beacon_events,
+    constants::SHUTDOWN_SUBMIT_WINDOW_MS,
     metrics::PBS_METRICS_REGISTRY,
-    routes::create_app_router,
+    mev_boost::{probe_tripped_relays, rebroadcast_registrations},
+    relay_check::check_relays,
+    routes::{create_admin_router, create_app_router},
     state::{BuilderApiState, PbsState},
 };
 
@@ -19,20 +33,128 @@ pub struct PbsService;
 
 impl PbsService {
     pub async fn run<S: BuilderApiState, T: BuilderApi<S>>(state: PbsState<S>) -> Result<()> {
-        // if state.pbs_config().relay_check {
-        //     PbsService::relay_check(state.relays()).await;
-        // }
+        Self::run_with_shutdown::<S, T, _>(state, wait_for_shutdown_signal()).await
+    }
+
+    /// Runs the service until `shutdown` resolves, instead of until SIGTERM or
+    /// SIGINT is received
+    pub async fn run_with_shutdown<S, T, F>(state: PbsState<S>, shutdown: F) -> Result<()>
+    where
+        S: BuilderApiState,
+        T: BuilderApi<S>,
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let shutdown = shutdown.boxed().shared();
+
+        state.pbs_config().validate(state.relays())?;
+
+        let startup_check = &state.pbs_config().startup_check;
+        if startup_check.enabled {
+            let reports = check_relays(state.relays(), startup_check).await;
+            let passed = reports.iter().filter(|report| report.passed()).count();
+            ensure!(
+                passed >= startup_check.min_passing_relays,
+                "only {passed} of {} relays passed the startup check, {} required",
+                reports.len(),
+                startup_check.min_passing_relays
+            );
+        }
+
+        if !state.pbs_config().skip_proposer_sigverify && state.config.beacon_client.is_none() {
+            warn!("no beacon node configured, proposer signatures won't be verified");
+        }
 
         let address = SocketAddr::from(([0, 0, 0, 0], state.config.pbs_config.port));
+        let tls =
+            state.config.pbs_config.tls.as_ref().map(|tls| tls.server_config()).transpose()?;
         let events_subs =
             state.config.event_publiher.as_ref().map(|e| e.n_subscribers()).unwrap_or_default();
+
+        tokio::spawn(probe_tripped_relays(state.clone()));
+        tokio::spawn(rebroadcast_registrations(state.clone()));
+        tokio::spawn(Self::reload_on_sighup(state.clone()));
+
+        if state.pbs_config().beacon_events {
+            if let Some(beacon_client) = state.config.beacon_client.clone() {
+                tokio::spawn(follow_beacon_events::<S, T>(beacon_client, state.clone()));
+            }
+        }
+
+        if let Some(admin_config) = state.config.pbs_config.admin_api.clone() {
+            let admin_address = admin_config.listen_address();
+            let admin_app = create_admin_router(&admin_config, state.clone());
+            let admin_listener =
+                TcpListener::bind(admin_address).await.wrap_err("failed admin tcp binding")?;
+
+            info!(?admin_address, auth = admin_config.auth_token.is_some(), "Starting admin API");
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                if let Err(err) = serve_with_shutdown(
+                    admin_listener,
+                    admin_app,
+                    shutdown,
+                    SHUTDOWN_DRAIN_TIMEOUT,
+                    None,
+                )
+                .await
+                {
+                    error!(?err, "Admin API exited");
+                }
+            });
+        }
+
+        // after the signal new get_header calls return 204, but the listener is
+        // kept open for a while so headers already handed out can be submitted.
+        // Then submit_block calls in flight are given time to finish
+        let shutdown = {
+            let state = state.clone();
+            async move {
+                shutdown.await;
+                state.set_shutting_down();
+                sleep(Duration::from_millis(SHUTDOWN_SUBMIT_WINDOW_MS)).await;
+                info!("Closing PBS listener");
+            }
+        };
+        let drain_timeout =
+            SHUTDOWN_DRAIN_TIMEOUT.saturating_sub(Duration::from_millis(SHUTDOWN_SUBMIT_WINDOW_MS));
         let app = create_app_router::<S, T>(state);
 
-        info!(?address, events_subs, "Starting PBS service");
+        info!(?address, events_subs, tls = tls.is_some(), "Starting PBS service");
 
         let listener = TcpListener::bind(address).await.expect("failed tcp binding");
 
-        axum::serve(listener, app).await.wrap_err("PBS server exited")
+        serve_with_shutdown(listener, app, shutdown, drain_timeout, tls)
+            .await
+            .wrap_err("PBS server exited")?;
+
+        info!("PBS service stopped");
+        Ok(())
+    }
+
+    /// Reloads relays and pbs config from the config file on SIGHUP. Requests
+    /// already in flight finish on the old config
+    async fn reload_on_sighup<S: BuilderApiState>(state: PbsState<S>) {
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => hangup,
+            Err(err) => {
+                error!(?err, "failed to listen for SIGHUP, config reload disabled");
+                return;
+            }
+        };
+
+        while hangup.recv().await.is_some() {
+            info!("Received SIGHUP, reloading config");
+
+            let current = state.with_latest_config();
+            match reload_pbs_config(&current.config) {
+                Ok(config) => {
+                    let relays = config.relays.len();
+                    state.reload_config(config);
+                    info!(relays, "Config reloaded");
+                }
+                Err(err) => error!(?err, "Invalid config, keeping the current one"),
+            }
+        }
     }
 
     pub fn register_metric(c: Box<dyn Collector>) {
@@ -42,32 +164,4 @@ impl PbsService {
     pub fn init_metrics() -> Result<()> {
         MetricsProvider::load_and_run(PBS_METRICS_REGISTRY.clone())
     }
-
-    // TODO: before starting, send a sanity check to relay
-    // pub async fn relay_check(relays: &[RelayEntry]) {
-    //     info!("Sending initial relay checks");
-
-    //     let mut handles = Vec::with_capacity(relays.len());
-
-    //     for relay in relays {
-    //         handles.push(Box::pin(send_relay_check(relay.clone())))
-    //     }
-
-    //     let results = join_all(handles).await;
-
-    //     if !results.iter().any(|r| r.is_ok()) {
-    //         error!("No relay passed check successfully");
-    //         return;
-    //     }
-
-    //     for (i, res) in results.into_iter().enumerate() {
-    //         let relay_id = relays[i].id.as_str();
-
-    //         if let Err(err) = res {
-    //             error!(?err, "Failed to get status from {relay_id}");
-    //         } else {
-    //             info!(relay_id, "Initial check successful")
-    //         }
-    //     }
-    // }
 }
@@ -0,0 +1,141 @@
+//! Coalesces identical concurrent requests, e.g. from redundant beacon nodes,
+//! so that only the first one reaches the relays
+
+use std::{future::Future, hash::Hash, sync::Arc};
+
+use dashmap::{mapref::entry::Entry, DashMap};
+use futures::{
+    future::{BoxFuture, Shared},
+    FutureExt,
+};
+use tracing::debug;
+
+/// Requests in flight by key. A call with the same key as one in flight waits
+/// for its result instead of running
+pub struct SingleFlight<K, V> {
+    in_flight: Arc<DashMap<K, Shared<BoxFuture<'static, V>>>>,
+}
+
+impl<K, V> Clone for SingleFlight<K, V> {
+    fn clone(&self) -> Self {
+        Self { in_flight: self.in_flight.clone() }
+    }
+}
+
+impl<K, V> Default for SingleFlight<K, V>
+where
+    K: Eq + Hash,
+{
+    fn default() -> Self {
+        Self { in_flight: Arc::new(DashMap::new()) }
+    }
+}
+
+impl<K, V> SingleFlight<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone + Send + Sync + 'static,
+{
+    /// Runs `request`, or waits for the result of the call in flight for the
+    /// same key. The request keeps running if the first caller goes away while
+    /// others are waiting
+    pub async fn run<F>(&self, key: K, request: F) -> V
+    where
+        F: Future<Output = V> + Send + 'static,
+    {
+        let (shared, _guard) = match self.in_flight.entry(key.clone()) {
+            Entry::Occupied(entry) => {
+                debug!("identical request in flight, waiting for its result");
+                (entry.get().clone(), None)
+            }
+            Entry::Vacant(entry) => {
+                let shared = request.boxed().shared();
+                entry.insert(shared.clone());
+                (shared, Some(RemoveOnDrop { in_flight: &*self.in_flight, key }))
+            }
+        };
+
+        shared.await
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+}
+
+/// Removes the entry once the first caller is done with it, including when
+/// it's cancelled, so later calls run again
+struct RemoveOnDrop<'a, K: Eq + Hash, V> {
+    in_flight: &'a DashMap<K, V>,
+    key: K,
+}
+
+impl<K: Eq + Hash, V> Drop for RemoveOnDrop<'_, K, V> {
+    fn drop(&mut self) {
+        self.in_flight.remove(&self.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_single_flight() {
+        let flights = SingleFlight::<u64, usize>::default();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let request = |calls: Arc<AtomicUsize>| async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            calls.fetch_add(1, Ordering::Relaxed) + 1
+        };
+
+        let (a, b, c) = tokio::join!(
+            flights.run(1, request(calls.clone())),
+            flights.run(1, request(calls.clone())),
+            flights.run(2, request(calls.clone())),
+        );
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+        assert_eq!(flights.in_flight(), 0);
+
+        // finished requests are not cached
+        flights.run(1, request(calls.clone())).await;
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn test_first_caller_cancelled() {
+        let flights = SingleFlight::<u64, u64>::default();
+
+        let first = tokio::spawn({
+            let flights = flights.clone();
+            async move {
+                flights
+                    .run(1, async {
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        1
+                    })
+                    .await
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let second = tokio::spawn({
+            let flights = flights.clone();
+            async move { flights.run(1, async { 2 }).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        first.abort();
+
+        // the second caller still gets the result of the first request
+        assert_eq!(second.await.unwrap(), 1);
+        assert_eq!(flights.in_flight(), 0);
+    }
+}
@@ -1,41 +1,114 @@
 use std::{
     collections::HashSet,
-    sync::{Arc, Mutex},
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
 };
 
-use alloy::{primitives::B256, rpc::types::beacon::BlsPublicKey};
+use alloy::{
+    primitives::{B256, U256},
+    rpc::types::beacon::BlsPublicKey,
+};
 use cb_common::{
-    config::{PbsConfig, PbsModuleConfig},
-    pbs::{BuilderEvent, GetHeaderReponse, RelayClient},
+    beacon::HeadEvent,
+    config::{PbsConfig, PbsModuleConfig, RuntimeMuxConfig},
+    pbs::{
+        BuilderEvent, GetHeaderParams, GetHeaderReponse, RelayClient, SubmitBlindedBlockResponse,
+    },
 };
 use dashmap::DashMap;
+use futures::TryFutureExt;
 use uuid::Uuid;
 
+use crate::{
+    bids::{BidPipeline, RelayBid},
+    health::RelayHealth,
+    latency::RelayLatencies,
+    registrations::RegistrationStore,
+    single_flight::SingleFlight,
+};
+
+/// Result of a request that may be shared by several callers
+pub type SharedResult<T> = Result<T, Arc<eyre::Report>>;
+
+/// Slot, parent hash, validator pubkey, builder boost factor and local block
+/// value of a get_header request
+type GetHeaderKey = (u64, B256, BlsPublicKey, Option<u64>, Option<U256>);
+
 pub trait BuilderApiState: Clone + Sync + Send + 'static {}
 impl BuilderApiState for () {}
 
+/// Config that can be swapped at runtime, together with the relay health
+/// tracker using its thresholds and the bid pipeline built from it
+struct ReloadableConfig<U> {
+    config: PbsModuleConfig<U>,
+    relay_health: RelayHealth,
+    bid_pipeline: BidPipeline,
+}
+
 /// State for the Pbs module. It can be extended in two ways:
 /// - By adding extra configs to be loaded at startup
 /// - By adding extra data to the state
 #[derive(Clone)]
 pub struct PbsState<U, S: BuilderApiState = ()> {
-    /// Config data for the Pbs service
+    /// Config data for the Pbs service, this is a snapshot which is not updated
+    /// if the config is reloaded, see `with_latest_config`
     pub config: PbsModuleConfig<U>,
     /// Opaque extra data for library use
     pub data: S,
     /// Info about the latest slot and its uuid
     current_slot_info: Arc<Mutex<(u64, Uuid)>>,
+    /// Latest head event of the beacon node, if following its events
+    head: Arc<RwLock<Option<HeadEvent>>>,
     /// Keeps track of which relays delivered which block for which slot
-    bid_cache: Arc<DashMap<u64, Vec<GetHeaderReponse>>>,
+    bid_cache: Arc<DashMap<u64, Vec<RelayBid>>>,
+    /// Filters and scorers run after the built-in ones from the config
+    custom_bid_pipeline: BidPipeline,
+    /// Built-in filters and scorers from `config`, followed by the custom ones
+    bid_pipeline: BidPipeline,
+    /// Latest registration of each validator
+    registrations: RegistrationStore,
+    /// Health scores and circuit breaker state of each relay
+    relay_health: RelayHealth,
+    /// Recent get_header latencies of each relay
+    relay_latencies: RelayLatencies,
+    /// get_header requests in flight by all their params
+    in_flight_headers: SingleFlight<GetHeaderKey, SharedResult<Option<GetHeaderReponse>>>,
+    /// submit_block requests in flight by block hash
+    in_flight_blocks: SingleFlight<B256, SharedResult<SubmitBlindedBlockResponse>>,
+    /// Latest config, swapped when the config is reloaded
+    latest_config: Arc<RwLock<ReloadableConfig<U>>>,
+    /// Set once a shutdown signal is received
+    shutting_down: Arc<AtomicBool>,
 }
 
-impl<U> PbsState<U, ()> {
+impl<U: Clone> PbsState<U, ()> {
     pub fn new(config: PbsModuleConfig<U>) -> Self {
+        let relay_health = RelayHealth::new(config.pbs_config.relay_health.clone());
+        let bid_pipeline = BidPipeline::from_config(&config.pbs_config.bid_selection);
+        let latest_config = ReloadableConfig {
+            config: config.clone(),
+            relay_health: relay_health.clone(),
+            bid_pipeline: bid_pipeline.clone(),
+        };
+
         Self {
             config,
             data: (),
             current_slot_info: Arc::new(Mutex::new((0, Uuid::new_v4()))),
+            head: Arc::new(RwLock::new(None)),
             bid_cache: Arc::new(DashMap::new()),
+            custom_bid_pipeline: BidPipeline::default(),
+            bid_pipeline,
+            registrations: RegistrationStore::default(),
+            relay_health,
+            relay_latencies: RelayLatencies::default(),
+            in_flight_headers: SingleFlight::default(),
+            in_flight_blocks: SingleFlight::default(),
+            latest_config: Arc::new(RwLock::new(latest_config)),
+            shutting_down: Arc::new(AtomicBool::new(false)),
         }
     }
 
@@ -44,7 +117,17 @@ impl<U> PbsState<U, ()> {
             data,
             config: self.config,
             current_slot_info: self.current_slot_info,
+            head: self.head,
             bid_cache: self.bid_cache,
+            custom_bid_pipeline: self.custom_bid_pipeline,
+            bid_pipeline: self.bid_pipeline,
+            registrations: self.registrations,
+            relay_health: self.relay_health,
+            relay_latencies: self.relay_latencies,
+            in_flight_headers: self.in_flight_headers,
+            in_flight_blocks: self.in_flight_blocks,
+            latest_config: self.latest_config,
+            shutting_down: self.shutting_down,
         }
     }
 }
@@ -53,6 +136,56 @@ impl<U, S> PbsState<U, S>
 where
     S: BuilderApiState,
 {
+    /// Adds filters and scorers to choose the bid returned from get_header,
+    /// run after the built-in ones enabled in the config
+    pub fn with_bid_pipeline(mut self, pipeline: BidPipeline) -> Self {
+        self.custom_bid_pipeline = self.custom_bid_pipeline.extend(&pipeline);
+        self.bid_pipeline = self.bid_pipeline.extend(&pipeline);
+
+        let mut latest = self.latest_config.write().expect("poisoned");
+        latest.bid_pipeline = latest.bid_pipeline.clone().extend(&pipeline);
+        drop(latest);
+
+        self
+    }
+
+    /// Returns a copy of the state using the latest config. Requests should
+    /// call this once when they start, so that they finish on the same config
+    /// even if it's reloaded in the meantime
+    pub fn with_latest_config(&self) -> Self
+    where
+        U: Clone,
+    {
+        let latest = self.latest_config.read().expect("poisoned");
+        Self {
+            config: latest.config.clone(),
+            data: self.data.clone(),
+            current_slot_info: self.current_slot_info.clone(),
+            head: self.head.clone(),
+            bid_cache: self.bid_cache.clone(),
+            custom_bid_pipeline: self.custom_bid_pipeline.clone(),
+            bid_pipeline: latest.bid_pipeline.clone(),
+            registrations: self.registrations.clone(),
+            relay_health: latest.relay_health.clone(),
+            relay_latencies: self.relay_latencies.clone(),
+            in_flight_headers: self.in_flight_headers.clone(),
+            in_flight_blocks: self.in_flight_blocks.clone(),
+            latest_config: self.latest_config.clone(),
+            shutting_down: self.shutting_down.clone(),
+        }
+    }
+
+    /// Swaps the config used by new requests. Relay scores are kept, relays
+    /// are tracked by id
+    pub fn reload_config(&self, config: PbsModuleConfig<U>) {
+        let mut latest = self.latest_config.write().expect("poisoned");
+        latest.relay_health =
+            latest.relay_health.with_config(config.pbs_config.relay_health.clone());
+        latest.bid_pipeline = BidPipeline::from_config(&config.pbs_config.bid_selection)
+            .extend(&self.custom_bid_pipeline);
+        latest.config = config;
+    }
+
     pub fn publish_event(&self, e: BuilderEvent) {
         if let Some(publisher) = self.config.event_publiher.as_ref() {
             publisher.publish(e);
@@ -75,6 +208,25 @@ where
         *guard
     }
 
+    /// Latest head of the chain, only set if following the beacon node events
+    pub fn head(&self) -> Option<HeadEvent> {
+        self.head.read().expect("poisoned").clone()
+    }
+
+    pub(crate) fn set_head(&self, head: HeadEvent) {
+        *self.head.write().expect("poisoned") = Some(head);
+    }
+
+    /// Whether the module is draining requests before exiting, new headers
+    /// shouldn't be requested
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_shutting_down(&self) {
+        self.shutting_down.store(true, Ordering::Relaxed);
+    }
+
     // Getters
     pub fn pbs_config(&self) -> &PbsConfig {
         &self.config.pbs_config
@@ -82,13 +234,63 @@ where
     pub fn relays(&self) -> &[RelayClient] {
         &self.config.relays
     }
+    pub fn relay_health(&self) -> &RelayHealth {
+        &self.relay_health
+    }
+    pub fn relay_latencies(&self) -> &RelayLatencies {
+        &self.relay_latencies
+    }
+    pub fn registrations(&self) -> &RegistrationStore {
+        &self.registrations
+    }
+
+    /// Built-in filters and scorers from the config, followed by the ones added
+    /// with `with_bid_pipeline`
+    pub fn bid_pipeline(&self) -> &BidPipeline {
+        &self.bid_pipeline
+    }
+
+    /// Mux config of a validator, if it doesn't use the global relays
+    pub fn mux(&self, pubkey: &BlsPublicKey) -> Option<&RuntimeMuxConfig> {
+        self.config.muxes.as_ref().and_then(|muxes| muxes.get(pubkey)).map(|mux| mux.as_ref())
+    }
+
+    /// Relays to use for a validator, from its mux if it has one
+    pub fn validator_relays(&self, pubkey: &BlsPublicKey) -> &[RelayClient] {
+        self.mux(pubkey).map_or(self.relays(), |mux| &mux.relays)
+    }
+
+    /// Filters out relays that are tripped by the circuit breaker
+    pub fn healthy_relays<'a>(&self, relays: &'a [RelayClient]) -> Vec<&'a RelayClient> {
+        relays.iter().filter(|relay| self.relay_health.is_available(&relay.id)).collect()
+    }
+
+    /// Add some bids for the request to the cache, dropping the ones rejected
+    /// by the bid pipeline. Returns the cached bid with the highest score among
+    /// the ones for the same slot, parent hash and validator, since redundant
+    /// beacon nodes may request headers on different parents
+    pub fn add_bids(
+        &self,
+        params: &GetHeaderParams,
+        bids: Vec<RelayBid>,
+    ) -> Option<GetHeaderReponse> {
+        let pipeline = self.bid_pipeline();
+        let bids = pipeline.filter(params, bids);
 
-    /// Add some bids to the cache, the bids are all assumed to be for the
-    /// provided slot Returns the bid with the max value
-    pub fn add_bids(&self, slot: u64, bids: Vec<GetHeaderReponse>) -> Option<GetHeaderReponse> {
-        let mut slot_entry = self.bid_cache.entry(slot).or_default();
+        let mut slot_entry = self.bid_cache.entry(params.slot).or_default();
         slot_entry.extend(bids);
-        slot_entry.iter().max_by_key(|bid| bid.value()).cloned()
+        let request_bids = slot_entry.iter().filter(|bid| {
+            bid.validator_pubkey == params.pubkey && bid.bid.parent_hash() == params.parent_hash
+        });
+        pipeline.best(params, request_bids).map(|best| best.bid.clone())
+    }
+
+    /// Bids received for a slot, empty if the slot is not in the cache anymore
+    pub fn get_bids(&self, slot: u64) -> Vec<GetHeaderReponse> {
+        self.bid_cache
+            .get(&slot)
+            .map(|bids| bids.iter().map(|bid| bid.bid.clone()).collect())
+            .unwrap_or_default()
     }
 
     /// Retrieves a list of relays pubkeys that delivered a given block hash
@@ -101,16 +303,51 @@ where
         self.bid_cache.get(&slot).and_then(|bids| {
             let filtered: HashSet<_> = bids
                 .iter()
-                .filter(|&bid| (bid.block_hash() == block_hash))
-                .map(|bid| bid.pubkey())
+                .filter(|&bid| (bid.bid.block_hash() == block_hash))
+                .map(|bid| bid.bid.pubkey())
                 .collect();
 
             (!filtered.is_empty()).then_some(filtered)
         })
     }
 
+    /// Runs a get_header request, or waits for the result of an identical one
+    /// in flight, e.g. from another beacon node. Requests are identical if
+    /// all their params are, including the boost factor and local block value
+    /// that decide whether the bid is returned
+    pub async fn single_flight_get_header<F>(
+        &self,
+        params: &GetHeaderParams,
+        request: F,
+    ) -> SharedResult<Option<GetHeaderReponse>>
+    where
+        F: Future<Output = eyre::Result<Option<GetHeaderReponse>>> + Send + 'static,
+    {
+        let key = (
+            params.slot,
+            params.parent_hash,
+            params.pubkey,
+            params.builder_boost_factor,
+            params.local_block_value,
+        );
+        self.in_flight_headers.run(key, request.map_err(Arc::new)).await
+    }
+
+    /// Runs a submit_block request, or waits for the result of one in flight
+    /// for the same block hash
+    pub async fn single_flight_submit_block<F>(
+        &self,
+        block_hash: B256,
+        request: F,
+    ) -> SharedResult<SubmitBlindedBlockResponse>
+    where
+        F: Future<Output = eyre::Result<SubmitBlindedBlockResponse>> + Send + 'static,
+    {
+        self.in_flight_blocks.run(block_hash, request.map_err(Arc::new)).await
+    }
+
     /// Clear bids which are more than ~3 minutes old
     fn clear(&self, last_slot: u64) {
-        self.bid_cache.retain(|slot, _| last_slot.saturating_sub(*slot) < 15)
+        self.bid_cache.retain(|slot, _| last_slot.saturating_sub(*slot) < 15);
     }
 }
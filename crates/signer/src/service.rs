@@ -18,11 +18,13 @@ use cb_common::{
     },
     config::StartSignerConfig,
     types::{Jwt, ModuleId},
+    utils::{serve_with_shutdown, set_trace_parent, wait_for_shutdown_signal},
+    SHUTDOWN_DRAIN_TIMEOUT,
 };
 use eyre::{Result, WrapErr};
 use headers::{authorization::Bearer, Authorization};
 use tokio::{net::TcpListener, sync::RwLock};
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info, info_span, warn, Instrument};
 use uuid::Uuid;
 
 use crate::{error::SignerModuleError, manager::SigningManager};
@@ -65,10 +67,16 @@ impl SigningService {
 
         let address = SocketAddr::from(([0, 0, 0, 0], config.server_port));
         let listener = TcpListener::bind(address).await.wrap_err("failed tcp binding")?;
+        let tls = config.tls.as_ref().map(|tls| tls.server_config()).transpose()?;
 
-        if let Err(err) = axum::serve(listener, app).await {
+        let shutdown = wait_for_shutdown_signal();
+        if let Err(err) =
+            serve_with_shutdown(listener, app, shutdown, SHUTDOWN_DRAIN_TIMEOUT, tls).await
+        {
             error!(?err, "Signing server exited")
         }
+
+        info!("Signing service stopped");
         Ok(())
     }
 }
@@ -89,7 +97,10 @@ async fn jwt_auth(
 
     req.extensions_mut().insert(module_id.clone());
 
-    Ok(next.run(req).await)
+    let span = info_span!("signer_request", %module_id, path = req.uri().path());
+    set_trace_parent(&span, req.headers());
+
+    Ok(next.run(req).instrument(span).await)
 }
 
 /// Implements get_pubkeys from the Signer API
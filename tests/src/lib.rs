@@ -1,3 +1,4 @@
+pub mod mock_beacon;
 pub mod mock_relay;
 pub mod mock_validator;
 pub mod utils;
@@ -0,0 +1,39 @@
+use axum::{http::header::CONTENT_TYPE, routing::get, Router};
+use serde_json::json;
+
+/// Beacon node stub that streams a head event for `head_slot` and a payload
+/// attributes event for the next slot, then closes the stream
+pub fn mock_beacon_events_router(head_slot: u64) -> Router {
+    let root = format!("0x{}", "11".repeat(32));
+    let head = json!({
+        "slot": head_slot.to_string(),
+        "block": root,
+        "state": root,
+        "epoch_transition": false,
+        "execution_optimistic": false,
+    });
+    let payload_attributes = json!({
+        "version": "deneb",
+        "data": {
+            "proposer_index": "1",
+            "proposal_slot": (head_slot + 1).to_string(),
+            "parent_block_number": "1",
+            "parent_block_root": root,
+            "parent_block_hash": root,
+            "payload_attributes": {
+                "timestamp": "0",
+                "prev_randao": root,
+                "suggested_fee_recipient": format!("0x{}", "00".repeat(20)),
+                "withdrawals": [],
+            },
+        },
+    });
+    let body = format!(
+        "event: head\ndata: {head}\n\nevent: payload_attributes\ndata: {payload_attributes}\n\n"
+    );
+
+    Router::new().route(
+        "/eth/v1/events",
+        get(move || async move { ([(CONTENT_TYPE, "text/event-stream")], body) }),
+    )
+}
@@ -1,20 +1,25 @@
-use std::sync::{
-    atomic::{AtomicU64, Ordering},
-    Arc,
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
 use alloy::{primitives::U256, rpc::types::beacon::relay::ValidatorRegistration};
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
+    http::{header::CONTENT_TYPE, HeaderMap, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
 use cb_common::{
     pbs::{
-        GetHeaderParams, GetHeaderReponse, SubmitBlindedBlockResponse, BULDER_API_PATH,
-        GET_HEADER_PATH, GET_STATUS_PATH, REGISTER_VALIDATOR_PATH, SUBMIT_BLOCK_PATH,
+        EncodingType, GetHeaderParams, GetHeaderReponse, SignedExecutionPayloadHeaderDeneb,
+        SignedExecutionPayloadHeaderElectra, SubmitBlindedBlockResponse, Version, BULDER_API_PATH,
+        CONTENT_TYPE_SSZ, GET_HEADER_PATH, GET_STATUS_PATH, HEADER_CONSENSUS_VERSION,
+        REGISTER_VALIDATOR_PATH, SUBMIT_BLOCK_PATH,
     },
     signer::Signer,
     types::Chain,
@@ -26,6 +31,11 @@ pub struct MockRelayState {
     pub chain: Chain,
     pub get_header_delay_ms: u64,
     pub signer: Signer,
+    /// Whether to accept and return SSZ, otherwise SSZ requests are rejected
+    pub supports_ssz: bool,
+    /// Number of register_validator requests to fail with a 503 before
+    /// accepting them
+    register_validator_failures: Arc<AtomicU64>,
     received_get_header: Arc<AtomicU64>,
     received_get_status: Arc<AtomicU64>,
     received_register_validator: Arc<AtomicU64>,
@@ -53,6 +63,8 @@ impl MockRelayState {
             chain,
             signer,
             get_header_delay_ms,
+            supports_ssz: false,
+            register_validator_failures: Default::default(),
             received_get_header: Default::default(),
             received_get_status: Default::default(),
             received_register_validator: Default::default(),
@@ -61,6 +73,18 @@ impl MockRelayState {
     }
 }
 
+impl MockRelayState {
+    pub fn with_ssz(mut self) -> Self {
+        self.supports_ssz = true;
+        self
+    }
+
+    pub fn with_register_validator_failures(self, failures: u64) -> Self {
+        self.register_validator_failures.store(failures, Ordering::Relaxed);
+        self
+    }
+}
+
 pub fn mock_relay_app_router(state: Arc<MockRelayState>) -> Router {
     let builder_routes = Router::new()
         .route(GET_HEADER_PATH, get(handle_get_header))
@@ -74,19 +98,47 @@ pub fn mock_relay_app_router(state: Arc<MockRelayState>) -> Router {
 
 async fn handle_get_header(
     State(state): State<Arc<MockRelayState>>,
-    Path(GetHeaderParams { parent_hash, .. }): Path<GetHeaderParams>,
+    headers: HeaderMap,
+    Path(GetHeaderParams { slot, parent_hash, .. }): Path<GetHeaderParams>,
 ) -> Response {
     state.received_get_header.fetch_add(1, Ordering::Relaxed);
 
-    let mut response = GetHeaderReponse::default();
-    response.data.message.header.parent_hash = parent_hash;
-    response.data.message.header.block_hash.0[0] = 1;
-    response.data.message.set_value(U256::from(10));
-    response.data.message.pubkey = state.signer.pubkey();
-    response.data.signature =
-        state.signer.sign(state.chain, &response.data.message.tree_hash_root().0).await;
+    if state.get_header_delay_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(state.get_header_delay_ms)).await;
+    }
+
+    let response = match state.chain.fork_at_slot(slot) {
+        Version::Deneb => {
+            let mut bid = SignedExecutionPayloadHeaderDeneb::default();
+            bid.message.header.parent_hash = parent_hash;
+            bid.message.header.block_hash.0[0] = 1;
+            bid.message.set_value(U256::from(10));
+            bid.message.pubkey = state.signer.pubkey();
+            bid.signature = state.signer.sign(state.chain, &bid.message.tree_hash_root().0).await;
+            GetHeaderReponse::Deneb(bid)
+        }
+        Version::Electra => {
+            let mut bid = SignedExecutionPayloadHeaderElectra::default();
+            bid.message.header.parent_hash = parent_hash;
+            bid.message.header.block_hash.0[0] = 1;
+            bid.message.set_value(U256::from(10));
+            bid.message.pubkey = state.signer.pubkey();
+            bid.signature = state.signer.sign(state.chain, &bid.message.tree_hash_root().0).await;
+            GetHeaderReponse::Electra(bid)
+        }
+    };
 
-    (StatusCode::OK, axum::Json(response)).into_response()
+    if state.supports_ssz && EncodingType::from_accept(&headers) == EncodingType::Ssz {
+        let mut res_headers = HeaderMap::new();
+        res_headers.insert(
+            HEADER_CONSENSUS_VERSION,
+            HeaderValue::from_static(response.version().as_str()),
+        );
+        res_headers.insert(CONTENT_TYPE, HeaderValue::from_static(CONTENT_TYPE_SSZ));
+        (StatusCode::OK, res_headers, response.as_ssz_bytes()).into_response()
+    } else {
+        (StatusCode::OK, axum::Json(response)).into_response()
+    }
 }
 
 async fn handle_get_status(State(state): State<Arc<MockRelayState>>) -> impl IntoResponse {
@@ -100,11 +152,35 @@ async fn handle_register_validator(
 ) -> impl IntoResponse {
     state.received_register_validator.fetch_add(1, Ordering::Relaxed);
     debug!("Received {} registrations", validators.len());
+
+    let failures = &state.register_validator_failures;
+    if failures.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1)).is_ok() {
+        return StatusCode::SERVICE_UNAVAILABLE;
+    }
+
     StatusCode::OK
 }
 
-async fn handle_submit_block(State(state): State<Arc<MockRelayState>>) -> impl IntoResponse {
+async fn handle_submit_block(
+    State(state): State<Arc<MockRelayState>>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
     state.received_submit_block.fetch_add(1, Ordering::Relaxed);
+
+    if !state.supports_ssz && EncodingType::from_content_type(&headers) == EncodingType::Ssz {
+        return StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response();
+    }
+
     let response = SubmitBlindedBlockResponse::default();
-    (StatusCode::OK, Json(response)).into_response()
+    if state.supports_ssz && EncodingType::from_accept(&headers) == EncodingType::Ssz {
+        let mut res_headers = HeaderMap::new();
+        res_headers.insert(
+            HEADER_CONSENSUS_VERSION,
+            HeaderValue::from_static(response.version().as_str()),
+        );
+        res_headers.insert(CONTENT_TYPE, HeaderValue::from_static(CONTENT_TYPE_SSZ));
+        (StatusCode::OK, res_headers, response.as_ssz_bytes()).into_response()
+    } else {
+        (StatusCode::OK, Json(response)).into_response()
+    }
 }
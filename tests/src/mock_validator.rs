@@ -2,8 +2,17 @@ use alloy::{
     primitives::B256,
     rpc::types::beacon::{relay::ValidatorRegistration, BlsPublicKey},
 };
-use cb_common::pbs::{GetHeaderReponse, RelayClient, SignedBlindedBeaconBlock};
-use reqwest::Error;
+use cb_common::{
+    pbs::{
+        EncodingType, GetHeaderQuery, GetHeaderReponse, RelayClient, SignedBlindedBeaconBlock,
+        SubmitBlindedBlockResponse, Version, CONTENT_TYPE_SSZ,
+    },
+    utils::get_consensus_version,
+};
+use reqwest::{
+    header::{ACCEPT, CONTENT_TYPE},
+    Error, StatusCode,
+};
 
 use crate::utils::generate_mock_relay;
 
@@ -24,6 +33,49 @@ impl MockValidator {
         Ok(())
     }
 
+    /// Sends get_header on top of `parent_hash` and returns the bid
+    pub async fn do_get_header_for_parent(
+        &self,
+        parent_hash: B256,
+    ) -> eyre::Result<GetHeaderReponse> {
+        let url = self.comm_boost.get_header_url(0, parent_hash, BlsPublicKey::ZERO);
+        let res = self.comm_boost.client.get(url).send().await?.bytes().await?;
+
+        Ok(serde_json::from_slice(&res)?)
+    }
+
+    /// Sends get_header with query params, the response may be empty if the
+    /// local block is preferred
+    pub async fn do_get_header_with_query(&self, query: GetHeaderQuery) -> Result<(), Error> {
+        let url = self.comm_boost.get_header_url(0, B256::ZERO, BlsPublicKey::ZERO);
+        self.comm_boost.client.get(url).query(&query).send().await?.error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Sends get_header and returns the status code, without checking the
+    /// response
+    pub async fn do_get_header_status(&self) -> Result<StatusCode, Error> {
+        let url = self.comm_boost.get_header_url(0, B256::ZERO, BlsPublicKey::ZERO);
+        let res = self.comm_boost.client.get(url).send().await?;
+
+        Ok(res.status())
+    }
+
+    pub async fn do_get_header_ssz(&self) -> Result<(), Error> {
+        let url = self.comm_boost.get_header_url(0, B256::ZERO, BlsPublicKey::ZERO);
+        let res = self.comm_boost.client.get(url).header(ACCEPT, CONTENT_TYPE_SSZ).send().await?;
+
+        assert_eq!(EncodingType::from_content_type(res.headers()), EncodingType::Ssz);
+        let version = get_consensus_version(res.headers()).unwrap().unwrap();
+        assert_eq!(version, Version::Deneb);
+
+        let res = res.bytes().await?;
+        assert!(GetHeaderReponse::from_ssz_bytes(&res, version).is_ok());
+
+        Ok(())
+    }
+
     pub async fn do_get_status(&self) -> Result<(), Error> {
         let url = self.comm_boost.get_status_url();
         let _res = self.comm_boost.client.get(url).send().await?;
@@ -33,9 +85,14 @@ impl MockValidator {
     }
 
     pub async fn do_register_validator(&self) -> Result<(), Error> {
-        let url = self.comm_boost.register_validator_url();
+        self.do_register_custom_validators(vec![]).await
+    }
 
-        let registration: Vec<ValidatorRegistration> = vec![];
+    pub async fn do_register_custom_validators(
+        &self,
+        registration: Vec<ValidatorRegistration>,
+    ) -> Result<(), Error> {
+        let url = self.comm_boost.register_validator_url();
 
         self.comm_boost
             .client
@@ -65,4 +122,28 @@ impl MockValidator {
 
         Ok(())
     }
+
+    pub async fn do_submit_block_ssz(&self) -> Result<(), Error> {
+        let url = self.comm_boost.submit_block_url();
+
+        let signed_blinded_block = SignedBlindedBeaconBlock::default();
+
+        let res = self
+            .comm_boost
+            .client
+            .post(url)
+            .header(CONTENT_TYPE, CONTENT_TYPE_SSZ)
+            .header(ACCEPT, CONTENT_TYPE_SSZ)
+            .body(signed_blinded_block.as_ssz_bytes())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        assert_eq!(EncodingType::from_content_type(res.headers()), EncodingType::Ssz);
+
+        let res = res.bytes().await?;
+        assert!(SubmitBlindedBlockResponse::from_ssz_bytes(&res, Version::Deneb).is_ok());
+
+        Ok(())
+    }
 }
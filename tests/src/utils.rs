@@ -23,3 +23,10 @@ pub fn generate_mock_relay(port: u16, pubkey: BlsPublicKey) -> Result<RelayClien
     let config = RelayConfig { entry, ..RelayConfig::default() };
     RelayClient::new(config)
 }
+
+/// Mock relay client which requests SSZ encoded payloads
+pub fn generate_mock_relay_ssz(port: u16, pubkey: BlsPublicKey) -> Result<RelayClient> {
+    let entry = RelayEntry { id: format!("mock_{port}"), pubkey, url: get_local_address(port) };
+    let config = RelayConfig { entry, enable_ssz: true, ..RelayConfig::default() };
+    RelayClient::new(config)
+}
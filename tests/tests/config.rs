@@ -7,6 +7,11 @@ async fn test_load_config() -> Result<()> {
 
     assert_eq!(config.chain, Chain::Holesky);
     assert!(config.relays[0].headers.is_some());
+    assert_eq!(config.pbs.pbs_config.builder_boost_factors.len(), 1);
+    assert_eq!(config.muxes.as_ref().unwrap()[0].relays, vec!["example-relay".to_string()]);
+    assert_eq!(config.pbs.pbs_config.bid_archive.as_ref().unwrap().max_files, Some(30));
+    assert_eq!(config.pbs.pbs_config.admin_api.as_ref().unwrap().port, 18551);
+    assert_eq!(config.pbs.pbs_config.registrations.rebroadcast_interval_secs, 3600);
     // TODO: add more
     Ok(())
 }
@@ -1,6 +1,6 @@
 use std::fs;
 
-use alloy::rpc::types::beacon::relay::ValidatorRegistration;
+use alloy::{primitives::b256, rpc::types::beacon::relay::ValidatorRegistration};
 use cb_common::pbs::{SignedBlindedBeaconBlock, SubmitBlindedBlockResponse};
 #[test]
 fn test_registrations() {
@@ -22,3 +22,22 @@ fn test_submit_block_response() {
     let parsed = serde_json::from_slice::<SubmitBlindedBlockResponse>(&file);
     assert!(parsed.is_ok());
 }
+
+#[test]
+fn test_payload_to_header() {
+    let file = fs::read("data/submit_block_response_holesky.json").unwrap();
+    let SubmitBlindedBlockResponse::Deneb(parsed) = serde_json::from_slice(&file).unwrap() else {
+        panic!("expected deneb payload");
+    };
+    let header = parsed.execution_payload.to_header();
+
+    assert_eq!(header.block_hash, parsed.execution_payload.block_hash);
+    assert_eq!(
+        header.transactions_root,
+        b256!("0cc3f86dcff23e56808f6a9884ca907e90638c3bab82d190b6d511091e95e659")
+    );
+    assert_eq!(
+        header.withdrawals_root,
+        b256!("742c20d07a323073d8c805cf5525d54c0dde5c4af44833569c0167f404fa673a")
+    );
+}
@@ -1,20 +1,37 @@
-use std::{net::SocketAddr, sync::Arc, time::Duration, u64};
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+    u64,
+};
 
-use alloy::primitives::U256;
+use alloy::{
+    primitives::{B256, U256},
+    rpc::types::beacon::{relay::ValidatorRegistration, BlsPublicKey},
+};
 use cb_common::{
-    config::{PbsConfig, PbsModuleConfig},
-    pbs::RelayClient,
+    beacon::BeaconClient,
+    config::{
+        load_muxes, AdminApiConfig, MuxConfig, PbsConfig, PbsModuleConfig, RelayConfig,
+        StartupCheckConfig,
+    },
+    pbs::{
+        GetHeaderParams, GetHeaderQuery, GetHeaderReponse, RelayClient, RelayEntry,
+        SignedExecutionPayloadHeaderDeneb,
+    },
     signer::Signer,
     types::Chain,
 };
-use cb_pbs::{DefaultBuilderApi, PbsService, PbsState};
+use cb_pbs::{DefaultBuilderApi, PbsService, PbsState, RelayBid};
 use cb_tests::{
+    mock_beacon::mock_beacon_events_router,
     mock_relay::{mock_relay_app_router, MockRelayState},
     mock_validator::MockValidator,
-    utils::{generate_mock_relay, setup_test_env},
+    utils::{generate_mock_relay, generate_mock_relay_ssz, get_local_address, setup_test_env},
 };
 use eyre::Result;
-use tokio::net::TcpListener;
+use reqwest::StatusCode;
+use tokio::{net::TcpListener, sync::oneshot};
 use tracing::info;
 
 async fn start_mock_relay_service(state: Arc<MockRelayState>, port: u16) -> Result<()> {
@@ -34,10 +51,31 @@ fn get_pbs_static_config(port: u16) -> PbsConfig {
         relay_check: true,
         timeout_get_header_ms: u64::MAX,
         timeout_get_payload_ms: u64::MAX,
+        submit_block_to_bidders_only: false,
+        submit_block_fallback_to_all: true,
         timeout_register_validator_ms: u64::MAX,
         skip_sigverify: false,
+        // the mock validator submits an unsigned block for slot 0
+        skip_proposer_sigverify: true,
+        skip_slot_checks: true,
+        validate_fee_recipient: false,
+        validate_gas_limit: false,
+        validate_timestamp: false,
+        beacon_node_url: None,
+        beacon_events: true,
         min_bid_wei: U256::ZERO,
         late_in_slot_time_ms: u64::MAX,
+        min_premium_wei: U256::ZERO,
+        builder_boost_factors: Default::default(),
+        relay_health: Default::default(),
+        // mock relays are started at the same time as the module
+        startup_check: StartupCheckConfig { enabled: false, ..Default::default() },
+        adaptive_timing_games: Default::default(),
+        bid_selection: Default::default(),
+        registrations: Default::default(),
+        bid_archive: None,
+        admin_api: None,
+        tls: None,
     }
 }
 
@@ -53,6 +91,9 @@ fn to_pbs_config(
         event_publiher: None,
         extra: (),
         relays,
+        muxes: None,
+        bid_archive: None,
+        beacon_client: None,
     }
 }
 
@@ -171,3 +212,741 @@ async fn test_submit_block() -> Result<()> {
     assert_eq!(mock_state.received_submit_block(), 1);
     Ok(())
 }
+
+#[tokio::test]
+async fn test_get_header_ssz() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 3500;
+
+    let mock_relay = generate_mock_relay_ssz(port + 1, signer.pubkey())?;
+    let mock_state = Arc::new(MockRelayState::new(chain, signer, 0).with_ssz());
+    tokio::spawn(start_mock_relay_service(mock_state.clone(), port + 1));
+
+    let config = to_pbs_config(chain, get_pbs_static_config(port), vec![mock_relay]);
+    let state = PbsState::new(config);
+    tokio::spawn(PbsService::run::<(), DefaultBuilderApi>(state));
+
+    // leave some time to start servers
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mock_validator = MockValidator::new(port)?;
+    info!("Sending get header with SSZ");
+    let res = mock_validator.do_get_header_ssz().await;
+
+    assert!(res.is_ok());
+    assert_eq!(mock_state.received_get_header(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_submit_block_ssz_fallback() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 3600;
+
+    // relay is configured for SSZ but rejects it
+    let relays = vec![generate_mock_relay_ssz(port + 1, signer.pubkey())?];
+    let mock_state = Arc::new(MockRelayState::new(chain, signer, 0));
+    tokio::spawn(start_mock_relay_service(mock_state.clone(), port + 1));
+
+    let config = to_pbs_config(chain, get_pbs_static_config(port), relays);
+    let state = PbsState::new(config);
+    tokio::spawn(PbsService::run::<(), DefaultBuilderApi>(state));
+
+    // leave some time to start servers
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mock_validator = MockValidator::new(port)?;
+    info!("Sending submit block with SSZ");
+    let res = mock_validator.do_submit_block_ssz().await;
+
+    assert!(res.is_ok());
+    // first request is rejected, then retried with JSON
+    assert_eq!(mock_state.received_submit_block(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_header_mux() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 3700;
+
+    let relays = vec![
+        generate_mock_relay(port + 1, signer.pubkey())?,
+        generate_mock_relay(port + 2, signer.pubkey())?,
+    ];
+    let mux_state = Arc::new(MockRelayState::new(chain, signer.clone(), 0));
+    let other_state = Arc::new(MockRelayState::new(chain, signer, 0));
+    tokio::spawn(start_mock_relay_service(mux_state.clone(), port + 1));
+    tokio::spawn(start_mock_relay_service(other_state.clone(), port + 2));
+
+    // the mock validator requests headers for the zero pubkey
+    let mux = MuxConfig {
+        id: "test-mux".to_string(),
+        validator_pubkeys: vec![BlsPublicKey::ZERO],
+        validators_file: None,
+        relays: vec![relays[0].id.to_string()],
+        timeout_get_header_ms: None,
+        timeout_register_validator_ms: None,
+        min_bid_eth: None,
+    };
+    let muxes = load_muxes(vec![mux], &relays)?;
+
+    let mut config = to_pbs_config(chain, get_pbs_static_config(port), relays);
+    config.muxes = Some(Arc::new(muxes));
+    let state = PbsState::new(config);
+    tokio::spawn(PbsService::run::<(), DefaultBuilderApi>(state));
+
+    // leave some time to start servers
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mock_validator = MockValidator::new(port)?;
+    info!("Sending get header for mux validator");
+    let res = mock_validator.do_get_header().await;
+
+    assert!(res.is_ok());
+    assert_eq!(mux_state.received_get_header(), 1);
+    assert_eq!(other_state.received_get_header(), 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reload_config() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 3800;
+
+    let old_relay = generate_mock_relay(port + 1, signer.pubkey())?;
+    let new_relay = generate_mock_relay(port + 2, signer.pubkey())?;
+    let old_state = Arc::new(MockRelayState::new(chain, signer.clone(), 0));
+    let new_state = Arc::new(MockRelayState::new(chain, signer, 0));
+    tokio::spawn(start_mock_relay_service(old_state.clone(), port + 1));
+    tokio::spawn(start_mock_relay_service(new_state.clone(), port + 2));
+
+    let config = to_pbs_config(chain, get_pbs_static_config(port), vec![old_relay]);
+    let state = PbsState::new(config);
+    tokio::spawn(PbsService::run::<(), DefaultBuilderApi>(state.clone()));
+
+    // leave some time to start servers
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    info!("Reloading config with a new relay");
+    state.reload_config(to_pbs_config(chain, get_pbs_static_config(port), vec![new_relay]));
+    // snapshots taken before the reload keep the old relays
+    assert_eq!(state.relays()[0].id.as_str(), format!("mock_{}", port + 1));
+
+    let mock_validator = MockValidator::new(port)?;
+    info!("Sending get header");
+    let res = mock_validator.do_get_header().await;
+
+    assert!(res.is_ok());
+    assert_eq!(old_state.received_get_header(), 0);
+    assert_eq!(new_state.received_get_header(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_admin_api() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 3900;
+    let admin_port = port + 2;
+
+    let mock_relay = generate_mock_relay(port + 1, signer.pubkey())?;
+    let mock_state = Arc::new(MockRelayState::new(chain, signer, 0));
+    tokio::spawn(start_mock_relay_service(mock_state.clone(), port + 1));
+
+    let mut pbs_config = get_pbs_static_config(port);
+    pbs_config.admin_api = Some(AdminApiConfig {
+        host: "127.0.0.1".parse()?,
+        port: admin_port,
+        auth_token: Some("token".to_string()),
+    });
+    let config = to_pbs_config(chain, pbs_config, vec![mock_relay]);
+    let state = PbsState::new(config);
+    tokio::spawn(PbsService::run::<(), DefaultBuilderApi>(state));
+
+    // leave some time to start servers
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mock_validator = MockValidator::new(port)?;
+    info!("Sending get header");
+    mock_validator.do_get_header().await?;
+
+    let client = reqwest::Client::new();
+    let admin_url = |path: &str| format!("http://127.0.0.1:{admin_port}/admin/v1{path}");
+
+    let res = client.get(admin_url("/relays")).send().await?;
+    assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let relays: serde_json::Value =
+        client.get(admin_url("/relays")).bearer_auth("token").send().await?.json().await?;
+    assert_eq!(relays[0]["id"], format!("mock_{}", port + 1));
+    assert_eq!(relays[0]["circuit_state"], "closed");
+
+    let bids: serde_json::Value =
+        client.get(admin_url("/bids/0")).bearer_auth("token").send().await?.json().await?;
+    assert_eq!(bids.as_array().unwrap().len(), 1);
+
+    let counters: serde_json::Value =
+        client.get(admin_url("/counters")).bearer_auth("token").send().await?.json().await?;
+    assert_eq!(counters[0]["requests"], 1);
+    assert_eq!(counters[0]["successes"], 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_submit_block_to_bidders_only() -> Result<()> {
+    setup_test_env();
+    let bidder = Signer::new_random();
+    let other = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 4000;
+
+    let relays = vec![
+        generate_mock_relay(port + 1, bidder.pubkey())?,
+        generate_mock_relay(port + 2, other.pubkey())?,
+    ];
+    let bidder_state = Arc::new(MockRelayState::new(chain, bidder.clone(), 0));
+    let other_state = Arc::new(MockRelayState::new(chain, other, 0));
+    tokio::spawn(start_mock_relay_service(bidder_state.clone(), port + 1));
+    tokio::spawn(start_mock_relay_service(other_state.clone(), port + 2));
+
+    let mut pbs_config = get_pbs_static_config(port);
+    pbs_config.submit_block_to_bidders_only = true;
+    pbs_config.submit_block_fallback_to_all = false;
+    let config = to_pbs_config(chain, pbs_config, relays);
+    let state = PbsState::new(config);
+    tokio::spawn(PbsService::run::<(), DefaultBuilderApi>(state.clone()));
+
+    // leave some time to start servers
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mock_validator = MockValidator::new(port)?;
+    info!("Sending submit block with no cached bids");
+    assert!(mock_validator.do_submit_block().await.is_err());
+    assert_eq!(bidder_state.received_submit_block(), 0);
+    assert_eq!(other_state.received_submit_block(), 0);
+
+    // the mock validator submits the default block
+    let mut bid = SignedExecutionPayloadHeaderDeneb::default();
+    bid.message.pubkey = bidder.pubkey();
+    let params = GetHeaderParams {
+        slot: 0,
+        parent_hash: Default::default(),
+        pubkey: Default::default(),
+        builder_boost_factor: None,
+        local_block_value: None,
+    };
+    let relay_id = state.relays()[0].id.clone();
+    let bid =
+        RelayBid { relay_id, validator_pubkey: params.pubkey, bid: GetHeaderReponse::Deneb(bid) };
+    state.add_bids(&params, vec![bid]);
+
+    info!("Sending submit block with a cached bid");
+    mock_validator.do_submit_block().await?;
+    assert_eq!(bidder_state.received_submit_block(), 1);
+    assert_eq!(other_state.received_submit_block(), 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_submit_block_past_slot() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 4100;
+
+    let relays = vec![generate_mock_relay(port + 1, signer.pubkey())?];
+    let mock_state = Arc::new(MockRelayState::new(chain, signer, 0));
+    tokio::spawn(start_mock_relay_service(mock_state.clone(), port + 1));
+
+    let mut pbs_config = get_pbs_static_config(port);
+    pbs_config.skip_slot_checks = false;
+    let config = to_pbs_config(chain, pbs_config, relays);
+    let state = PbsState::new(config);
+    tokio::spawn(PbsService::run::<(), DefaultBuilderApi>(state));
+
+    // leave some time to start servers
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mock_validator = MockValidator::new(port)?;
+    info!("Sending submit block for slot 0");
+    assert!(mock_validator.do_submit_block().await.is_err());
+    assert_eq!(mock_state.received_submit_block(), 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_register_validators_only_changes() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 4200;
+
+    let relays = vec![generate_mock_relay(port + 1, signer.pubkey())?];
+    let mock_state = Arc::new(MockRelayState::new(chain, signer, 0));
+    tokio::spawn(start_mock_relay_service(mock_state.clone(), port + 1));
+
+    let config = to_pbs_config(chain, get_pbs_static_config(port), relays);
+    let state = PbsState::new(config);
+    tokio::spawn(PbsService::run::<(), DefaultBuilderApi>(state));
+
+    // leave some time to start servers
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let data = std::fs::read("data/registration_holesky.json")?;
+    let registrations: Vec<ValidatorRegistration> = serde_json::from_slice(&data)?;
+
+    let mock_validator = MockValidator::new(port)?;
+    info!("Sending new registrations");
+    mock_validator.do_register_custom_validators(registrations.clone()).await?;
+    assert_eq!(mock_state.received_register_validator(), 1);
+
+    info!("Sending the same registrations again");
+    mock_validator.do_register_custom_validators(registrations.clone()).await?;
+    assert_eq!(mock_state.received_register_validator(), 1);
+
+    info!("Sending a registration with an invalid signature");
+    let mut invalid = registrations[0].clone();
+    invalid.message.gas_limit += 1;
+    assert!(mock_validator.do_register_custom_validators(vec![invalid]).await.is_err());
+    assert_eq!(mock_state.received_register_validator(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_register_validators_relay_recovers() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 4730;
+
+    let relays = vec![generate_mock_relay(port + 1, signer.pubkey())?];
+    let mock_state =
+        Arc::new(MockRelayState::new(chain, signer, 0).with_register_validator_failures(1));
+    tokio::spawn(start_mock_relay_service(mock_state.clone(), port + 1));
+
+    let mut pbs_config = get_pbs_static_config(port);
+    pbs_config.registrations.batch_retries = 0;
+    let config = to_pbs_config(chain, pbs_config, relays);
+    let state = PbsState::new(config);
+    tokio::spawn(PbsService::run::<(), DefaultBuilderApi>(state));
+
+    // leave some time to start servers
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let data = std::fs::read("data/registration_holesky.json")?;
+    let registrations: Vec<ValidatorRegistration> = serde_json::from_slice(&data)?;
+
+    let mock_validator = MockValidator::new(port)?;
+    info!("Sending registrations to a failing relay");
+    assert!(mock_validator.do_register_custom_validators(registrations.clone()).await.is_err());
+    assert_eq!(mock_state.received_register_validator(), 1);
+
+    info!("Retrying once the relay recovered");
+    mock_validator.do_register_custom_validators(registrations.clone()).await?;
+    assert_eq!(mock_state.received_register_validator(), 2);
+
+    info!("Sending the same registrations again");
+    mock_validator.do_register_custom_validators(registrations).await?;
+    assert_eq!(mock_state.received_register_validator(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_register_validators_batches() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 4300;
+
+    let relays = vec![generate_mock_relay(port + 1, signer.pubkey())?];
+    let mock_state =
+        Arc::new(MockRelayState::new(chain, signer, 0).with_register_validator_failures(1));
+    tokio::spawn(start_mock_relay_service(mock_state.clone(), port + 1));
+
+    let mut pbs_config = get_pbs_static_config(port);
+    pbs_config.registrations.batch_size = 5;
+    pbs_config.registrations.max_concurrent_batches = 1;
+    let config = to_pbs_config(chain, pbs_config, relays);
+    let state = PbsState::new(config);
+    tokio::spawn(PbsService::run::<(), DefaultBuilderApi>(state));
+
+    // leave some time to start servers
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let data = std::fs::read("data/registration_holesky.json")?;
+    let registrations: Vec<ValidatorRegistration> = serde_json::from_slice(&data)?;
+
+    let mock_validator = MockValidator::new(port)?;
+    info!("Sending registrations in batches");
+    mock_validator.do_register_custom_validators(registrations).await?;
+
+    // 17 registrations in 4 batches, the first one is retried once
+    assert_eq!(mock_state.received_register_validator(), 5);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_startup_check_min_passing_relays() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 4400;
+
+    // no relay is listening on this port
+    let relays = vec![generate_mock_relay(port + 1, signer.pubkey())?];
+
+    let mut pbs_config = get_pbs_static_config(port);
+    pbs_config.startup_check =
+        StartupCheckConfig { enabled: true, timeout_ms: 500, min_passing_relays: 1 };
+    let config = to_pbs_config(chain, pbs_config, relays);
+    let state = PbsState::new(config);
+
+    let res = PbsService::run::<(), DefaultBuilderApi>(state).await;
+    assert!(res.unwrap_err().to_string().contains("0 of 1 relays passed"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_status_through_proxy() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 4500;
+
+    // the relay host doesn't resolve, so requests only reach the mock relay
+    // through the proxy. The mock relay serves proxied requests as its own
+    let entry = RelayEntry {
+        id: "proxied".to_string(),
+        pubkey: signer.pubkey(),
+        url: "http://relay.invalid".to_string(),
+    };
+    let relay_config = RelayConfig {
+        entry,
+        proxy: Some(format!("http://127.0.0.1:{}", port + 1).parse()?),
+        local_address: Some(Ipv4Addr::LOCALHOST.into()),
+        ..RelayConfig::default()
+    };
+    let relays = vec![RelayClient::new(relay_config)?];
+    let mock_state = Arc::new(MockRelayState::new(chain, signer, 0));
+    tokio::spawn(start_mock_relay_service(mock_state.clone(), port + 1));
+
+    let config = to_pbs_config(chain, get_pbs_static_config(port), relays);
+    let state = PbsState::new(config);
+    tokio::spawn(PbsService::run::<(), DefaultBuilderApi>(state));
+
+    // leave some time to start servers
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mock_validator = MockValidator::new(port)?;
+    let res = mock_validator.do_get_status().await;
+
+    assert!(res.is_ok());
+    assert_eq!(mock_state.received_get_status(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_beacon_events_update_slot() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 4600;
+
+    let relays = vec![generate_mock_relay(port + 1, signer.pubkey())?];
+    let listener = TcpListener::bind(("0.0.0.0", port + 2)).await?;
+    tokio::spawn(async move { axum::serve(listener, mock_beacon_events_router(100)).await });
+
+    let mut config = to_pbs_config(chain, get_pbs_static_config(port), relays);
+    config.beacon_client =
+        Some(BeaconClient::new(format!("http://0.0.0.0:{}", port + 2).parse()?)?);
+    let state = PbsState::new(config);
+    tokio::spawn(PbsService::run::<(), DefaultBuilderApi>(state.clone()));
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // the payload attributes event moves the state to the proposal slot
+    assert_eq!(state.head().map(|head| head.slot), Some(100));
+    assert_eq!(state.get_slot_and_uuid().0, 101);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_header_relay_allowlist() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+    let allowed = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 4700;
+
+    let relays = vec![
+        generate_mock_relay(port + 1, signer.pubkey())?,
+        generate_mock_relay(port + 2, allowed.pubkey())?,
+    ];
+    let mock_state = Arc::new(MockRelayState::new(chain, signer, 0));
+    let allowed_state = Arc::new(MockRelayState::new(chain, allowed.clone(), 0));
+    tokio::spawn(start_mock_relay_service(mock_state.clone(), port + 1));
+    tokio::spawn(start_mock_relay_service(allowed_state.clone(), port + 2));
+
+    let mut pbs_config = get_pbs_static_config(port);
+    pbs_config.bid_selection.relay_allowlist = vec![format!("mock_{}", port + 2)];
+    let config = to_pbs_config(chain, pbs_config, relays);
+    let state = PbsState::new(config);
+    tokio::spawn(PbsService::run::<(), DefaultBuilderApi>(state.clone()));
+
+    // leave some time to start servers
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mock_validator = MockValidator::new(port)?;
+    info!("Sending get header");
+    mock_validator.do_get_header().await?;
+    assert_eq!(mock_state.received_get_header(), 1);
+    assert_eq!(allowed_state.received_get_header(), 1);
+
+    // only the bid of the allowed relay is kept
+    let bids = state.get_bids(0);
+    assert_eq!(bids.len(), 1);
+    assert_eq!(bids[0].pubkey(), allowed.pubkey());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_relay_extra_urls() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 4710;
+
+    // nothing listens on the entry url, only the extra url reaches the relay
+    let mock_relay = generate_mock_relay(port + 1, signer.pubkey())?;
+    let relay_config = RelayConfig {
+        extra_urls: vec![get_local_address(port + 2).parse()?],
+        ..(*mock_relay.config).clone()
+    };
+    let relays = vec![RelayClient::new(relay_config)?];
+    let mock_state = Arc::new(MockRelayState::new(chain, signer, 0));
+    tokio::spawn(start_mock_relay_service(mock_state.clone(), port + 2));
+
+    let config = to_pbs_config(chain, get_pbs_static_config(port), relays);
+    let state = PbsState::new(config);
+    tokio::spawn(PbsService::run::<(), DefaultBuilderApi>(state));
+
+    // leave some time to start servers
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mock_validator = MockValidator::new(port)?;
+    info!("Sending get header");
+    mock_validator.do_get_header().await?;
+    assert_eq!(mock_state.received_get_header(), 1);
+
+    info!("Sending submit block");
+    mock_validator.do_submit_block().await?;
+    assert_eq!(mock_state.received_submit_block(), 1);
+
+    info!("Sending get status");
+    mock_validator.do_get_status().await?;
+    assert_eq!(mock_state.received_get_status(), 1);
+
+    info!("Sending register validator");
+    mock_validator.do_register_validator().await?;
+    assert_eq!(mock_state.received_register_validator(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_header_single_flight() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 4720;
+
+    // slow enough that both requests are in flight at the same time
+    let mock_relay = generate_mock_relay(port + 1, signer.pubkey())?;
+    let mock_state = Arc::new(MockRelayState::new(chain, signer, 200));
+    tokio::spawn(start_mock_relay_service(mock_state.clone(), port + 1));
+
+    let config = to_pbs_config(chain, get_pbs_static_config(port), vec![mock_relay]);
+    let state = PbsState::new(config);
+    tokio::spawn(PbsService::run::<(), DefaultBuilderApi>(state.clone()));
+
+    // leave some time to start servers
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let first = MockValidator::new(port)?;
+    let second = MockValidator::new(port)?;
+    info!("Sending the same get header twice");
+    let (res_first, res_second) = tokio::join!(first.do_get_header(), second.do_get_header());
+    res_first?;
+    res_second?;
+
+    assert_eq!(mock_state.received_get_header(), 1);
+    assert_eq!(state.get_bids(0).len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_header_single_flight_local_value() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 4740;
+
+    // slow enough that both requests are in flight at the same time
+    let mock_relay = generate_mock_relay(port + 1, signer.pubkey())?;
+    let mock_state = Arc::new(MockRelayState::new(chain, signer, 200));
+    tokio::spawn(start_mock_relay_service(mock_state.clone(), port + 1));
+
+    let config = to_pbs_config(chain, get_pbs_static_config(port), vec![mock_relay]);
+    let state = PbsState::new(config);
+    tokio::spawn(PbsService::run::<(), DefaultBuilderApi>(state.clone()));
+
+    // leave some time to start servers
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let first = MockValidator::new(port)?;
+    let second = MockValidator::new(port)?;
+    let low = GetHeaderQuery { local_block_value: Some(U256::from(1)), ..Default::default() };
+    let high = GetHeaderQuery { local_block_value: Some(U256::MAX), ..Default::default() };
+    info!("Sending get header with different local block values");
+    let (res_first, res_second) =
+        tokio::join!(first.do_get_header_with_query(low), second.do_get_header_with_query(high));
+    res_first?;
+    res_second?;
+
+    // each request decides between the builder and local block on its own
+    assert_eq!(mock_state.received_get_header(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_submit_block_after_shutdown_signal() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 4750;
+
+    let relays = vec![generate_mock_relay(port + 1, signer.pubkey())?];
+    let mock_state = Arc::new(MockRelayState::new(chain, signer, 0));
+    tokio::spawn(start_mock_relay_service(mock_state.clone(), port + 1));
+
+    let config = to_pbs_config(chain, get_pbs_static_config(port), relays);
+    let state = PbsState::new(config);
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let shutdown = async move {
+        let _ = shutdown_rx.await;
+    };
+    tokio::spawn(PbsService::run_with_shutdown::<(), DefaultBuilderApi, _>(state, shutdown));
+
+    // leave some time to start servers
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mock_validator = MockValidator::new(port)?;
+    info!("Sending get header before the shutdown signal");
+    assert_eq!(mock_validator.do_get_header_status().await?, StatusCode::OK);
+
+    let _ = shutdown_tx.send(());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    info!("Sending get header and submit block after the shutdown signal");
+    assert_eq!(mock_validator.do_get_header_status().await?, StatusCode::NO_CONTENT);
+    assert!(mock_validator.do_submit_block().await.is_ok());
+    assert_eq!(mock_state.received_get_header(), 1);
+    assert_eq!(mock_state.received_submit_block(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_submit_block_unknown_proposer() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 4760;
+
+    let relays = vec![generate_mock_relay(port + 1, signer.pubkey())?];
+    let mock_state = Arc::new(MockRelayState::new(chain, signer, 0));
+    tokio::spawn(start_mock_relay_service(mock_state.clone(), port + 1));
+
+    // beacon node that doesn't know any validator
+    let listener = TcpListener::bind(("0.0.0.0", port + 2)).await?;
+    tokio::spawn(async move { axum::serve(listener, axum::Router::new()).await });
+
+    let mut pbs_config = get_pbs_static_config(port);
+    pbs_config.skip_proposer_sigverify = false;
+    pbs_config.beacon_events = false;
+    let mut config = to_pbs_config(chain, pbs_config, relays);
+    config.beacon_client =
+        Some(BeaconClient::new(format!("http://0.0.0.0:{}", port + 2).parse()?)?);
+    let state = PbsState::new(config);
+    tokio::spawn(PbsService::run::<(), DefaultBuilderApi>(state));
+
+    // leave some time to start servers
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mock_validator = MockValidator::new(port)?;
+    info!("Sending get header and submit block for an unknown proposer");
+    mock_validator.do_get_header().await?;
+    assert!(mock_validator.do_submit_block().await.is_err());
+    assert_eq!(mock_state.received_submit_block(), 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_header_different_parents() -> Result<()> {
+    setup_test_env();
+    let signer = Signer::new_random();
+
+    let chain = Chain::Holesky;
+    let port = 4770;
+
+    let mock_relay = generate_mock_relay(port + 1, signer.pubkey())?;
+    let mock_state = Arc::new(MockRelayState::new(chain, signer, 0));
+    tokio::spawn(start_mock_relay_service(mock_state.clone(), port + 1));
+
+    let config = to_pbs_config(chain, get_pbs_static_config(port), vec![mock_relay]);
+    let state = PbsState::new(config);
+    tokio::spawn(PbsService::run::<(), DefaultBuilderApi>(state.clone()));
+
+    // leave some time to start servers
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mock_validator = MockValidator::new(port)?;
+    let first_parent = B256::repeat_byte(1);
+    let second_parent = B256::repeat_byte(2);
+    info!("Sending get header on two parents in the same slot");
+    let first = mock_validator.do_get_header_for_parent(first_parent).await?;
+    let second = mock_validator.do_get_header_for_parent(second_parent).await?;
+
+    // both bids are cached for the slot, but each request gets its own
+    assert_eq!(state.get_bids(0).len(), 2);
+    assert_eq!(first.parent_hash(), first_parent);
+    assert_eq!(second.parent_hash(), second_parent);
+    Ok(())
+}